@@ -2,7 +2,7 @@ use std::{fs::File, io::{BufReader, Read, Seek}};
 
 use mp4kit::{Error, Mp4};
 
-fn parse_mp4<'a, T: Read + Seek>(reader: &'a mut T) -> Result<(), Error> {
+fn parse_mp4<T: Read + Seek>(reader: &mut T) -> Result<(), Error> {
     let mp4 = Mp4::parse(reader)?;
     println!("Video: {mp4:?}");
 
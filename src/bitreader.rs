@@ -0,0 +1,132 @@
+use crate::Error;
+
+// Big endian bit reader over a byte slice, most significant bit first
+#[derive(Clone, Debug)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize, // In bits
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn bits_left(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.position)
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, Error> {
+        if self.position >= self.data.len() * 8 {
+            return Err(Error::EOF());
+        }
+        let byte = self.data[self.position / 8];
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit == 1)
+    }
+
+    pub fn read_bits(&mut self, count: u8) -> Result<u32, Error> {
+        if count > 32 {
+            return Err(Error::InvalidData(format!("BitReader: cannot read {:?} bits", count)));
+        }
+        Ok(self.read_bits_u64(count)? as u32)
+    }
+
+    pub fn read_bits_u64(&mut self, count: u8) -> Result<u64, Error> {
+        if count > 64 {
+            return Err(Error::InvalidData(format!("BitReader: cannot read {:?} bits", count)));
+        }
+        if count as usize > self.bits_left() {
+            return Err(Error::EOF());
+        }
+        let mut value: u64 = 0;
+        for _ in 0..count {
+            value = value << 1 | self.read_bit()? as u64;
+        }
+        Ok(value)
+    }
+
+    pub fn read_u8(&mut self, count: u8) -> Result<u8, Error> {
+        Ok(self.read_bits(count.min(8))? as u8)
+    }
+
+    // Unsigned exp-Golomb code, ue(v)
+    pub fn read_ue(&mut self) -> Result<u32, Error> {
+        let mut leading_zeros: u8 = 0;
+        while !self.read_bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return Err(Error::InvalidData("BitReader: exp-Golomb code is too long".to_owned()));
+            }
+        }
+        Ok((((1u64 << leading_zeros) - 1) + self.read_bits_u64(leading_zeros)?) as u32)
+    }
+
+    // Signed exp-Golomb code, se(v)
+    pub fn read_se(&mut self) -> Result<i32, Error> {
+        let value = self.read_ue()? as i64;
+        Ok(match value & 1 {
+            1 => (value + 1) / 2,
+            _ => -(value / 2),
+        } as i32)
+    }
+
+    // ue(v) with the largest value the syntax element allows
+    pub fn read_ue_max(&mut self, name: &str, max: u32) -> Result<u32, Error> {
+        let value = self.read_ue()?;
+        if value > max {
+            return Err(Error::InvalidData(format!("{} out of range {:?}", name, value)));
+        }
+        Ok(value)
+    }
+
+    // se(v) within the range the syntax element allows
+    pub fn read_se_range(&mut self, name: &str, min: i32, max: i32) -> Result<i32, Error> {
+        let value = self.read_se()?;
+        if value < min || value > max {
+            return Err(Error::InvalidData(format!("{} out of range {:?}", name, value)));
+        }
+        Ok(value)
+    }
+
+    // True while data remains before the rbsp_stop_one_bit
+    pub fn more_rbsp_data(&self) -> bool {
+        let Some(last) = self.data.iter().rposition(|byte| *byte != 0) else {
+            return false;
+        };
+        let stop_bit = last * 8 + 7 - self.data[last].trailing_zeros() as usize;
+        self.position < stop_bit
+    }
+
+    pub fn skip_bits(&mut self, count: usize) -> Result<(), Error> {
+        if count > self.bits_left() {
+            return Err(Error::EOF());
+        }
+        self.position += count;
+        Ok(())
+    }
+
+    pub fn byte_align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+// Remove the emulation prevention bytes of a NAL unit: 0x000003 becomes 0x0000
+pub fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
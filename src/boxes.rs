@@ -1,20 +1,47 @@
+pub mod ac3;
+pub mod alac;
+pub mod av1c;
+pub mod avcc;
+pub mod chpl;
 pub mod dinf;
 pub mod dref;
 pub mod edts;
 pub mod elst;
+pub mod esds;
+pub mod flac;
 pub mod ftyp;
 pub mod hdlr;
+pub mod hvcc;
+pub mod ilst;
 pub mod mdat;
 pub mod mdhd;
 pub mod mdia;
+pub mod mehd;
+pub mod meta;
+pub mod mfhd;
 pub mod minf;
+pub mod moof;
 pub mod moov;
+pub mod mp4a;
+pub mod mvex;
 pub mod mvhd;
+pub mod nmhd;
+pub mod opus;
+pub mod pssh;
+pub mod sinf;
 pub mod smhd;
+pub mod tenc;
+pub mod tfdt;
+pub mod tfhd;
 pub mod tkhd;
+pub mod traf;
 pub mod trak;
+pub mod tref;
+pub mod trex;
+pub mod trun;
 pub mod udta;
 pub mod vmhd;
+pub mod vpcc;
 pub mod wide;
 
 pub mod co64;
@@ -27,27 +54,64 @@ pub mod stss;
 pub mod stsz;
 pub mod stts;
 
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{box_types, BoxParser, BoxReader, Error, Parser, Reader};
+use crate::{box_types, BoxParser, BoxReader, BoxWriter, Error, Parser, Reader, Writer};
 
+pub use ac3::{Ac3SampleEntry, Ac3SpecificBox, Ec3SampleEntry, Ec3SpecificBox, Ec3Substream};
+pub use alac::{AlacSampleEntry, AlacSpecificBox};
+pub use av1c::{
+    Av1CodecConfigurationRecord, Av1ColorConfig, Av1OperatingPoint, Av1SampleEntry,
+    Av1SequenceHeader, Av1TimingInfo,
+};
+pub use avcc::{AvcDecoderConfigurationRecord, AvcHighProfileExtension, AvcSampleEntry};
+pub use chpl::{ChapterListBox, ChapterListEntry, CHPL_TIMESCALE};
 pub use dinf::DataInfoBox;
 pub use dref::DataReferenceBox;
 pub use edts::EditBox;
 pub use elst::EditListBox;
+pub use esds::{DecoderConfigDescriptor, EsDescriptor, EsdsBox};
+pub use flac::{FlacMetadataBlock, FlacSampleEntry, FlacSpecificBox, FlacStreamInfo};
 pub use ftyp::FtypBox;
 pub use hdlr::HandlerBox;
+pub use hvcc::{HevcDecoderConfigurationRecord, HevcNalArray, HevcSampleEntry};
+pub use ilst::{
+    CoverArt, DataBox, ImageFormat, ItemListBox, MetadataItem, MetadataKey, MetadataValue,
+};
 pub use mdat::MediaDataBox;
 pub use mdhd::MediaHeaderBox;
 pub use mdia::MediaBox;
+pub use mehd::MovieExtendsHeaderBox;
+pub use meta::MetaBox;
+pub use mfhd::MovieFragmentHeaderBox;
 pub use minf::MediaInfoBox;
+pub use moof::MovieFragmentBox;
 pub use moov::MoovBox;
+pub use mp4a::{AudioSpecificConfig, Mp4aSampleEntry};
+pub use mvex::MovieExtendsBox;
 pub use mvhd::MvhdBox;
+pub use nmhd::NullMediaHeaderBox;
+pub use opus::{OpusChannelMapping, OpusSampleEntry, OpusSpecificBox};
+pub use pssh::{
+    KeyId, ProtectionSystemHeaderBox, COMMON_SYSTEM_ID, FAIRPLAY_SYSTEM_ID, PLAYREADY_SYSTEM_ID,
+    WIDEVINE_SYSTEM_ID,
+};
+pub use sinf::{
+    ProtectedSampleEntry, ProtectionSchemeInfoBox, SchemeInformationBox, SchemeTypeBox,
+};
 pub use smhd::SoundInfoBox;
+pub use tenc::TrackEncryptionBox;
+pub use tfdt::TrackFragmentDecodeTimeBox;
+pub use tfhd::TrackFragmentHeaderBox;
 pub use tkhd::TrackHeaderBox;
-pub use trak::TrackBox;
+pub use traf::TrackFragmentBox;
+pub use trak::{Track, TrackBox};
+pub use tref::{TrackReference, TrackReferenceBox};
+pub use trex::TrackExtendsBox;
+pub use trun::{TrackRunBox, TrackRunEntry};
 pub use udta::UserDataBox;
 pub use vmhd::VideoInfoBox;
+pub use vpcc::{VpCodecConfigurationRecord, VpSampleEntry};
 pub use wide::WideBox;
 
 pub use co64::ChunkOffset64Box;
@@ -55,7 +119,10 @@ pub use ctts::CompositionOffsetBox;
 pub use stbl::SampleTableBox;
 pub use stco::ChunkOffsetBox;
 pub use stsc::SampleToChunkBox;
-pub use stsd::VideoSampleDescriptionBox;
+pub use stsd::{
+    AudioCodec, AudioSampleEntry, SampleDescriptionBox, SampleEntry, VideoCodec,
+    VisualSampleEntry,
+};
 pub use stss::SyncSampleBox;
 pub use stsz::SampleSizeBox;
 pub use stts::TimeToSampleBox;
@@ -143,7 +210,6 @@ impl Reader for ListBox {
         let mut children: Vec<BoxElement> = Vec::new();
         let mut iter = Self::iter(header);
         while let Some(child) = iter.next(reader)? {
-            println!("{:?}: {:?}", header.name, child);
             children.push(child);
         }
         Ok(Self { children })
@@ -157,7 +223,7 @@ pub struct ListBoxIterator {
 }
 
 impl ListBoxIterator {
-    pub fn next<'a, T: Read + Seek>(
+    pub fn next<T: Read + Seek>(
         &mut self,
         reader: &mut BoxReader<T>,
     ) -> Result<Option<BoxElement>, Error> {
@@ -190,6 +256,112 @@ impl Reader for SkipBox {
     }
 }
 
+// Box kept as raw bytes so that it can be written back unchanged
+#[derive(Clone, Debug)]
+pub struct RawBox {
+    pub header: BoxHeader,
+    pub data: Vec<u8>,
+}
+
+impl Reader for RawBox {
+    fn read<'a, T: Read + Seek>(
+        reader: &mut BoxReader<T>,
+        header: BoxHeader,
+    ) -> Result<Self, Error> {
+        let data = reader.read_bytes((header.size - HEADER_LENGTH) as usize)?;
+        Ok(Self { header, data })
+    }
+}
+
+impl Writer for RawBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.header.name, |writer| writer.write_bytes(&self.data))
+    }
+}
+
+// Read the child boxes of a box after its fixed fields of offset bytes, such as a sample
+// entry or a full box. Children not consumed by read_child are kept as raw boxes.
+pub(crate) fn read_children<T, F>(
+    reader: &mut BoxReader<T>,
+    header: BoxHeader,
+    offset: u64,
+    mut read_child: F,
+) -> Result<Vec<RawBox>, Error>
+where
+    T: Read + Seek,
+    F: FnMut(&mut BoxReader<T>, BoxHeader) -> Result<bool, Error>,
+{
+    let mut extensions: Vec<RawBox> = Vec::new();
+    let mut content_parsed_size = HEADER_LENGTH + offset;
+    while content_parsed_size + HEADER_LENGTH <= header.size {
+        let child_header = BoxHeader::read(reader)?;
+        // A zero size child would never advance, an oversized one would read past the parent
+        let remaining = header.size - content_parsed_size;
+        if child_header.size < HEADER_LENGTH || child_header.size > remaining {
+            return Err(Error::InvalidData(format!(
+                "{:?}: invalid child box size {:?}",
+                header.name, child_header.size
+            )));
+        }
+        if !read_child(reader, child_header)? {
+            extensions.push(RawBox::read(reader, child_header)?);
+        }
+        content_parsed_size += child_header.size;
+    }
+    // Skip trailing bytes too short for a box, such as a 32 bit terminator
+    if content_parsed_size < header.size {
+        reader.skip(header.size - content_parsed_size)?;
+    }
+    Ok(extensions)
+}
+
+// Writer usable as a trait object, so that the typed children of a container can be listed
+pub(crate) trait ChildWriter {
+    fn write_child(&self, writer: &mut BoxWriter<dyn Write>) -> Result<u64, Error>;
+}
+
+impl<B: Writer> ChildWriter for B {
+    fn write_child(&self, writer: &mut BoxWriter<dyn Write>) -> Result<u64, Error> {
+        self.write(writer)
+    }
+}
+
+// Write the children of a container in the order their types were read. Typed children,
+// given in their default order, and extensions are taken in turn for each type. Children
+// missing from the order, such as the ones of a new box, follow in their default order.
+pub(crate) fn write_children(
+    writer: &mut BoxWriter<dyn Write>,
+    order: &[BoxType],
+    children: Vec<(BoxType, &dyn ChildWriter)>,
+    extensions: &[RawBox],
+) -> Result<(), Error> {
+    let mut children: Vec<Option<(BoxType, &dyn ChildWriter)>> =
+        children.into_iter().map(Some).collect();
+    let mut extensions: Vec<Option<&RawBox>> = extensions.iter().map(Some).collect();
+    for &name in order {
+        let child = children
+            .iter_mut()
+            .find(|child| matches!(child, Some((kind, _)) if *kind == name));
+        if let Some((_, child)) = child.and_then(Option::take) {
+            child.write_child(writer)?;
+            continue;
+        }
+        let extension = extensions
+            .iter_mut()
+            .find(|extension| matches!(extension, Some(raw) if raw.header.name == name));
+        if let Some(extension) = extension.and_then(Option::take) {
+            extension.write(writer)?;
+        }
+    }
+    for (_, child) in children.into_iter().flatten() {
+        child.write_child(writer)?;
+    }
+    for extension in extensions.into_iter().flatten() {
+        extension.write(writer)?;
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub enum BoxContent {
     Ftyp(FtypBox),
@@ -200,6 +372,7 @@ pub enum BoxContent {
     Udta(UserDataBox),
     Wide(WideBox),
     Tkhd(TrackHeaderBox),
+    Tref(TrackReferenceBox),
     Edts(EditBox),
     // Elst(EditListBox), // Elst is only present in Edts
     Mdia(MediaBox),
@@ -208,10 +381,11 @@ pub enum BoxContent {
     Minf(MediaInfoBox),
     Vmhd(VideoInfoBox),
     Smhd(SoundInfoBox),
+    Nmhd(NullMediaHeaderBox),
     Dinf(DataInfoBox),
     // Dref(DataReferenceBox), // Dref is only present in Dinf
     Stbl(SampleTableBox),
-    Stsd(VideoSampleDescriptionBox),
+    Stsd(SampleDescriptionBox),
     Stts(TimeToSampleBox),
     Stsc(SampleToChunkBox),
     Stsz(SampleSizeBox),
@@ -219,6 +393,16 @@ pub enum BoxContent {
     Stco(ChunkOffsetBox),
     Co64(ChunkOffset64Box),
     Ctts(CompositionOffsetBox),
+    Mvex(MovieExtendsBox),
+    Mehd(MovieExtendsHeaderBox),
+    Trex(TrackExtendsBox),
+    Moof(MovieFragmentBox),
+    Mfhd(MovieFragmentHeaderBox),
+    Traf(TrackFragmentBox),
+    Tfhd(TrackFragmentHeaderBox),
+    Tfdt(TrackFragmentDecodeTimeBox),
+    Trun(TrackRunBox),
+    Pssh(ProtectionSystemHeaderBox),
 
     Unknown(SkipBox),
 }
@@ -237,6 +421,9 @@ impl Reader for BoxContent {
             BoxType::UserData => BoxContent::Udta(UserDataBox::read(reader, header)?),
             BoxType::Wide => BoxContent::Wide(WideBox::read(reader, header)?),
             BoxType::TrackHeader => BoxContent::Tkhd(TrackHeaderBox::read(reader, header)?),
+            BoxType::TrackReference => {
+                BoxContent::Tref(TrackReferenceBox::read(reader, header)?)
+            }
             BoxType::Edit => BoxContent::Edts(EditBox::read(reader, header)?),
             BoxType::Media => BoxContent::Mdia(MediaBox::read(reader, header)?),
             BoxType::MediaHeader => BoxContent::Mdhd(MediaHeaderBox::read(reader, header)?),
@@ -244,10 +431,13 @@ impl Reader for BoxContent {
             BoxType::MediaInfo => BoxContent::Minf(MediaInfoBox::read(reader, header)?),
             BoxType::VideoInfo => BoxContent::Vmhd(VideoInfoBox::read(reader, header)?),
             BoxType::SoundInfo => BoxContent::Smhd(SoundInfoBox::read(reader, header)?),
+            BoxType::NullMediaHeader => {
+                BoxContent::Nmhd(NullMediaHeaderBox::read(reader, header)?)
+            }
             BoxType::DataInfo => BoxContent::Dinf(DataInfoBox::read(reader, header)?),
             BoxType::SampleTable => BoxContent::Stbl(SampleTableBox::read(reader, header)?),
-            BoxType::VideoSampleDescription => {
-                BoxContent::Stsd(VideoSampleDescriptionBox::read(reader, header)?)
+            BoxType::SampleDescription => {
+                BoxContent::Stsd(SampleDescriptionBox::read(reader, header)?)
             }
             BoxType::TimeToSample => BoxContent::Stts(TimeToSampleBox::read(reader, header)?),
             BoxType::SampleToChunk => BoxContent::Stsc(SampleToChunkBox::read(reader, header)?),
@@ -258,6 +448,26 @@ impl Reader for BoxContent {
             BoxType::CompositionOffset => {
                 BoxContent::Ctts(CompositionOffsetBox::read(reader, header)?)
             }
+            BoxType::MovieExtends => BoxContent::Mvex(MovieExtendsBox::read(reader, header)?),
+            BoxType::MovieExtendsHeader => {
+                BoxContent::Mehd(MovieExtendsHeaderBox::read(reader, header)?)
+            }
+            BoxType::TrackExtends => BoxContent::Trex(TrackExtendsBox::read(reader, header)?),
+            BoxType::MovieFragment => BoxContent::Moof(MovieFragmentBox::read(reader, header)?),
+            BoxType::MovieFragmentHeader => {
+                BoxContent::Mfhd(MovieFragmentHeaderBox::read(reader, header)?)
+            }
+            BoxType::TrackFragment => BoxContent::Traf(TrackFragmentBox::read(reader, header)?),
+            BoxType::TrackFragmentHeader => {
+                BoxContent::Tfhd(TrackFragmentHeaderBox::read(reader, header)?)
+            }
+            BoxType::TrackFragmentDecodeTime => {
+                BoxContent::Tfdt(TrackFragmentDecodeTimeBox::read(reader, header)?)
+            }
+            BoxType::TrackRun => BoxContent::Trun(TrackRunBox::read(reader, header)?),
+            BoxType::ProtectionSystemHeader => {
+                BoxContent::Pssh(ProtectionSystemHeaderBox::read(reader, header)?)
+            }
             _ => BoxContent::Unknown(SkipBox::read(reader, header)?),
         };
         Ok(result)
@@ -301,7 +511,7 @@ box_types!(
     DataRef     0x64726566u32,  // "dref"
     UrlRef      0x75726c20u32,  // "url "
     SampleTable 0x7374626cu32,  // "stbl"
-    VideoSampleDescription 0x73747364u32, // "stsd"
+    SampleDescription 0x73747364u32, // "stsd"
     TimeToSample 0x73747473u32, // "stts"
     SampleToChunk 0x73747363u32,// "stsc"
     SampleSize  0x7374737Au32,  // "stsz"
@@ -309,4 +519,122 @@ box_types!(
     ChunkOffset 0x7374636Fu32,  // "stco"
     ChunkOffset64 0x636F3634,   // "co64"
     CompositionOffset 0x63747473, // "ctts"
+    MovieExtends 0x6d766578u32, // "mvex"
+    MovieExtendsHeader 0x6d656864u32, // "mehd"
+    TrackExtends 0x74726578u32, // "trex"
+    MovieFragment 0x6d6f6f66u32, // "moof"
+    MovieFragmentHeader 0x6d666864u32, // "mfhd"
+    TrackFragment 0x74726166u32, // "traf"
+    TrackFragmentHeader 0x74666864u32, // "tfhd"
+    TrackFragmentDecodeTime 0x74666474u32, // "tfdt"
+    TrackRun    0x7472756eu32,  // "trun"
+    Avc1        0x61766331u32,  // "avc1"
+    Avc3        0x61766333u32,  // "avc3"
+    AvcConfiguration 0x61766343u32, // "avcC"
+    Hvc1        0x68766331u32,  // "hvc1"
+    Hev1        0x68657631u32,  // "hev1"
+    HevcConfiguration 0x68766343u32, // "hvcC"
+    Av01        0x61763031u32,  // "av01"
+    Av1Configuration 0x61763143u32, // "av1C"
+    Vp08        0x76703038u32,  // "vp08"
+    Vp09        0x76703039u32,  // "vp09"
+    VpConfiguration 0x76706343u32, // "vpcC"
+    Mp4a        0x6d703461u32,  // "mp4a"
+    ElementaryStreamDescriptor 0x65736473u32, // "esds"
+    Opus        0x4f707573u32,  // "Opus"
+    OpusSpecific 0x644f7073u32, // "dOps"
+    Flac        0x664c6143u32,  // "fLaC"
+    FlacSpecific 0x64664c61u32, // "dfLa"
+    Ac3         0x61632d33u32,  // "ac-3"
+    Ac3Specific 0x64616333u32,  // "dac3"
+    Ec3         0x65632d33u32,  // "ec-3"
+    Ec3Specific 0x64656333u32,  // "dec3"
+    Alac        0x616c6163u32,  // "alac"
+    Meta        0x6d657461u32,  // "meta"
+    ItemList    0x696c7374u32,  // "ilst"
+    Data        0x64617461u32,  // "data"
+    Mean        0x6d65616eu32,  // "mean"
+    Name        0x6e616d65u32,  // "name"
+    Freeform    0x2d2d2d2du32,  // "----"
+    Free        0x66726565u32,  // "free"
+    Skip        0x736b6970u32,  // "skip"
+    TrackReference 0x74726566u32, // "tref"
+    Hint        0x68696e74u32,  // "hint"
+    ContentDescribes 0x63647363u32, // "cdsc"
+    Chapter     0x63686170u32,  // "chap"
+    Synchronization 0x73796e63u32, // "sync"
+    VideoDepth  0x76646570u32,  // "vdep"
+    Subtitle    0x73756274u32,  // "subt"
+    Font        0x666f6e74u32,  // "font"
+    Timecode    0x746d6364u32,  // "tmcd"
+    HintDependency 0x68696e64u32, // "hind"
+    Scalable    0x7363616cu32,  // "scal"
+    ChapterList 0x6368706cu32,  // "chpl"
+    NullMediaHeader 0x6e6d6864u32, // "nmhd"
+    Tx3g        0x74783367u32,  // "tx3g"
+    FontTable   0x66746162u32,  // "ftab"
+    ProtectionSystemHeader 0x70737368u32, // "pssh"
+    ProtectionSchemeInfo 0x73696e66u32, // "sinf"
+    OriginalFormat 0x66726d61u32, // "frma"
+    SchemeType  0x7363686du32,  // "schm"
+    SchemeInformation 0x73636869u32, // "schi"
+    TrackEncryption 0x74656e63u32, // "tenc"
+    Encv        0x656e6376u32,  // "encv"
+    Enca        0x656e6361u32,  // "enca"
 );
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::stsd::AUDIO_SAMPLE_ENTRY_LENGTH;
+
+    // mp4a sample entry with the given bytes after its 28 bytes of fixed fields
+    fn audio_entry(children: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(36 + children.len() as u32).to_be_bytes());
+        data.extend_from_slice(b"mp4a");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&[0, 2, 0, 16, 0, 0, 0, 0, 0xAC, 0x44, 0, 0]);
+        data.extend_from_slice(children);
+        data
+    }
+
+    fn read_entry_children(data: &[u8]) -> Result<Vec<RawBox>, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        reader.skip(AUDIO_SAMPLE_ENTRY_LENGTH)?;
+        read_children(&mut reader, header, AUDIO_SAMPLE_ENTRY_LENGTH, |_, _| Ok(false))
+    }
+
+    #[test]
+    fn children_kept_as_raw_boxes() {
+        let mut children = vec![0, 0, 0, 8];
+        children.extend_from_slice(b"free");
+        children.extend_from_slice(&[0, 0, 0, 10]);
+        children.extend_from_slice(b"abcd");
+        children.extend_from_slice(&[1, 2]);
+        // 32 bit terminator
+        children.extend_from_slice(&[0; 4]);
+        let extensions = read_entry_children(&audio_entry(&children)).unwrap();
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions[0].header.name, BoxType::Free);
+        assert_eq!(extensions[1].data, vec![1, 2]);
+    }
+
+    #[test]
+    fn invalid_child_sizes_rejected() {
+        for size in [0u32, 4, 100] {
+            let mut children = size.to_be_bytes().to_vec();
+            children.extend_from_slice(b"abcd");
+            children.extend_from_slice(&[0; 8]);
+            assert!(matches!(
+                read_entry_children(&audio_entry(&children)),
+                Err(Error::InvalidData(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, stsd::AudioSampleEntry, BitReader, BoxHeader, BoxReader, BoxType, BoxWriter,
+    Error, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+// Sample rates indexed by fscod
+pub const AC3_SAMPLE_RATES: [u32; 3] = [48000, 44100, 32000];
+
+// Full bandwidth channels indexed by acmod
+pub const AC3_CHANNEL_COUNTS: [u8; 8] = [2, 1, 2, 3, 3, 4, 4, 5];
+
+fn ac3_sample_rate(fscod: u8) -> u32 {
+    AC3_SAMPLE_RATES.get(fscod as usize).copied().unwrap_or(0)
+}
+
+fn ac3_channel_count(acmod: u8, lfeon: bool) -> u8 {
+    AC3_CHANNEL_COUNTS[acmod as usize & 0x07] + lfeon as u8
+}
+
+// ETSI TS 102 366 F.3 AC-3 Sample Entry
+#[derive(Clone, Debug)]
+pub struct Ac3SampleEntry {
+    pub audio: AudioSampleEntry,
+    pub config: Ac3SpecificBox,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt...
+}
+
+impl Reader for Ac3SampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut config: Option<Ac3SpecificBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::Ac3Specific => {
+                    config = Some(Ac3SpecificBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Ac-3: dac3 box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            audio,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Writer for Ac3SampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Ac3, |writer| {
+            self.audio.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ETSI TS 102 366 F.4 AC3SpecificBox
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Ac3SpecificBox {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub bsmod: u8,
+    pub acmod: u8, // Audio coding mode, channel layout without the LFE
+    pub lfeon: bool,
+    pub bit_rate_code: u8,
+}
+
+impl Ac3SpecificBox {
+    pub fn sample_rate(&self) -> u32 {
+        ac3_sample_rate(self.fscod)
+    }
+
+    pub fn channel_count(&self) -> u8 {
+        ac3_channel_count(self.acmod, self.lfeon)
+    }
+
+    // Nominal bit rate in kbit/s
+    pub fn bit_rate(&self) -> u32 {
+        const BIT_RATES: [u32; 19] = [
+            32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+        ];
+        BIT_RATES.get(self.bit_rate_code as usize).copied().unwrap_or(0)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let value: u32 = (self.fscod as u32) << 22
+            | (self.bsid as u32 & 0x1F) << 17
+            | (self.bsmod as u32 & 0x07) << 14
+            | (self.acmod as u32 & 0x07) << 11
+            | (self.lfeon as u32) << 10
+            | (self.bit_rate_code as u32 & 0x1F) << 5;
+        value.to_be_bytes()[1..].to_vec()
+    }
+}
+
+impl Reader for Ac3SpecificBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        if header.size < HEADER_LENGTH + 3 {
+            return Err(Error::InvalidData("Dac3: box is too short".to_owned()));
+        }
+        let data = reader.read_bytes(3)?;
+        let mut bits = BitReader::new(&data);
+        let config = Self {
+            fscod: bits.read_u8(2)?,
+            bsid: bits.read_u8(5)?,
+            bsmod: bits.read_u8(3)?,
+            acmod: bits.read_u8(3)?,
+            lfeon: bits.read_bit()?,
+            bit_rate_code: bits.read_u8(5)?,
+        };
+        let parsed_size = HEADER_LENGTH + 3;
+        if parsed_size < header.size {
+            reader.skip(header.size - parsed_size)?;
+        }
+        Ok(config)
+    }
+}
+
+impl Writer for Ac3SpecificBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Ac3Specific, |writer| writer.write_bytes(&self.to_bytes()))
+    }
+}
+
+// ETSI TS 102 366 F.5 E-AC-3 Sample Entry
+#[derive(Clone, Debug)]
+pub struct Ec3SampleEntry {
+    pub audio: AudioSampleEntry,
+    pub config: Ec3SpecificBox,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt...
+}
+
+impl Reader for Ec3SampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut config: Option<Ec3SpecificBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::Ec3Specific => {
+                    config = Some(Ec3SpecificBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Ec-3: dec3 box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            audio,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Writer for Ec3SampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Ec3, |writer| {
+            self.audio.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ETSI TS 102 366 F.6 EC3SpecificBox
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Ec3SpecificBox {
+    pub data_rate: u16, // kbit/s
+    pub substreams: Vec<Ec3Substream>,
+    pub extension: Vec<u8>, // Trailing bytes, such as the Atmos JOC flags
+}
+
+// Independent substream
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Ec3Substream {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub asvc: bool,
+    pub bsmod: u8,
+    pub acmod: u8,
+    pub lfeon: bool,
+    pub num_dep_sub: u8,
+    pub chan_loc: u16, // Channels added by the dependent substreams
+}
+
+impl Ec3Substream {
+    pub fn channel_count(&self) -> u8 {
+        // Channel pairs Lc/Rc, Lrs/Rrs, Lsd/Rsd, Lw/Rw and Lvh/Rvh, the MSB being Lc/Rc
+        let pairs = (self.chan_loc & 0x019C).count_ones() as u8;
+        let singles = (self.chan_loc & 0x0063).count_ones() as u8;
+        ac3_channel_count(self.acmod, self.lfeon) + 2 * pairs + singles
+    }
+}
+
+impl Ec3SpecificBox {
+    pub fn sample_rate(&self) -> u32 {
+        self.substreams.first().map(|substream| ac3_sample_rate(substream.fscod)).unwrap_or(0)
+    }
+
+    // Channels of the first independent substream with its dependent substreams
+    pub fn channel_count(&self) -> u8 {
+        self.substreams.first().map(|substream| substream.channel_count()).unwrap_or(0)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let num_ind_sub = self.substreams.len().saturating_sub(1) as u16;
+        let mut bytes = (self.data_rate << 3 | num_ind_sub & 0x07).to_be_bytes().to_vec();
+        for substream in self.substreams.iter() {
+            bytes.push(substream.fscod << 6 | (substream.bsid & 0x1F) << 1);
+            bytes.push(
+                (substream.asvc as u8) << 7
+                    | (substream.bsmod & 0x07) << 4
+                    | (substream.acmod & 0x07) << 1
+                    | substream.lfeon as u8,
+            );
+            let num_dep_sub = (substream.num_dep_sub & 0x0F) << 1;
+            match substream.num_dep_sub {
+                0 => bytes.push(0),
+                _ => {
+                    bytes.push(num_dep_sub | (substream.chan_loc >> 8) as u8 & 0x01);
+                    bytes.push(substream.chan_loc as u8);
+                }
+            }
+        }
+        bytes.extend_from_slice(&self.extension);
+        bytes
+    }
+}
+
+impl Reader for Ec3SpecificBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let data = reader.read_bytes((header.size - HEADER_LENGTH) as usize)?;
+        let mut bits = BitReader::new(&data);
+        let data_rate = bits.read_bits(13)? as u16;
+        let num_ind_sub = bits.read_u8(3)? + 1;
+        let mut substreams = Vec::with_capacity(num_ind_sub as usize);
+        for _ in 0..num_ind_sub {
+            let fscod = bits.read_u8(2)?;
+            let bsid = bits.read_u8(5)?;
+            bits.skip_bits(1)?; // Reserved
+            let asvc = bits.read_bit()?;
+            let bsmod = bits.read_u8(3)?;
+            let acmod = bits.read_u8(3)?;
+            let lfeon = bits.read_bit()?;
+            bits.skip_bits(3)?; // Reserved
+            let num_dep_sub = bits.read_u8(4)?;
+            let chan_loc = match num_dep_sub {
+                0 => {
+                    bits.skip_bits(1)?; // Reserved
+                    0
+                }
+                _ => bits.read_bits(9)? as u16,
+            };
+            substreams.push(Ec3Substream {
+                fscod,
+                bsid,
+                asvc,
+                bsmod,
+                acmod,
+                lfeon,
+                num_dep_sub,
+                chan_loc,
+            });
+        }
+        let extension = data[bits.position() / 8..].to_vec();
+
+        Ok(Self {
+            data_rate,
+            substreams,
+            extension,
+        })
+    }
+}
+
+impl Writer for Ec3SpecificBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Ec3Specific, |writer| writer.write_bytes(&self.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{AudioCodec, Mp4, SampleEntry};
+
+    // testdata/gencodecs.py audio.mp4 audio: track 3 is 5.1 AC-3 at 384 kbit/s, track 4 is 7.1 E-AC-3
+    // with a dependent substream and the JOC extension
+    const AUDIO: &[u8] = include_bytes!("../../testdata/audio.mp4");
+
+    fn written(value: &impl Writer) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        value.write(&mut BoxWriter::new(&mut data)).unwrap();
+        data
+    }
+
+    fn read_dac3(data: &[u8]) -> Result<Ac3SpecificBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        Ac3SpecificBox::read(&mut reader, header)
+    }
+
+    #[test]
+    fn ac3_and_ec3_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let SampleEntry::Audio(AudioCodec::Ac3(ac3)) = &mp4.track(3).unwrap().sample_entries()[0] else {
+            panic!("track 3 is not ac-3");
+        };
+        let config = &ac3.config;
+        assert_eq!((config.fscod, config.bsid, config.bsmod, config.acmod, config.lfeon), (0, 8, 0, 7, true));
+        assert_eq!((config.sample_rate(), config.channel_count(), config.bit_rate()), (48000, 6, 384));
+
+        let SampleEntry::Audio(AudioCodec::Ec3(ec3)) = &mp4.track(4).unwrap().sample_entries()[0] else {
+            panic!("track 4 is not ec-3");
+        };
+        let config = &ec3.config;
+        assert_eq!(config.data_rate, 640);
+        assert_eq!(
+            config.substreams,
+            vec![Ec3Substream {
+                fscod: 0,
+                bsid: 16,
+                asvc: false,
+                bsmod: 0,
+                acmod: 7,
+                lfeon: true,
+                num_dep_sub: 1,
+                chan_loc: 0x80, // Lrs/Rrs
+            }]
+        );
+        assert_eq!((config.sample_rate(), config.channel_count()), (48000, 8));
+        assert_eq!(config.extension, vec![0x01, 0x10]);
+
+        for (name, entry) in [(b"ac-3", written(ac3)), (b"ec-3", written(ec3))] {
+            let start = AUDIO.windows(4).position(|bytes| bytes == name).unwrap() - 4;
+            assert_eq!(&AUDIO[start..start + entry.len()], &entry[..]);
+        }
+    }
+
+    #[test]
+    fn short_dac3_rejected() {
+        let config = Ac3SpecificBox {
+            fscod: 1,
+            bsid: 8,
+            acmod: 2,
+            bit_rate_code: 10,
+            ..Default::default()
+        };
+        let data = written(&config);
+        assert_eq!(read_dac3(&data).unwrap(), config);
+        assert_eq!((config.sample_rate(), config.channel_count(), config.bit_rate()), (44100, 2, 192));
+
+        // Two bytes of payload and the next box right after
+        let mut short = data[..10].to_vec();
+        short[3] = 10;
+        short.extend_from_slice(&data);
+        assert!(matches!(read_dac3(&short), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, stsd::AudioSampleEntry, BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox,
+    Reader, Writer, HEADER_LENGTH,
+};
+
+// Apple Lossless Audio Codec sample entry, the configuration is a nested alac box
+// https://github.com/macosforge/alac/blob/master/ALACMagicCookieDescription.txt
+#[derive(Clone, Debug)]
+pub struct AlacSampleEntry {
+    pub audio: AudioSampleEntry,
+    pub config: AlacSpecificBox,
+    pub extensions: Vec<RawBox>, // Other child boxes: chan...
+}
+
+impl Reader for AlacSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut config: Option<AlacSpecificBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::Alac if config.is_none() => {
+                    config = Some(AlacSpecificBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Alac: alac configuration box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            audio,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Writer for AlacSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Alac, |writer| {
+            self.audio.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ALACSpecificConfig, the magic cookie given to the decoder
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AlacSpecificBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub frame_length: u32,
+    pub compatible_version: u8,
+    pub bit_depth: u8,
+    pub pb: u8, // Rice tuning parameters
+    pub mb: u8,
+    pub kb: u8,
+    pub num_channels: u8,
+    pub max_run: u16,
+    pub max_frame_bytes: u32,
+    pub avg_bit_rate: u32,
+    pub sample_rate: u32,
+}
+
+// Size of the ALACSpecificConfig
+pub const ALAC_SPECIFIC_CONFIG_LENGTH: u64 = 24;
+
+impl AlacSpecificBox {
+    pub fn magic_cookie(&self) -> Vec<u8> {
+        let mut cookie = Vec::with_capacity(ALAC_SPECIFIC_CONFIG_LENGTH as usize);
+        cookie.extend_from_slice(&self.frame_length.to_be_bytes());
+        cookie.extend_from_slice(&[
+            self.compatible_version,
+            self.bit_depth,
+            self.pb,
+            self.mb,
+            self.kb,
+            self.num_channels,
+        ]);
+        cookie.extend_from_slice(&self.max_run.to_be_bytes());
+        cookie.extend_from_slice(&self.max_frame_bytes.to_be_bytes());
+        cookie.extend_from_slice(&self.avg_bit_rate.to_be_bytes());
+        cookie.extend_from_slice(&self.sample_rate.to_be_bytes());
+        cookie
+    }
+}
+
+impl Reader for AlacSpecificBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let parsed_size = HEADER_LENGTH + 4 + ALAC_SPECIFIC_CONFIG_LENGTH;
+        if header.size < parsed_size {
+            return Err(Error::InvalidData("Alac: box is too short".to_owned()));
+        }
+        let (version, flags) = reader.read_header_extra()?;
+        let config = Self {
+            version,
+            flags,
+
+            frame_length: reader.read_u32()?,
+            compatible_version: reader.read_u8()?,
+            bit_depth: reader.read_u8()?,
+            pb: reader.read_u8()?,
+            mb: reader.read_u8()?,
+            kb: reader.read_u8()?,
+            num_channels: reader.read_u8()?,
+            max_run: reader.read_u16()?,
+            max_frame_bytes: reader.read_u32()?,
+            avg_bit_rate: reader.read_u32()?,
+            sample_rate: reader.read_u32()?,
+        };
+        if parsed_size < header.size {
+            reader.skip(header.size - parsed_size)?;
+        }
+        Ok(config)
+    }
+}
+
+impl Writer for AlacSpecificBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Alac, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_bytes(&self.magic_cookie())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{AudioCodec, Mp4, SampleEntry};
+
+    // testdata/gencodecs.py audio.mp4 audio: track 5 is 24 bit 96 kHz stereo ALAC
+    const AUDIO: &[u8] = include_bytes!("../../testdata/audio.mp4");
+
+    fn read_alac(data: &[u8]) -> Result<AlacSpecificBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        AlacSpecificBox::read(&mut reader, header)
+    }
+
+    #[test]
+    fn alac_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let SampleEntry::Audio(AudioCodec::Alac(entry)) = &mp4.track(5).unwrap().sample_entries()[0] else {
+            panic!("track 5 is not alac");
+        };
+        let config = &entry.config;
+        assert_eq!((config.frame_length, config.bit_depth, config.num_channels), (4096, 24, 2));
+        assert_eq!((config.pb, config.mb, config.kb, config.max_run), (40, 10, 14, 255));
+        assert_eq!(config.sample_rate, 96000);
+        let cookie = config.magic_cookie();
+        assert_eq!(cookie.len() as u64, ALAC_SPECIFIC_CONFIG_LENGTH);
+        assert_eq!(&cookie[20..], &96000u32.to_be_bytes());
+
+        // The configuration box has the same name as the sample entry
+        let mut written: Vec<u8> = Vec::new();
+        entry.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = AUDIO.windows(4).position(|name| name == b"alac").unwrap() - 4;
+        assert_eq!(&AUDIO[start..start + written.len()], &written[..]);
+        assert_eq!(read_alac(&written[8 + entry.audio.length() as usize..]).unwrap(), *config);
+    }
+
+    #[test]
+    fn short_alac_rejected() {
+        let mut data: Vec<u8> = Vec::new();
+        AlacSpecificBox::default().write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(read_alac(&data).unwrap(), AlacSpecificBox::default());
+        data[3] -= 4;
+        assert!(matches!(read_alac(&data), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,537 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children,
+    stsd::{VisualSampleEntry, VISUAL_SAMPLE_ENTRY_LENGTH},
+    BitReader, BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox, Reader, Writer,
+    HEADER_LENGTH,
+};
+
+pub const AV1_OBU_SEQUENCE_HEADER: u8 = 1;
+
+// AV1 Codec ISOBMFF Binding 2.2 AV1 Sample Entry
+#[derive(Clone, Debug)]
+pub struct Av1SampleEntry {
+    pub visual: VisualSampleEntry,
+    pub config: Av1CodecConfigurationRecord,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, pasp, colr...
+}
+
+impl Reader for Av1SampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let visual = VisualSampleEntry::read(reader, header)?;
+        let mut config: Option<Av1CodecConfigurationRecord> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            VISUAL_SAMPLE_ENTRY_LENGTH,
+            |reader, child_header| match child_header.name {
+                BoxType::Av1Configuration => {
+                    config = Some(Av1CodecConfigurationRecord::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Av01: av1C box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            visual,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Av1SampleEntry {
+    // AV1 Codec ISOBMFF Binding 5 codecs parameter: av01.P.LLT.DD[.M.CCC.cp.tc.mc.F]
+    pub fn codec_string(&self) -> String {
+        let config = &self.config;
+        let tier = if config.seq_tier_0 == 1 { 'H' } else { 'M' };
+        let mut codec = format!(
+            "av01.{}.{:02}{}.{:02}",
+            config.seq_profile,
+            config.seq_level_idx_0,
+            tier,
+            config.bit_depth()
+        );
+        // Optional fields are only written when they differ from the defaults
+        if let Some(Ok(sequence_header)) = config.sequence_header() {
+            let color = &sequence_header.color_config;
+            let chroma_sample_position = match color.subsampling_x && color.subsampling_y {
+                true => color.chroma_sample_position,
+                false => 0,
+            };
+            let chroma = format!(
+                "{}{}{}",
+                color.subsampling_x as u8, color.subsampling_y as u8, chroma_sample_position
+            );
+            let is_default = !color.mono_chrome
+                && chroma == "110"
+                && color.color_primaries == 1
+                && color.transfer_characteristics == 1
+                && color.matrix_coefficients == 1
+                && !color.color_range;
+            if !is_default {
+                codec.push_str(&format!(
+                    ".{}.{}.{:02}.{:02}.{:02}.{}",
+                    color.mono_chrome as u8,
+                    chroma,
+                    color.color_primaries,
+                    color.transfer_characteristics,
+                    color.matrix_coefficients,
+                    color.color_range as u8
+                ));
+            }
+        }
+        codec
+    }
+}
+
+impl Writer for Av1SampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Av01, |writer| {
+            self.visual.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// AV1 Codec ISOBMFF Binding 2.3 AV1 Codec Configuration Box
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Av1CodecConfigurationRecord {
+    pub version: u8,
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: u8,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+    pub config_obus: Vec<u8>, // Sequence header and metadata OBUs
+}
+
+impl Av1CodecConfigurationRecord {
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (true, true) => 12,
+            (true, false) => 10,
+            _ => 8,
+        }
+    }
+
+    // Decode the sequence header OBU carried in configOBUs, if any
+    pub fn sequence_header(&self) -> Option<Result<Av1SequenceHeader, Error>> {
+        let mut data = &self.config_obus[..];
+        while !data.is_empty() {
+            let (obu_type, payload, size) = match read_obu(data) {
+                Ok(obu) => obu,
+                Err(error) => return Some(Err(error)),
+            };
+            if obu_type == AV1_OBU_SEQUENCE_HEADER {
+                return Some(Av1SequenceHeader::parse(payload));
+            }
+            data = &data[size..];
+        }
+        None
+    }
+}
+
+impl Reader for Av1CodecConfigurationRecord {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let content_size = header.size - HEADER_LENGTH;
+        if content_size < 4 {
+            return Err(Error::InvalidData("Av1C: record is too short".to_owned()));
+        }
+        let marker_version = reader.read_u8()?;
+        if marker_version & 0x80 == 0 {
+            return Err(Error::InvalidData("Av1C: invalid marker".to_owned()));
+        }
+        let profile_level = reader.read_u8()?;
+        let flags = reader.read_u8()?;
+        let delay = reader.read_u8()?;
+        let config_obus = reader.read_bytes((content_size - 4) as usize)?;
+
+        Ok(Self {
+            version: marker_version & 0x7F,
+            seq_profile: profile_level >> 5,
+            seq_level_idx_0: profile_level & 0x1F,
+            seq_tier_0: flags >> 7,
+            high_bitdepth: flags & 0x40 != 0,
+            twelve_bit: flags & 0x20 != 0,
+            monochrome: flags & 0x10 != 0,
+            chroma_subsampling_x: flags & 0x08 != 0,
+            chroma_subsampling_y: flags & 0x04 != 0,
+            chroma_sample_position: flags & 0x03,
+            initial_presentation_delay_minus_one: match delay & 0x10 {
+                0 => None,
+                _ => Some(delay & 0x0F),
+            },
+            config_obus,
+        })
+    }
+}
+
+impl Writer for Av1CodecConfigurationRecord {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Av1Configuration, |writer| {
+            writer.write_u8(0x80 | self.version)?;
+            writer.write_u8(self.seq_profile << 5 | self.seq_level_idx_0)?;
+            writer.write_u8(
+                self.seq_tier_0 << 7
+                    | (self.high_bitdepth as u8) << 6
+                    | (self.twelve_bit as u8) << 5
+                    | (self.monochrome as u8) << 4
+                    | (self.chroma_subsampling_x as u8) << 3
+                    | (self.chroma_subsampling_y as u8) << 2
+                    | self.chroma_sample_position,
+            )?;
+            writer.write_u8(match self.initial_presentation_delay_minus_one {
+                Some(delay) => 0x10 | delay,
+                None => 0,
+            })?;
+            writer.write_bytes(&self.config_obus)
+        })
+    }
+}
+
+// Split the first OBU of data, returns (obu_type, payload, total size)
+fn read_obu(data: &[u8]) -> Result<(u8, &[u8], usize), Error> {
+    let header = *data.first().ok_or(Error::EOF())?;
+    let obu_type = (header >> 3) & 0x0F;
+    let has_extension = header & 0x04 != 0;
+    let has_size_field = header & 0x02 != 0;
+    let mut position = 1 + has_extension as usize;
+    let size = match has_size_field {
+        true => {
+            // leb128
+            let mut size: u64 = 0;
+            let mut i = 0;
+            loop {
+                let byte = *data.get(position).ok_or(Error::EOF())?;
+                size |= ((byte & 0x7F) as u64) << (i * 7);
+                position += 1;
+                i += 1;
+                if byte & 0x80 == 0 || i == 8 {
+                    break;
+                }
+            }
+            size as usize
+        }
+        false => data.len().saturating_sub(position),
+    };
+    let end = position.checked_add(size).filter(|end| *end <= data.len()).ok_or(Error::EOF())?;
+    Ok((obu_type, &data[position..end], end))
+}
+
+// AV1 5.5 Sequence header OBU, up to the color config and film grain flag
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Av1SequenceHeader {
+    pub seq_profile: u8,
+    pub still_picture: bool,
+    pub reduced_still_picture_header: bool,
+    pub timing_info: Option<Av1TimingInfo>,
+    pub operating_points: Vec<Av1OperatingPoint>,
+    pub max_frame_width: u32,
+    pub max_frame_height: u32,
+    pub use_128x128_superblock: bool,
+    pub enable_order_hint: bool,
+    pub enable_superres: bool,
+    pub enable_cdef: bool,
+    pub enable_restoration: bool,
+    pub color_config: Av1ColorConfig,
+    pub film_grain_params_present: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Av1TimingInfo {
+    pub num_units_in_display_tick: u32,
+    pub time_scale: u32,
+    pub num_ticks_per_picture: Option<u32>, // Set when equal_picture_interval
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Av1OperatingPoint {
+    pub idc: u16, // Enabled temporal and spatial layers
+    pub seq_level_idx: u8,
+    pub seq_tier: u8,
+    pub initial_display_delay_minus_one: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Av1ColorConfig {
+    pub bit_depth: u8,
+    pub mono_chrome: bool,
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub color_range: bool, // Full range
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub separate_uv_delta_q: bool,
+}
+
+// AV1 4.10.3 uvlc()
+fn read_uvlc(bits: &mut BitReader) -> Result<u32, Error> {
+    let mut leading_zeros: u8 = 0;
+    while !bits.read_bit()? {
+        leading_zeros += 1;
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+    }
+    Ok(bits.read_bits(leading_zeros)? + ((1u64 << leading_zeros) - 1) as u32)
+}
+
+impl Av1SequenceHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut bits = BitReader::new(data);
+        let mut header = Av1SequenceHeader {
+            seq_profile: bits.read_u8(3)?,
+            still_picture: bits.read_bit()?,
+            reduced_still_picture_header: bits.read_bit()?,
+            ..Default::default()
+        };
+
+        if header.reduced_still_picture_header {
+            header.operating_points.push(Av1OperatingPoint {
+                seq_level_idx: bits.read_u8(5)?,
+                ..Default::default()
+            });
+        } else {
+            let mut buffer_delay_length = 0;
+            let mut decoder_model_info_present = false;
+            if bits.read_bit()? {
+                let num_units_in_display_tick = bits.read_bits(32)?;
+                let time_scale = bits.read_bits(32)?;
+                let num_ticks_per_picture = match bits.read_bit()? {
+                    true => Some(read_uvlc(&mut bits)?.saturating_add(1)),
+                    false => None,
+                };
+                header.timing_info = Some(Av1TimingInfo {
+                    num_units_in_display_tick,
+                    time_scale,
+                    num_ticks_per_picture,
+                });
+                decoder_model_info_present = bits.read_bit()?;
+                if decoder_model_info_present {
+                    buffer_delay_length = bits.read_u8(5)? + 1;
+                    bits.skip_bits(32 + 5 + 5)?; // Decoding tick, removal and presentation time lengths
+                }
+            }
+            let initial_display_delay_present = bits.read_bit()?;
+            let operating_points_count = bits.read_u8(5)? + 1;
+            for _ in 0..operating_points_count {
+                let idc = bits.read_bits(12)? as u16;
+                let seq_level_idx = bits.read_u8(5)?;
+                let seq_tier = match seq_level_idx > 7 {
+                    true => bits.read_u8(1)?,
+                    false => 0,
+                };
+                if decoder_model_info_present && bits.read_bit()? {
+                    // Decoder and encoder buffer delays, low_delay_mode_flag
+                    bits.skip_bits(2 * buffer_delay_length as usize + 1)?;
+                }
+                let mut initial_display_delay_minus_one = None;
+                if initial_display_delay_present && bits.read_bit()? {
+                    initial_display_delay_minus_one = Some(bits.read_u8(4)?);
+                }
+                header.operating_points.push(Av1OperatingPoint {
+                    idc,
+                    seq_level_idx,
+                    seq_tier,
+                    initial_display_delay_minus_one,
+                });
+            }
+        }
+
+        let frame_width_bits = bits.read_u8(4)? + 1;
+        let frame_height_bits = bits.read_u8(4)? + 1;
+        header.max_frame_width = bits.read_bits(frame_width_bits)? + 1;
+        header.max_frame_height = bits.read_bits(frame_height_bits)? + 1;
+        if !header.reduced_still_picture_header && bits.read_bit()? {
+            bits.skip_bits(4 + 3)?; // Frame id lengths
+        }
+        header.use_128x128_superblock = bits.read_bit()?;
+        bits.skip_bits(2)?; // enable_filter_intra, enable_intra_edge_filter
+        if !header.reduced_still_picture_header {
+            // enable_interintra_compound, enable_masked_compound, enable_warped_motion, enable_dual_filter
+            bits.skip_bits(4)?;
+            header.enable_order_hint = bits.read_bit()?;
+            if header.enable_order_hint {
+                bits.skip_bits(2)?; // enable_jnt_comp, enable_ref_frame_mvs
+            }
+            let seq_force_screen_content_tools = match bits.read_bit()? {
+                true => 2, // SELECT_SCREEN_CONTENT_TOOLS
+                false => bits.read_u8(1)?,
+            };
+            if seq_force_screen_content_tools > 0 && !bits.read_bit()? {
+                bits.skip_bits(1)?; // seq_force_integer_mv
+            }
+            if header.enable_order_hint {
+                bits.skip_bits(3)?; // order_hint_bits_minus_1
+            }
+        }
+        header.enable_superres = bits.read_bit()?;
+        header.enable_cdef = bits.read_bit()?;
+        header.enable_restoration = bits.read_bit()?;
+        header.color_config = Av1ColorConfig::parse(&mut bits, header.seq_profile)?;
+        header.film_grain_params_present = bits.read_bit()?;
+        Ok(header)
+    }
+}
+
+impl Av1ColorConfig {
+    // AV1 5.5.2 Color config syntax
+    fn parse(bits: &mut BitReader, seq_profile: u8) -> Result<Self, Error> {
+        let mut config = Av1ColorConfig {
+            bit_depth: 8,
+            ..Default::default()
+        };
+        if bits.read_bit()? {
+            config.bit_depth = match seq_profile == 2 && bits.read_bit()? {
+                true => 12,
+                false => 10,
+            };
+        }
+        config.mono_chrome = seq_profile != 1 && bits.read_bit()?;
+        if bits.read_bit()? {
+            config.color_primaries = bits.read_u8(8)?;
+            config.transfer_characteristics = bits.read_u8(8)?;
+            config.matrix_coefficients = bits.read_u8(8)?;
+        } else {
+            // Unspecified
+            config.color_primaries = 2;
+            config.transfer_characteristics = 2;
+            config.matrix_coefficients = 2;
+        }
+        if config.mono_chrome {
+            config.color_range = bits.read_bit()?;
+            config.subsampling_x = true;
+            config.subsampling_y = true;
+            return Ok(config);
+        }
+        let is_srgb = config.color_primaries == 1
+            && config.transfer_characteristics == 13
+            && config.matrix_coefficients == 0;
+        if is_srgb {
+            config.color_range = true;
+        } else {
+            config.color_range = bits.read_bit()?;
+            match seq_profile {
+                0 => {
+                    config.subsampling_x = true;
+                    config.subsampling_y = true;
+                }
+                1 => (),
+                _ if config.bit_depth == 12 => {
+                    config.subsampling_x = bits.read_bit()?;
+                    config.subsampling_y = config.subsampling_x && bits.read_bit()?;
+                }
+                _ => config.subsampling_x = true,
+            }
+            if config.subsampling_x && config.subsampling_y {
+                config.chroma_sample_position = bits.read_u8(2)?;
+            }
+        }
+        config.separate_uv_delta_q = bits.read_bit()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleEntry, VideoCodec};
+
+    // testdata/gencodecs.py av1.mp4 av1: 8 bit BT.709 1920x1080 level 4.0, 10 bit PQ 3840x2160 level 5.1
+    const AV1: &[u8] = include_bytes!("../../testdata/av1.mp4");
+
+    fn av1_entry(mp4: &Mp4, track_id: u32) -> &Av1SampleEntry {
+        match &mp4.track(track_id).unwrap().sample_entries()[0] {
+            SampleEntry::Video(VideoCodec::Av1(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    fn read_av1c(data: &[u8]) -> Result<Av1CodecConfigurationRecord, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        Av1CodecConfigurationRecord::read(&mut reader, header)
+    }
+
+    #[test]
+    fn av01_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AV1)).unwrap();
+        let entry = av1_entry(&mp4, 1);
+        let config = &entry.config;
+        assert_eq!((config.version, config.seq_profile, config.seq_level_idx_0, config.seq_tier_0), (1, 0, 8, 0));
+        assert_eq!(config.bit_depth(), 8);
+        assert!(!config.monochrome && config.chroma_subsampling_x && config.chroma_subsampling_y);
+        assert_eq!(config.initial_presentation_delay_minus_one, None);
+
+        let header = config.sequence_header().unwrap().unwrap();
+        assert_eq!((header.max_frame_width, header.max_frame_height), (1920, 1080));
+        assert_eq!(header.timing_info, None);
+        assert_eq!(
+            header.operating_points,
+            vec![Av1OperatingPoint {
+                seq_level_idx: 8,
+                ..Default::default()
+            }]
+        );
+        assert!(header.use_128x128_superblock && header.enable_order_hint);
+        assert!(!header.enable_superres && header.enable_cdef && header.enable_restoration);
+        let color = &header.color_config;
+        assert_eq!((color.bit_depth, color.color_primaries, color.transfer_characteristics), (8, 1, 1));
+        assert!(!color.color_range && color.subsampling_x && color.subsampling_y);
+        assert!(!header.film_grain_params_present);
+
+        let header = av1_entry(&mp4, 2).config.sequence_header().unwrap().unwrap();
+        assert_eq!((header.max_frame_width, header.max_frame_height), (3840, 2160));
+        assert_eq!(header.operating_points[0].seq_level_idx, 13);
+        let color = &header.color_config;
+        assert_eq!(color.bit_depth, 10);
+        assert_eq!((color.color_primaries, color.transfer_characteristics, color.matrix_coefficients), (9, 16, 9));
+
+        let starts: Vec<usize> = (0..AV1.len() - 4).filter(|&i| &AV1[i..i + 4] == b"av01").map(|i| i - 4).collect();
+        for (track_id, start) in [(1, starts[0]), (2, starts[1])] {
+            let mut written: Vec<u8> = Vec::new();
+            av1_entry(&mp4, track_id).write(&mut BoxWriter::new(&mut written)).unwrap();
+            assert_eq!(&AV1[start..start + written.len()], &written[..]);
+        }
+    }
+
+    #[test]
+    fn invalid_records_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AV1)).unwrap();
+        let config = &av1_entry(&mp4, 1).config;
+        let mut data: Vec<u8> = Vec::new();
+        config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(&read_av1c(&data).unwrap(), config);
+
+        let mut unmarked = data.clone();
+        unmarked[8] = 0x01;
+        assert!(matches!(read_av1c(&unmarked), Err(Error::InvalidData(_))));
+        let short = [0, 0, 0, 11, b'a', b'v', b'1', b'C', 0x81, 0x08, 0x0C];
+        assert!(matches!(read_av1c(&short), Err(Error::InvalidData(_))));
+
+        // An OBU size running past configOBUs
+        let mut truncated = config.clone();
+        truncated.config_obus.pop();
+        assert!(matches!(truncated.sequence_header(), Some(Err(Error::EOF()))));
+        truncated.config_obus.clear();
+        assert!(truncated.sequence_header().is_none());
+    }
+}
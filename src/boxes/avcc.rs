@@ -0,0 +1,290 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children,
+    stsd::{VisualSampleEntry, VISUAL_SAMPLE_ENTRY_LENGTH},
+    AvcPictureParameterSet, AvcSequenceParameterSet, BoxHeader, BoxReader, BoxType, BoxWriter,
+    Error, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+// ISO/IEC 14496-15 5.4.2 AVC Sample Entry, avc1 or avc3
+#[derive(Clone, Debug)]
+pub struct AvcSampleEntry {
+    pub name: BoxType,
+    pub visual: VisualSampleEntry,
+    pub config: AvcDecoderConfigurationRecord,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, pasp, colr...
+}
+
+impl Reader for AvcSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let visual = VisualSampleEntry::read(reader, header)?;
+        let mut config: Option<AvcDecoderConfigurationRecord> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            VISUAL_SAMPLE_ENTRY_LENGTH,
+            |reader, child_header| match child_header.name {
+                BoxType::AvcConfiguration => {
+                    config = Some(AvcDecoderConfigurationRecord::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound(format!("{:?}: avcC box is mandatory", header.name)));
+        }
+        Ok(Self {
+            name: header.name,
+            visual,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl AvcSampleEntry {
+    // RFC 6381 codecs parameter: avc1.PPCCLL
+    pub fn codec_string(&self) -> String {
+        format!(
+            "{}.{:02X}{:02X}{:02X}",
+            self.name,
+            self.config.profile_indication,
+            self.config.profile_compatibility,
+            self.config.level_indication
+        )
+    }
+}
+
+impl Writer for AvcSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.name, |writer| {
+            self.visual.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ISO/IEC 14496-15 5.3.3.1 AVC Decoder Configuration Record
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AvcDecoderConfigurationRecord {
+    pub configuration_version: u8,
+    pub profile_indication: u8,
+    pub profile_compatibility: u8,
+    pub level_indication: u8,
+    pub length_size_minus_one: u8,
+    pub sequence_parameter_sets: Vec<Vec<u8>>,
+    pub picture_parameter_sets: Vec<Vec<u8>>,
+    pub high_profile: Option<AvcHighProfileExtension>,
+}
+
+// Extension present for the High profiles (100, 110, 122 and 144)
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AvcHighProfileExtension {
+    pub chroma_format: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub sequence_parameter_set_exts: Vec<Vec<u8>>,
+}
+
+impl AvcDecoderConfigurationRecord {
+    pub fn nal_length_size(&self) -> u8 {
+        self.length_size_minus_one + 1
+    }
+
+    pub fn is_high_profile(profile_indication: u8) -> bool {
+        matches!(profile_indication, 100 | 110 | 122 | 144)
+    }
+
+    // Decode the first sequence parameter set
+    pub fn sps(&self) -> Option<Result<AvcSequenceParameterSet, Error>> {
+        Some(AvcSequenceParameterSet::parse(self.sequence_parameter_sets.first()?))
+    }
+
+    // Decode the first picture parameter set
+    pub fn pps(&self) -> Option<Result<AvcPictureParameterSet, Error>> {
+        let sps = match self.sps() {
+            Some(Ok(sps)) => Some(sps),
+            _ => None,
+        };
+        Some(AvcPictureParameterSet::parse(self.picture_parameter_sets.first()?, sps.as_ref()))
+    }
+}
+
+// A 16 bit length then the NAL unit, which must fit in the box content
+fn read_parameter_set<T: Read + Seek>(
+    reader: &mut BoxReader<T>,
+    content_size: u64,
+    parsed_size: &mut u64,
+) -> Result<Vec<u8>, Error> {
+    let len = reader.read_u16()?;
+    *parsed_size += 2 + len as u64;
+    if *parsed_size > content_size {
+        return Err(Error::InvalidData(format!("AvcC: parameter set of {:?} bytes overruns the box", len)));
+    }
+    reader.read_bytes(len as usize)
+}
+
+impl Reader for AvcDecoderConfigurationRecord {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let content_size = header.size - HEADER_LENGTH;
+        let configuration_version = reader.read_u8()?;
+        let profile_indication = reader.read_u8()?;
+        let profile_compatibility = reader.read_u8()?;
+        let level_indication = reader.read_u8()?;
+        let length_size_minus_one = reader.read_u8()? & 0x03;
+        let mut parsed_size: u64 = 5;
+
+        let sps_count = reader.read_u8()? & 0x1F;
+        parsed_size += 1;
+        let mut sequence_parameter_sets = Vec::with_capacity(sps_count as usize);
+        for _ in 0..sps_count {
+            sequence_parameter_sets.push(read_parameter_set(reader, content_size, &mut parsed_size)?);
+        }
+        let pps_count = reader.read_u8()?;
+        parsed_size += 1;
+        let mut picture_parameter_sets = Vec::with_capacity(pps_count as usize);
+        for _ in 0..pps_count {
+            picture_parameter_sets.push(read_parameter_set(reader, content_size, &mut parsed_size)?);
+        }
+
+        // Some encoders omit the extension even for High profiles
+        let mut high_profile = None;
+        if Self::is_high_profile(profile_indication) && parsed_size + 4 <= content_size {
+            let chroma_format = reader.read_u8()? & 0x03;
+            let bit_depth_luma_minus8 = reader.read_u8()? & 0x07;
+            let bit_depth_chroma_minus8 = reader.read_u8()? & 0x07;
+            let sps_ext_count = reader.read_u8()?;
+            parsed_size += 4;
+            let mut sequence_parameter_set_exts = Vec::with_capacity(sps_ext_count as usize);
+            for _ in 0..sps_ext_count {
+                let sps_ext = read_parameter_set(reader, content_size, &mut parsed_size)?;
+                sequence_parameter_set_exts.push(sps_ext);
+            }
+            high_profile = Some(AvcHighProfileExtension {
+                chroma_format,
+                bit_depth_luma_minus8,
+                bit_depth_chroma_minus8,
+                sequence_parameter_set_exts,
+            });
+        }
+        if parsed_size > content_size {
+            return Err(Error::InvalidData(format!("AvcC: {:?} bytes overrun the box", parsed_size)));
+        }
+        if parsed_size < content_size {
+            reader.skip(content_size - parsed_size)?;
+        }
+
+        Ok(Self {
+            configuration_version,
+            profile_indication,
+            profile_compatibility,
+            level_indication,
+            length_size_minus_one,
+            sequence_parameter_sets,
+            picture_parameter_sets,
+            high_profile,
+        })
+    }
+}
+
+impl Writer for AvcDecoderConfigurationRecord {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::AvcConfiguration, |writer| {
+            writer.write_u8(self.configuration_version)?;
+            writer.write_u8(self.profile_indication)?;
+            writer.write_u8(self.profile_compatibility)?;
+            writer.write_u8(self.level_indication)?;
+            writer.write_u8(0xFC | self.length_size_minus_one)?;
+            writer.write_u8(0xE0 | self.sequence_parameter_sets.len() as u8)?;
+            for sps in self.sequence_parameter_sets.iter() {
+                writer.write_u16(sps.len() as u16)?;
+                writer.write_bytes(sps)?;
+            }
+            writer.write_u8(self.picture_parameter_sets.len() as u8)?;
+            for pps in self.picture_parameter_sets.iter() {
+                writer.write_u16(pps.len() as u16)?;
+                writer.write_bytes(pps)?;
+            }
+            if let Some(high_profile) = &self.high_profile {
+                writer.write_u8(0xFC | high_profile.chroma_format)?;
+                writer.write_u8(0xF8 | high_profile.bit_depth_luma_minus8)?;
+                writer.write_u8(0xF8 | high_profile.bit_depth_chroma_minus8)?;
+                writer.write_u8(high_profile.sequence_parameter_set_exts.len() as u8)?;
+                for sps_ext in high_profile.sequence_parameter_set_exts.iter() {
+                    writer.write_u16(sps_ext.len() as u16)?;
+                    writer.write_bytes(sps_ext)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleEntry, VideoCodec};
+
+    // testdata/gen.py: avc1 with a High profile avcC holding one SPS and one PPS
+    const PROGRESSIVE: &[u8] = include_bytes!("../../testdata/progressive.mp4");
+
+    fn avc_entry(mp4: &Mp4) -> &AvcSampleEntry {
+        match &mp4.track(1).unwrap().sample_entries()[0] {
+            SampleEntry::Video(VideoCodec::Avc(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    fn read_avcc(data: &[u8]) -> Result<AvcDecoderConfigurationRecord, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        AvcDecoderConfigurationRecord::read(&mut reader, header)
+    }
+
+    #[test]
+    fn avc1_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let entry = avc_entry(&mp4);
+        assert_eq!(entry.name, BoxType::Avc1);
+        assert_eq!((entry.visual.width, entry.visual.height), (640, 360));
+        assert_eq!(entry.codec_string(), "avc1.64001F");
+        let config = &entry.config;
+        assert_eq!(config.nal_length_size(), 4);
+        assert_eq!((config.sequence_parameter_sets.len(), config.picture_parameter_sets.len()), (1, 1));
+        assert_eq!(config.high_profile.as_ref().unwrap().chroma_format, 1);
+        let sps = config.sps().unwrap().unwrap();
+        assert_eq!(sps.profile_idc, 100);
+        assert!(config.pps().unwrap().is_ok());
+
+        let mut written: Vec<u8> = Vec::new();
+        entry.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = PROGRESSIVE.windows(4).rposition(|name| name == b"avc1").unwrap() - 4;
+        assert_eq!(&PROGRESSIVE[start..start + written.len()], &written[..]);
+    }
+
+    #[test]
+    fn high_profile_extension_optional() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let mut config = avc_entry(&mp4).config.clone();
+        config.high_profile = None;
+        let mut data: Vec<u8> = Vec::new();
+        config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(read_avcc(&data).unwrap(), config);
+    }
+
+    #[test]
+    fn overrunning_parameter_set_rejected() {
+        // One SPS of 16 bytes in a box with room for 4
+        let mut data = vec![0, 0, 0, 20, b'a', b'v', b'c', b'C', 1, 66, 0, 30, 0xFF, 0xE1, 0, 16];
+        data.extend_from_slice(&[0x67; 20]);
+        assert!(matches!(read_avcc(&data), Err(Error::InvalidData(_))));
+    }
+}
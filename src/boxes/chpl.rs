@@ -0,0 +1,74 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// Chapter starts are counted in 100 ns units
+pub const CHPL_TIMESCALE: u32 = 10_000_000;
+
+// Nero chapter list stored in moov/udta
+#[derive(Clone, Debug, Default)]
+pub struct ChapterListBox {
+    pub version: u8,
+    pub flags: u32,
+    pub chapters: Vec<ChapterListEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChapterListEntry {
+    pub start: u64, // In CHPL_TIMESCALE units
+    pub title: String,
+}
+
+impl Reader for ChapterListBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        if version > 0 {
+            reader.skip(4)?; // Reserved
+        }
+        let count = reader.read_u8()?;
+        let mut chapters = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = reader.read_u64()?;
+            let len = reader.read_u8()?;
+            let title = reader.read_bytes(len as usize)?;
+            chapters.push(ChapterListEntry {
+                start,
+                title: String::from_utf8_lossy(&title).into_owned(),
+            });
+        }
+        Ok(Self {
+            version,
+            flags,
+            chapters,
+        })
+    }
+}
+
+impl Writer for ChapterListBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        if self.chapters.len() > u8::MAX as usize {
+            return Err(Error::InvalidData(format!(
+                "Chpl: {:?} chapters, at most 255 fit",
+                self.chapters.len()
+            )));
+        }
+        writer.write_box(BoxType::ChapterList, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            if self.version > 0 {
+                writer.write_u32(0)?;
+            }
+            writer.write_u8(self.chapters.len() as u8)?;
+            for chapter in self.chapters.iter() {
+                // Titles longer than 255 bytes are cut on a character boundary
+                let mut len = chapter.title.len().min(u8::MAX as usize);
+                while !chapter.title.is_char_boundary(len) {
+                    len -= 1;
+                }
+                writer.write_u64(chapter.start)?;
+                writer.write_u8(len as u8)?;
+                writer.write_bytes(&chapter.title.as_bytes()[..len])?;
+            }
+            Ok(())
+        })
+    }
+}
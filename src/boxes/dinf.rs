@@ -1,6 +1,8 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{dref::DataReferenceBox, BoxHeader, BoxReader, BoxType, Error, Reader};
+use crate::{
+    dref::DataReferenceBox, BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/data_information_atom
 #[derive(Clone, Debug)]
@@ -24,3 +26,12 @@ impl Reader for DataInfoBox {
         })
     }
 }
+
+impl Writer for DataInfoBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::DataInfo, |writer| {
+            self.data_reference.write(writer)?;
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, BoxType, Error, Reader, HEADER_LENGTH};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer, HEADER_LENGTH};
 
 // https://developer.apple.com/documentation/quicktime-file-format/media_data_reference_atom
 #[derive(Clone, Debug)]
@@ -25,7 +25,6 @@ impl Reader for DataReferenceBox {
         let mut references = Vec::with_capacity(entry_count as usize);
         for _i in 0..entry_count  {
             if content_parsed_size >= header.size {
-                println!("Dref: wrong size {:?}", content_parsed_size);
                 break;
             }
             let child_header = match BoxHeader::read(reader) {
@@ -43,8 +42,7 @@ impl Reader for DataReferenceBox {
                     references.push(Reference::Url(url_box));
                 },
                 _ => {
-                    println!("Dref: unknown box {:?}", child_header);
-                    header.skip_content(reader, 0)?;
+                    child_header.skip_content(reader, 0)?;
                 }
             };
             content_parsed_size += child_header.size;
@@ -77,4 +75,32 @@ impl Reader for UrlBox {
             location,
         })
     }
-}
\ No newline at end of file
+}
+
+impl Writer for DataReferenceBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::DataRef, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.references.len() as u32)?;
+            for reference in self.references.iter() {
+                match reference {
+                    Reference::Url(url_box) => url_box.write(writer)?,
+                };
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Writer for UrlBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::UrlRef, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            // Self contained references (flag 1) carry no location
+            if !self.location.is_empty() {
+                writer.write_cstring(&self.location)?;
+            }
+            Ok(())
+        })
+    }
+}
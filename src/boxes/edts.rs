@@ -1,6 +1,8 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, BoxType, EditListBox, Error, Reader, HEADER_LENGTH};
+use crate::{
+    BoxHeader, BoxReader, BoxType, BoxWriter, EditListBox, Error, Reader, Writer, HEADER_LENGTH,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/edit_atom
 #[derive(Clone, Debug)]
@@ -27,3 +29,12 @@ impl Reader for EditBox {
         })
     }
 }
+
+impl Writer for EditBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Edit, |writer| {
+            self.list.write(writer)?;
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/edit_atom/edit_list_atom
 #[derive(Clone, Debug)]
@@ -59,3 +59,28 @@ impl Reader for EditListBox {
         })
     }
 }
+
+impl Writer for EditListBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::EditList, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.entries.len() as u32)?;
+            for entry in self.entries.iter() {
+                match self.version {
+                    0 => {
+                        writer.write_u32(entry.segment_duration as u32)?;
+                        writer.write_i32(entry.media_time as i32)?;
+                    }
+                    1 => {
+                        writer.write_u64(entry.segment_duration)?;
+                        writer.write_i64(entry.media_time)?;
+                    }
+                    _ => return Err(Error::InvalidData(format!("Elst: unknown version {:?}", self.version))),
+                }
+                writer.write_u16(entry.media_rate_integer)?;
+                writer.write_u16(entry.media_rate_fraction)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,362 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+pub const ES_DESCRIPTOR_TAG: u8 = 0x03;
+pub const DECODER_CONFIG_DESCRIPTOR_TAG: u8 = 0x04;
+pub const DECODER_SPECIFIC_INFO_TAG: u8 = 0x05;
+pub const SL_CONFIG_DESCRIPTOR_TAG: u8 = 0x06;
+
+// objectTypeIndication values of the MPEG-4 audio codecs
+pub const OBJECT_TYPE_MPEG4_AUDIO: u8 = 0x40;
+pub const OBJECT_TYPE_MPEG2_AAC_MAIN: u8 = 0x66;
+pub const OBJECT_TYPE_MPEG2_AAC_LC: u8 = 0x67;
+pub const OBJECT_TYPE_MPEG2_AAC_SSR: u8 = 0x68;
+pub const OBJECT_TYPE_MPEG1_AUDIO: u8 = 0x6B;
+
+// ISO/IEC 14496-14 5.6 ES Descriptor Box
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EsdsBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub es_descriptor: EsDescriptor,
+    pub size_length: u8, // Bytes used to encode descriptor sizes, 1 to 4
+}
+
+// ISO/IEC 14496-1 7.2.6.5 ES_Descriptor
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct EsDescriptor {
+    pub es_id: u16,
+    pub stream_priority: u8,
+    pub depends_on_es_id: Option<u16>,
+    pub url: Option<String>,
+    pub ocr_es_id: Option<u16>,
+    pub decoder_config: Option<DecoderConfigDescriptor>,
+    pub sl_config: Option<Vec<u8>>,
+}
+
+// ISO/IEC 14496-1 7.2.6.6 DecoderConfigDescriptor
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct DecoderConfigDescriptor {
+    pub object_type_indication: u8,
+    pub stream_type: u8,
+    pub up_stream: bool,
+    pub buffer_size_db: u32, // 24 bits
+    pub max_bitrate: u32,
+    pub avg_bitrate: u32,
+    pub decoder_specific_info: Option<Vec<u8>>,
+}
+
+// Read a descriptor tag and its expandable size, returns (tag, size, header length)
+fn read_descriptor_header<T: Read + Seek>(reader: &mut BoxReader<T>) -> Result<(u8, u32, u8), Error> {
+    let tag = reader.read_u8()?;
+    let mut size: u32 = 0;
+    for i in 1..=4 {
+        let byte = reader.read_u8()?;
+        size = size << 7 | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok((tag, size, i));
+        }
+    }
+    Err(Error::InvalidData("Esds: descriptor size is longer than 4 bytes".to_owned()))
+}
+
+// End of a descriptor of size bytes starting at the reader position, it must not overrun
+// the descriptor or the box that contains it
+fn check_descriptor_end<T: Read + Seek>(
+    reader: &mut BoxReader<T>,
+    tag: u8,
+    size: u32,
+    parent_end: u64,
+) -> Result<u64, Error> {
+    let end = reader.stream_position()? + size as u64;
+    if end > parent_end {
+        return Err(Error::InvalidData(format!(
+            "Esds: descriptor {:?} of {:?} bytes overruns its parent",
+            tag, size
+        )));
+    }
+    Ok(end)
+}
+
+// Sizes are written on at least size_length bytes, more when they do not fit
+fn write_descriptor_header<W: Write + ?Sized>(
+    writer: &mut BoxWriter<W>,
+    tag: u8,
+    size: usize,
+    size_length: u8,
+) -> Result<(), Error> {
+    if size >= 1 << 28 {
+        return Err(Error::InvalidData(format!("Esds: descriptor size {:?} is too large", size)));
+    }
+    let needed = (1..4).find(|&length| size < 1 << (7 * length)).unwrap_or(4) as u8;
+    writer.write_u8(tag)?;
+    let size_length = size_length.clamp(needed, 4);
+    for i in (0..size_length).rev() {
+        let byte = ((size >> (7 * i as usize)) & 0x7F) as u8;
+        writer.write_u8(if i > 0 { byte | 0x80 } else { byte })?;
+    }
+    Ok(())
+}
+
+fn write_descriptor<W: Write + ?Sized, F>(
+    writer: &mut BoxWriter<W>,
+    tag: u8,
+    size_length: u8,
+    content: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&mut BoxWriter<Vec<u8>>) -> Result<(), Error>,
+{
+    let mut buf: Vec<u8> = Vec::new();
+    content(&mut BoxWriter::new(&mut buf))?;
+    write_descriptor_header(writer, tag, buf.len(), size_length)?;
+    writer.write_bytes(&buf)
+}
+
+impl Reader for EsdsBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let end = header.start + header.size;
+
+        let (tag, size, size_length) = read_descriptor_header(reader)?;
+        if tag != ES_DESCRIPTOR_TAG {
+            return Err(Error::InvalidData(format!("Esds: invalid descriptor tag {:?}", tag)));
+        }
+        let descriptor_end = check_descriptor_end(reader, tag, size, end)?;
+        let mut es_descriptor = EsDescriptor {
+            es_id: reader.read_u16()?,
+            ..Default::default()
+        };
+        let es_flags = reader.read_u8()?;
+        es_descriptor.stream_priority = es_flags & 0x1F;
+        if es_flags & 0x80 != 0 {
+            es_descriptor.depends_on_es_id = Some(reader.read_u16()?);
+        }
+        if es_flags & 0x40 != 0 {
+            let len = reader.read_u8()?;
+            es_descriptor.url = Some(reader.read_string(len as usize)?);
+        }
+        if es_flags & 0x20 != 0 {
+            es_descriptor.ocr_es_id = Some(reader.read_u16()?);
+        }
+
+        while reader.stream_position()? + 2 <= descriptor_end {
+            let (tag, size, _) = read_descriptor_header(reader)?;
+            let child_end = check_descriptor_end(reader, tag, size, descriptor_end)?;
+            match tag {
+                DECODER_CONFIG_DESCRIPTOR_TAG => {
+                    let object_type_indication = reader.read_u8()?;
+                    let stream = reader.read_u8()?;
+                    let buffer_size_db = (reader.read_u8()? as u32) << 16 | reader.read_u16()? as u32;
+                    let max_bitrate = reader.read_u32()?;
+                    let avg_bitrate = reader.read_u32()?;
+                    let mut decoder_specific_info = None;
+                    while reader.stream_position()? + 2 <= child_end {
+                        let (tag, size, _) = read_descriptor_header(reader)?;
+                        check_descriptor_end(reader, tag, size, child_end)?;
+                        match tag {
+                            DECODER_SPECIFIC_INFO_TAG => {
+                                decoder_specific_info = Some(reader.read_bytes(size as usize)?);
+                            }
+                            _ => reader.skip(size as u64)?,
+                        }
+                    }
+                    es_descriptor.decoder_config = Some(DecoderConfigDescriptor {
+                        object_type_indication,
+                        stream_type: stream >> 2,
+                        up_stream: stream & 0x02 != 0,
+                        buffer_size_db,
+                        max_bitrate,
+                        avg_bitrate,
+                        decoder_specific_info,
+                    });
+                }
+                SL_CONFIG_DESCRIPTOR_TAG => {
+                    es_descriptor.sl_config = Some(reader.read_bytes(size as usize)?);
+                }
+                _ => (),
+            }
+            let position = reader.stream_position()?;
+            if position < child_end {
+                reader.skip(child_end - position)?;
+            }
+        }
+        let position = reader.stream_position()?;
+        if position < end {
+            reader.skip(end - position)?;
+        }
+
+        Ok(Self {
+            version,
+            flags,
+
+            es_descriptor,
+            size_length,
+        })
+    }
+}
+
+impl Writer for EsdsBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        let size_length = self.size_length;
+        let es = &self.es_descriptor;
+        writer.write_box(BoxType::ElementaryStreamDescriptor, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            write_descriptor(writer, ES_DESCRIPTOR_TAG, size_length, |writer| {
+                writer.write_u16(es.es_id)?;
+                let es_flags = (es.depends_on_es_id.is_some() as u8) << 7
+                    | (es.url.is_some() as u8) << 6
+                    | (es.ocr_es_id.is_some() as u8) << 5
+                    | es.stream_priority;
+                writer.write_u8(es_flags)?;
+                if let Some(depends_on_es_id) = es.depends_on_es_id {
+                    writer.write_u16(depends_on_es_id)?;
+                }
+                if let Some(url) = &es.url {
+                    let len = u8::try_from(url.len()).map_err(|_| {
+                        Error::InvalidData("Esds: url is longer than 255 bytes".to_owned())
+                    })?;
+                    writer.write_u8(len)?;
+                    writer.write_bytes(url.as_bytes())?;
+                }
+                if let Some(ocr_es_id) = es.ocr_es_id {
+                    writer.write_u16(ocr_es_id)?;
+                }
+                if let Some(config) = &es.decoder_config {
+                    write_descriptor(writer, DECODER_CONFIG_DESCRIPTOR_TAG, size_length, |writer| {
+                        writer.write_u8(config.object_type_indication)?;
+                        writer.write_u8(config.stream_type << 2 | (config.up_stream as u8) << 1 | 1)?;
+                        writer.write_u8((config.buffer_size_db >> 16) as u8)?;
+                        writer.write_u16(config.buffer_size_db as u16)?;
+                        writer.write_u32(config.max_bitrate)?;
+                        writer.write_u32(config.avg_bitrate)?;
+                        if let Some(info) = &config.decoder_specific_info {
+                            write_descriptor(writer, DECODER_SPECIFIC_INFO_TAG, size_length, |writer| {
+                                writer.write_bytes(info)
+                            })?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                if let Some(sl_config) = &es.sl_config {
+                    write_descriptor(writer, SL_CONFIG_DESCRIPTOR_TAG, size_length, |writer| {
+                        writer.write_bytes(sl_config)
+                    })?;
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+impl DecoderConfigDescriptor {
+    pub fn is_mpeg4_audio(&self) -> bool {
+        matches!(
+            self.object_type_indication,
+            OBJECT_TYPE_MPEG4_AUDIO
+                | OBJECT_TYPE_MPEG2_AAC_MAIN
+                | OBJECT_TYPE_MPEG2_AAC_LC
+                | OBJECT_TYPE_MPEG2_AAC_SSR
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn esds(size_length: u8, info: Vec<u8>) -> EsdsBox {
+        EsdsBox {
+            version: 0,
+            flags: 0,
+            es_descriptor: EsDescriptor {
+                es_id: 1,
+                decoder_config: Some(DecoderConfigDescriptor {
+                    object_type_indication: OBJECT_TYPE_MPEG4_AUDIO,
+                    stream_type: 5,
+                    max_bitrate: 128000,
+                    avg_bitrate: 128000,
+                    decoder_specific_info: Some(info),
+                    ..Default::default()
+                }),
+                sl_config: Some(vec![2]),
+                ..Default::default()
+            },
+            size_length,
+        }
+    }
+
+    fn roundtrip(esds: &EsdsBox) -> (Vec<u8>, EsdsBox) {
+        let mut data: Vec<u8> = Vec::new();
+        esds.write(&mut BoxWriter::new(&mut data)).unwrap();
+        let mut src = Cursor::new(&data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let read = EsdsBox::read(&mut reader, header).unwrap();
+        (data, read)
+    }
+
+    #[test]
+    fn sizes_on_requested_length() {
+        // ffmpeg style 4 bytes sizes
+        let esds = esds(4, vec![0x12, 0x10]);
+        let (data, read) = roundtrip(&esds);
+        assert_eq!(data[12..17], [ES_DESCRIPTOR_TAG, 0x80, 0x80, 0x80, 34]);
+        assert_eq!(read, esds);
+    }
+
+    #[test]
+    fn sizes_grow_when_they_do_not_fit() {
+        let esds = esds(1, vec![0xAB; 200]);
+        let (data, read) = roundtrip(&esds);
+        // 3 + 219 + 3 bytes of content need 2 bytes, as do the decoder config and info
+        assert_eq!(data[12..15], [ES_DESCRIPTOR_TAG, 0x81, 0x61]);
+        assert_eq!(data[18..21], [DECODER_CONFIG_DESCRIPTOR_TAG, 0x81, 0x58]);
+        assert_eq!(read.es_descriptor, esds.es_descriptor);
+    }
+
+    #[test]
+    fn fixture_roundtrip() {
+        // mp4a esds of testdata/gen.py
+        let data: &[u8] = &[
+            0, 0, 0, 39, b'e', b's', b'd', b's', 0, 0, 0, 0, 3, 25, 0, 2, 0, 4, 17, 0x40, 0x15, 0,
+            0, 0, 0, 1, 0xF4, 0, 0, 1, 0xF4, 0, 5, 2, 0x12, 0x10, 6, 1, 2,
+        ];
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let esds = EsdsBox::read(&mut reader, header).unwrap();
+        assert_eq!(esds.size_length, 1);
+        let (written, _) = roundtrip(&esds);
+        assert_eq!(written, data);
+    }
+    #[test]
+    fn descriptor_overruns_rejected() {
+        // testdata/gen.py, the mp4a esds is the only one of the file
+        let mut data = include_bytes!("../../testdata/progressive.mp4").to_vec();
+        let start = data.windows(4).position(|name| name == b"esds").unwrap() - 4;
+        let esds = data[start..start + 39].to_vec();
+        let read = |data: &[u8]| {
+            let mut src = Cursor::new(data);
+            let mut reader = BoxReader::new(&mut src);
+            let header = BoxHeader::read(&mut reader).unwrap();
+            EsdsBox::read(&mut reader, header)
+        };
+        assert!(read(&esds).is_ok());
+
+        // DecoderSpecificInfo past the decoder config, SLConfig and ES descriptor past the box
+        for (offset, size) in [(33, 0x20), (37, 2), (13, 26)] {
+            let mut esds = esds.clone();
+            esds[offset] = size;
+            assert!(matches!(read(&esds), Err(Error::InvalidData(_))));
+        }
+
+        // The whole file fails to parse rather than reading the following boxes
+        data[start + 33] = 0x20;
+        assert!(crate::Mp4::parse(&mut Cursor::new(&data)).is_err());
+    }
+}
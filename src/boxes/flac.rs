@@ -0,0 +1,227 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, stsd::AudioSampleEntry, BitReader, BoxHeader, BoxReader, BoxType, BoxWriter,
+    Error, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+pub const FLAC_METADATA_STREAMINFO: u8 = 0;
+
+// Encapsulation of FLAC in ISO Base Media File Format 3.3.1 FLAC Sample Entry
+#[derive(Clone, Debug)]
+pub struct FlacSampleEntry {
+    pub audio: AudioSampleEntry,
+    pub config: FlacSpecificBox,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, chnl...
+}
+
+impl Reader for FlacSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut config: Option<FlacSpecificBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::FlacSpecific => {
+                    config = Some(FlacSpecificBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Flac: dfLa box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            audio,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Writer for FlacSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Flac, |writer| {
+            self.audio.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// Encapsulation of FLAC in ISO Base Media File Format 3.3.2 FLAC Specific Box
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct FlacSpecificBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub blocks: Vec<FlacMetadataBlock>, // STREAMINFO first
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct FlacMetadataBlock {
+    pub block_type: u8,
+    pub data: Vec<u8>,
+}
+
+// FLAC format METADATA_BLOCK_STREAMINFO
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct FlacStreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub bits_per_sample: u8,
+    pub total_samples: u64,
+    pub md5: Vec<u8>,
+}
+
+impl FlacSpecificBox {
+    pub fn stream_info(&self) -> Option<Result<FlacStreamInfo, Error>> {
+        let block = self.blocks.iter().find(|block| block.block_type == FLAC_METADATA_STREAMINFO)?;
+        Some(FlacStreamInfo::parse(&block.data))
+    }
+
+    // "fLaC" marker followed by the metadata blocks, as found at the start of a FLAC stream
+    pub fn stream_header(&self) -> Vec<u8> {
+        let mut header = b"fLaC".to_vec();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let is_last = i + 1 == self.blocks.len();
+            header.push((is_last as u8) << 7 | block.block_type);
+            header.extend_from_slice(&(block.data.len() as u32).to_be_bytes()[1..]);
+            header.extend_from_slice(&block.data);
+        }
+        header
+    }
+}
+
+impl FlacStreamInfo {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut bits = BitReader::new(data);
+        Ok(Self {
+            min_block_size: bits.read_bits(16)? as u16,
+            max_block_size: bits.read_bits(16)? as u16,
+            min_frame_size: bits.read_bits(24)?,
+            max_frame_size: bits.read_bits(24)?,
+            sample_rate: bits.read_bits(20)?,
+            channels: bits.read_u8(3)? + 1,
+            bits_per_sample: bits.read_u8(5)? + 1,
+            total_samples: bits.read_bits_u64(36)?,
+            md5: data.get(18..34).ok_or(Error::EOF())?.to_vec(),
+        })
+    }
+}
+
+impl Reader for FlacSpecificBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let mut parsed_size = HEADER_LENGTH + 4;
+        let mut blocks: Vec<FlacMetadataBlock> = Vec::new();
+        while parsed_size + 4 <= header.size {
+            let block_header = reader.read_u32()?;
+            let len = block_header & 0x00FFFFFF;
+            if parsed_size + 4 + len as u64 > header.size {
+                return Err(Error::InvalidData(format!("DfLa: metadata block of {:?} bytes overruns the box", len)));
+            }
+            blocks.push(FlacMetadataBlock {
+                block_type: (block_header >> 24) as u8 & 0x7F,
+                data: reader.read_bytes(len as usize)?,
+            });
+            parsed_size += 4 + len as u64;
+            if block_header & 0x80000000 != 0 {
+                break;
+            }
+        }
+        if parsed_size < header.size {
+            reader.skip(header.size - parsed_size)?;
+        }
+
+        Ok(Self {
+            version,
+            flags,
+
+            blocks,
+        })
+    }
+}
+
+impl Writer for FlacSpecificBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::FlacSpecific, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            // Same layout as the stream header, without the marker
+            writer.write_bytes(&self.stream_header()[4..])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{AudioCodec, Mp4, SampleEntry};
+
+    // testdata/gencodecs.py audio.mp4 audio: track 2 is 24 bit 96 kHz FLAC, STREAMINFO then VORBIS_COMMENT
+    const AUDIO: &[u8] = include_bytes!("../../testdata/audio.mp4");
+
+    fn read_dfla(data: &[u8]) -> Result<FlacSpecificBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        FlacSpecificBox::read(&mut reader, header)
+    }
+
+    fn flac_entry(mp4: &Mp4) -> &FlacSampleEntry {
+        match &mp4.track(2).unwrap().sample_entries()[0] {
+            SampleEntry::Audio(AudioCodec::Flac(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    #[test]
+    fn flac_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let entry = flac_entry(&mp4);
+        let types: Vec<u8> = entry.config.blocks.iter().map(|block| block.block_type).collect();
+        assert_eq!(types, vec![FLAC_METADATA_STREAMINFO, 4]);
+        let info = entry.config.stream_info().unwrap().unwrap();
+        assert_eq!((info.min_block_size, info.max_block_size), (4096, 4096));
+        assert_eq!((info.min_frame_size, info.max_frame_size), (14, 8000));
+        assert_eq!((info.sample_rate, info.channels, info.bits_per_sample), (96000, 2, 24));
+        assert_eq!(info.total_samples, 960000);
+        assert_eq!(info.md5, (0..16).collect::<Vec<u8>>());
+
+        // Only the last block carries the last metadata block flag
+        let header = entry.config.stream_header();
+        assert_eq!(&header[..8], b"fLaC\x00\x00\x00\x22");
+        assert_eq!(header[4 + 4 + 34], 0x84);
+
+        let mut written: Vec<u8> = Vec::new();
+        entry.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = AUDIO.windows(4).position(|name| name == b"fLaC").unwrap() - 4;
+        assert_eq!(&AUDIO[start..start + written.len()], &written[..]);
+    }
+
+    #[test]
+    fn overrunning_metadata_block_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        flac_entry(&mp4).config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(&read_dfla(&data).unwrap(), &flac_entry(&mp4).config);
+
+        // Claim 2 more bytes in the VORBIS_COMMENT block than the box holds
+        data[12 + 4 + 34 + 3] += 2;
+        data.extend_from_slice(&[0; 2]);
+        assert!(matches!(read_dfla(&data), Err(Error::InvalidData(_))));
+        let too_short = [0, 0, 0, 14, b'd', b'f', b'L', b'a', 0, 0, 0, 0, 0, 1];
+        assert!(read_dfla(&too_short).unwrap().stream_info().is_none());
+    }
+}
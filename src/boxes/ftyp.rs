@@ -1,49 +1,29 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct FtypBox {
     pub major_brand: String,
     pub minor_brand: u32,
     pub compatible_brands: Vec<String>,
 }
 
-impl Default for FtypBox {
-    fn default() -> Self {
-        Self {
-            major_brand: Default::default(),
-            minor_brand: Default::default(),
-            compatible_brands: Default::default(),
-        }
-    }
-}
-
 impl Reader for FtypBox {
     fn read<'a, T: Read + Seek>(
         reader: &mut BoxReader<'a, T>,
         header: BoxHeader,
     ) -> Result<Self, Error> {
         let size = header.size;
-        if size < 16 || size % 4 != 0 {
+        if size < 16 || !size.is_multiple_of(4) {
             return Err(Error::InvalidData("ftyp has a wrong size".to_owned()));
         }
-        let major_brand = match reader.read_string(4) {
-            Ok(value) => value,
-            Err(error) => return Err(error),
-        };
-        let minor_brand = match reader.read_u32() {
-            Ok(value) => value,
-            Err(error) => return Err(error),
-        };
+        let major_brand = reader.read_string(4)?;
+        let minor_brand = reader.read_u32()?;
         let size = (size - 16) / 4;
         let mut compatible_brands = vec![];
         for _ in 0..size {
-            let brand = match reader.read_string(4) {
-                Ok(value) => value,
-                Err(error) => return Err(error),
-            };
-            compatible_brands.push(brand);
+            compatible_brands.push(reader.read_string(4)?);
         }
 
         Ok(Self {
@@ -53,3 +33,16 @@ impl Reader for FtypBox {
         })
     }
 }
+
+impl Writer for FtypBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::FileType, |writer| {
+            writer.write_fourcc(&self.major_brand)?;
+            writer.write_u32(self.minor_brand)?;
+            for brand in self.compatible_brands.iter() {
+                writer.write_fourcc(brand)?;
+            }
+            Ok(())
+        })
+    }
+}
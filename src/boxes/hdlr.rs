@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader, HEADER_LENGTH};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer, HEADER_LENGTH};
 
 // https://developer.apple.com/documentation/quicktime-file-format/handler_reference_atom
 #[derive(Clone, Debug)]
@@ -11,6 +11,8 @@ pub struct HandlerBox {
     pub component_type: String, // FourCC
     pub handler: String, // FourCC
     pub name: String,
+    // QuickTime files may omit the terminating NUL of the name
+    pub null_terminated: bool,
 }
 
 impl Reader for HandlerBox {
@@ -20,14 +22,35 @@ impl Reader for HandlerBox {
         let component_type = reader.read_string(4)?;
         let handler = reader.read_string(4)?;
         reader.skip(12)?; // Reserved
-        let len = header.size - HEADER_LENGTH - 4 - 4 - 4 - 12; 
-        let name = reader.read_string(len as usize)?;
+        let len = header
+            .size
+            .checked_sub(HEADER_LENGTH + 4 + 4 + 4 + 12)
+            .ok_or(Error::InvalidData("Hdlr: box is too short".to_owned()))?;
+        let data = reader.read_bytes(len as usize)?;
+        let end = data.iter().position(|&byte| byte == 0);
+        let name = String::from_utf8_lossy(&data[..end.unwrap_or(data.len())]).into_owned();
         Ok(Self {
             version,
             flags,
             component_type,
             handler,
             name,
+            null_terminated: end.is_some(),
+        })
+    }
+}
+
+impl Writer for HandlerBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Handler, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_fourcc(&self.component_type)?;
+            writer.write_fourcc(&self.handler)?;
+            writer.write_zeros(12)?; // Reserved
+            match self.null_terminated {
+                true => writer.write_cstring(&self.name),
+                false => writer.write_bytes(self.name.as_bytes()),
+            }
         })
     }
 }
@@ -0,0 +1,329 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children,
+    stsd::{VisualSampleEntry, VISUAL_SAMPLE_ENTRY_LENGTH},
+    BoxHeader, BoxReader, BoxType, BoxWriter, Error, HevcSequenceParameterSet,
+    HevcVideoParameterSet, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+pub const HEVC_NAL_VPS: u8 = 32;
+pub const HEVC_NAL_SPS: u8 = 33;
+pub const HEVC_NAL_PPS: u8 = 34;
+pub const HEVC_NAL_PREFIX_SEI: u8 = 39;
+pub const HEVC_NAL_SUFFIX_SEI: u8 = 40;
+
+// ISO/IEC 14496-15 8.4.1 HEVC Sample Entry, hvc1 or hev1
+#[derive(Clone, Debug)]
+pub struct HevcSampleEntry {
+    pub name: BoxType,
+    pub visual: VisualSampleEntry,
+    pub config: HevcDecoderConfigurationRecord,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, pasp, colr...
+}
+
+impl Reader for HevcSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let visual = VisualSampleEntry::read(reader, header)?;
+        let mut config: Option<HevcDecoderConfigurationRecord> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            VISUAL_SAMPLE_ENTRY_LENGTH,
+            |reader, child_header| match child_header.name {
+                BoxType::HevcConfiguration => {
+                    config = Some(HevcDecoderConfigurationRecord::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound(format!("{:?}: hvcC box is mandatory", header.name)));
+        }
+        Ok(Self {
+            name: header.name,
+            visual,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl HevcSampleEntry {
+    pub fn sequence_parameter_set(&self) -> Option<Result<HevcSequenceParameterSet, Error>> {
+        self.config.sequence_parameter_set()
+    }
+
+    // ISO/IEC 14496-15 E.3 codecs parameter: hvc1.A1.6.L93.B0
+    pub fn codec_string(&self) -> String {
+        let config = &self.config;
+        let profile_space = ["", "A", "B", "C"][config.general_profile_space as usize & 0x03];
+        let tier = if config.general_tier_flag { 'H' } else { 'L' };
+        let mut codec = format!(
+            "{}.{}{}.{:X}.{}{}",
+            self.name,
+            profile_space,
+            config.general_profile_idc,
+            config.general_profile_compatibility_flags.reverse_bits(),
+            tier,
+            config.general_level_idc
+        );
+        // Constraint bytes, trailing zero bytes omitted
+        let constraints = &config.general_constraint_indicator_flags.to_be_bytes()[2..];
+        let len = constraints.iter().rposition(|byte| *byte != 0).map_or(0, |i| i + 1);
+        for byte in &constraints[..len] {
+            codec.push_str(&format!(".{:02X}", byte));
+        }
+        codec
+    }
+}
+
+impl Writer for HevcSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.name, |writer| {
+            self.visual.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ISO/IEC 14496-15 8.3.3.1 HEVC Decoder Configuration Record
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcDecoderConfigurationRecord {
+    pub configuration_version: u8,
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    pub general_constraint_indicator_flags: u64, // 48 bits
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub avg_frame_rate: u16, // frames per 256 seconds
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    pub length_size_minus_one: u8,
+    pub arrays: Vec<HevcNalArray>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcNalArray {
+    pub array_completeness: bool,
+    pub nal_unit_type: u8,
+    pub nalus: Vec<Vec<u8>>,
+}
+
+impl HevcDecoderConfigurationRecord {
+    pub fn nal_length_size(&self) -> u8 {
+        self.length_size_minus_one + 1
+    }
+
+    pub fn nal_units(&self, nal_unit_type: u8) -> impl Iterator<Item = &Vec<u8>> {
+        self.arrays
+            .iter()
+            .filter(move |array| array.nal_unit_type == nal_unit_type)
+            .flat_map(|array| array.nalus.iter())
+    }
+
+    // Decode the first video parameter set
+    pub fn video_parameter_set(&self) -> Option<Result<HevcVideoParameterSet, Error>> {
+        Some(HevcVideoParameterSet::parse(self.vps().next()?))
+    }
+
+    // Decode the first sequence parameter set
+    pub fn sequence_parameter_set(&self) -> Option<Result<HevcSequenceParameterSet, Error>> {
+        Some(HevcSequenceParameterSet::parse(self.sps().next()?))
+    }
+
+    pub fn vps(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.nal_units(HEVC_NAL_VPS)
+    }
+
+    pub fn sps(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.nal_units(HEVC_NAL_SPS)
+    }
+
+    pub fn pps(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.nal_units(HEVC_NAL_PPS)
+    }
+
+    pub fn sei(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.nal_units(HEVC_NAL_PREFIX_SEI)
+            .chain(self.nal_units(HEVC_NAL_SUFFIX_SEI))
+    }
+}
+
+impl Reader for HevcDecoderConfigurationRecord {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let content_size = header.size - HEADER_LENGTH;
+        let configuration_version = reader.read_u8()?;
+        let profile = reader.read_u8()?;
+        let general_profile_compatibility_flags = reader.read_u32()?;
+        let general_constraint_indicator_flags =
+            (reader.read_u16()? as u64) << 32 | reader.read_u32()? as u64;
+        let general_level_idc = reader.read_u8()?;
+        let min_spatial_segmentation_idc = reader.read_u16()? & 0x0FFF;
+        let parallelism_type = reader.read_u8()? & 0x03;
+        let chroma_format_idc = reader.read_u8()? & 0x03;
+        let bit_depth_luma_minus8 = reader.read_u8()? & 0x07;
+        let bit_depth_chroma_minus8 = reader.read_u8()? & 0x07;
+        let avg_frame_rate = reader.read_u16()?;
+        let layers = reader.read_u8()?;
+        let num_of_arrays = reader.read_u8()?;
+        let mut parsed_size: u64 = 23;
+
+        let mut arrays = Vec::with_capacity(num_of_arrays as usize);
+        for _ in 0..num_of_arrays {
+            let array_type = reader.read_u8()?;
+            let num_nalus = reader.read_u16()?;
+            parsed_size += 3;
+            let mut nalus = Vec::with_capacity(num_nalus as usize);
+            for _ in 0..num_nalus {
+                let len = reader.read_u16()?;
+                parsed_size += 2 + len as u64;
+                if parsed_size > content_size {
+                    return Err(Error::InvalidData(format!("HvcC: NAL unit of {:?} bytes overruns the box", len)));
+                }
+                nalus.push(reader.read_bytes(len as usize)?);
+            }
+            arrays.push(HevcNalArray {
+                array_completeness: array_type & 0x80 != 0,
+                nal_unit_type: array_type & 0x3F,
+                nalus,
+            });
+        }
+        if parsed_size > content_size {
+            return Err(Error::InvalidData(format!("HvcC: {:?} bytes overrun the box", parsed_size)));
+        }
+        if parsed_size < content_size {
+            reader.skip(content_size - parsed_size)?;
+        }
+
+        Ok(Self {
+            configuration_version,
+            general_profile_space: profile >> 6,
+            general_tier_flag: profile & 0x20 != 0,
+            general_profile_idc: profile & 0x1F,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+            min_spatial_segmentation_idc,
+            parallelism_type,
+            chroma_format_idc,
+            bit_depth_luma_minus8,
+            bit_depth_chroma_minus8,
+            avg_frame_rate,
+            constant_frame_rate: layers >> 6,
+            num_temporal_layers: (layers >> 3) & 0x07,
+            temporal_id_nested: layers & 0x04 != 0,
+            length_size_minus_one: layers & 0x03,
+            arrays,
+        })
+    }
+}
+
+impl Writer for HevcDecoderConfigurationRecord {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::HevcConfiguration, |writer| {
+            writer.write_u8(self.configuration_version)?;
+            writer.write_u8(
+                self.general_profile_space << 6
+                    | (self.general_tier_flag as u8) << 5
+                    | self.general_profile_idc,
+            )?;
+            writer.write_u32(self.general_profile_compatibility_flags)?;
+            writer.write_u16((self.general_constraint_indicator_flags >> 32) as u16)?;
+            writer.write_u32(self.general_constraint_indicator_flags as u32)?;
+            writer.write_u8(self.general_level_idc)?;
+            writer.write_u16(0xF000 | self.min_spatial_segmentation_idc)?;
+            writer.write_u8(0xFC | self.parallelism_type)?;
+            writer.write_u8(0xFC | self.chroma_format_idc)?;
+            writer.write_u8(0xF8 | self.bit_depth_luma_minus8)?;
+            writer.write_u8(0xF8 | self.bit_depth_chroma_minus8)?;
+            writer.write_u16(self.avg_frame_rate)?;
+            writer.write_u8(
+                self.constant_frame_rate << 6
+                    | self.num_temporal_layers << 3
+                    | (self.temporal_id_nested as u8) << 2
+                    | self.length_size_minus_one,
+            )?;
+            writer.write_u8(self.arrays.len() as u8)?;
+            for array in self.arrays.iter() {
+                writer.write_u8((array.array_completeness as u8) << 7 | array.nal_unit_type)?;
+                writer.write_u16(array.nalus.len() as u16)?;
+                for nalu in array.nalus.iter() {
+                    writer.write_u16(nalu.len() as u16)?;
+                    writer.write_bytes(nalu)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleEntry, VideoCodec};
+
+    // testdata/gen.py hevc.mp4 hevc: hvc1 with VPS, SPS and PPS arrays then pasp
+    const HEVC: &[u8] = include_bytes!("../../testdata/hevc.mp4");
+
+    fn read_hvcc(data: &[u8]) -> Result<HevcDecoderConfigurationRecord, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        HevcDecoderConfigurationRecord::read(&mut reader, header)
+    }
+
+    #[test]
+    fn hvc1_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(HEVC)).unwrap();
+        let entry = match &mp4.track(1).unwrap().sample_entries()[0] {
+            SampleEntry::Video(VideoCodec::Hevc(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        };
+        assert_eq!(entry.codec_string(), "hvc1.1.6.L93.90");
+        let config = &entry.config;
+        assert_eq!(config.arrays.len(), 3);
+        assert_eq!((config.vps().count(), config.sps().count(), config.pps().count()), (1, 1, 1));
+        assert_eq!(config.sei().count(), 0);
+        let sps = entry.sequence_parameter_set().unwrap().unwrap();
+        assert_eq!((sps.width(), sps.height()), (640, 360));
+        assert_eq!(entry.extensions.len(), 1);
+
+        let mut written: Vec<u8> = Vec::new();
+        entry.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = HEVC.windows(4).position(|name| name == b"hvc1").unwrap() - 4;
+        assert_eq!(&HEVC[start..start + written.len()], &written[..]);
+    }
+
+    #[test]
+    fn overrunning_nal_unit_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(HEVC)).unwrap();
+        let mut config = match &mp4.track(1).unwrap().sample_entries()[0] {
+            SampleEntry::Video(VideoCodec::Hevc(entry)) => entry.config.clone(),
+            entry => panic!("unexpected sample entry {:?}", entry),
+        };
+        config.arrays.truncate(1);
+        let mut data: Vec<u8> = Vec::new();
+        config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(read_hvcc(&data).unwrap(), config);
+
+        // Claim 8 more bytes in the VPS than the box holds
+        let len = data.len();
+        data[len - config.arrays[0].nalus[0].len() - 1] += 8;
+        data.extend_from_slice(&[0; 8]);
+        assert!(matches!(read_hvcc(&data), Err(Error::InvalidData(_))));
+    }
+}
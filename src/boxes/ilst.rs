@@ -0,0 +1,394 @@
+use std::{
+    fmt,
+    io::{Read, Seek, Write},
+};
+
+use crate::{
+    read_children, BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox, Reader, Writer,
+    HEADER_LENGTH,
+};
+
+// Well-known data types, the lower 24 bits of the data atom type indicator
+pub const DATA_TYPE_IMPLICIT: u32 = 0;
+pub const DATA_TYPE_UTF8: u32 = 1;
+pub const DATA_TYPE_UTF16: u32 = 2;
+pub const DATA_TYPE_JPEG: u32 = 13;
+pub const DATA_TYPE_PNG: u32 = 14;
+pub const DATA_TYPE_SIGNED_INTEGER: u32 = 21;
+pub const DATA_TYPE_UNSIGNED_INTEGER: u32 = 22;
+pub const DATA_TYPE_BMP: u32 = 27;
+
+// Key of an ilst item, a four character code or a ---- freeform mean and name
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MetadataKey {
+    Atom(u32),
+    Freeform { mean: String, name: String },
+}
+
+impl MetadataKey {
+    pub const TITLE: Self = Self::Atom(0xA96E616D); // "©nam"
+    pub const ARTIST: Self = Self::Atom(0xA9415254); // "©ART"
+    pub const ALBUM_ARTIST: Self = Self::Atom(0x61415254); // "aART"
+    pub const ALBUM: Self = Self::Atom(0xA9616C62); // "©alb"
+    pub const YEAR: Self = Self::Atom(0xA9646179); // "©day"
+    pub const GENRE: Self = Self::Atom(0xA967656E); // "©gen"
+    pub const COMMENT: Self = Self::Atom(0xA9636D74); // "©cmt"
+    pub const COMPOSER: Self = Self::Atom(0xA9777274); // "©wrt"
+    pub const ENCODER: Self = Self::Atom(0xA9746F6F); // "©too"
+    pub const TRACK_NUMBER: Self = Self::Atom(0x74726B6E); // "trkn"
+    pub const DISC_NUMBER: Self = Self::Atom(0x6469736B); // "disk"
+    pub const COVER_ART: Self = Self::Atom(0x636F7672); // "covr"
+
+    pub fn freeform(mean: &str, name: &str) -> Self {
+        Self::Freeform {
+            mean: mean.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    fn box_type(&self) -> BoxType {
+        match self {
+            Self::Atom(fourcc) => BoxType::from(*fourcc),
+            Self::Freeform { .. } => BoxType::Freeform,
+        }
+    }
+}
+
+// The copyright sign of the iTunes codes is the Latin-1 byte 0xA9
+impl fmt::Display for MetadataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Atom(fourcc) => {
+                let name: String = fourcc.to_be_bytes().iter().map(|&byte| byte as char).collect();
+                f.write_str(&name)
+            }
+            Self::Freeform { mean, name } => write!(f, "----:{}:{}", mean, name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Bmp,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoverArt {
+    pub format: ImageFormat,
+    pub data: Vec<u8>,
+}
+
+// Decoded value of a data atom
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetadataValue {
+    Text(String),
+    Integer(i64),
+    Index { number: u16, total: u16 }, // trkn and disk
+    Image(CoverArt),
+    Binary { type_indicator: u32, data: Vec<u8> },
+}
+
+// Apple ilst item: data atoms under the item key, freeform items add mean and name
+#[derive(Clone, Debug)]
+pub struct MetadataItem {
+    pub key: MetadataKey,
+    pub data: Vec<DataBox>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl MetadataItem {
+    pub fn values(&self) -> impl Iterator<Item = MetadataValue> + '_ {
+        self.data.iter().map(|data| data.value(&self.key))
+    }
+}
+
+fn read_string_box<T: Read + Seek>(
+    reader: &mut BoxReader<T>,
+    header: BoxHeader,
+) -> Result<String, Error> {
+    let len = header
+        .size
+        .checked_sub(HEADER_LENGTH + 4)
+        .ok_or(Error::InvalidData(format!("{:?}: box is too short", header.name)))?;
+    reader.read_header_extra()?;
+    reader.read_string(len as usize)
+}
+
+fn write_string_box<W: Write + ?Sized>(
+    writer: &mut BoxWriter<W>,
+    name: BoxType,
+    value: &str,
+) -> Result<u64, Error> {
+    writer.write_box(name, |writer| {
+        writer.write_header_extra(0, 0)?;
+        writer.write_bytes(value.as_bytes())
+    })
+}
+
+impl Reader for MetadataItem {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut mean: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut data: Vec<DataBox> = Vec::new();
+        let extensions =
+            read_children(reader, header, 0, |reader, child_header| {
+                match child_header.name {
+                    BoxType::Mean => mean = Some(read_string_box(reader, child_header)?),
+                    BoxType::Name => name = Some(read_string_box(reader, child_header)?),
+                    BoxType::Data => data.push(DataBox::read(reader, child_header)?),
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            })?;
+
+        let key = match header.name {
+            BoxType::Freeform => {
+                if mean.is_none() || name.is_none() {
+                    return Err(Error::BoxNotFound(
+                        "Ilst: ---- item needs mean and name boxes".to_owned(),
+                    ));
+                }
+                MetadataKey::Freeform {
+                    mean: mean.unwrap(),
+                    name: name.unwrap(),
+                }
+            }
+            name => MetadataKey::Atom(u32::from(name)),
+        };
+        Ok(Self {
+            key,
+            data,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MetadataItem {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.key.box_type(), |writer| {
+            if let MetadataKey::Freeform { mean, name } = &self.key {
+                write_string_box(writer, BoxType::Mean, mean)?;
+                write_string_box(writer, BoxType::Name, name)?;
+            }
+            self.data.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// Apple data atom: type indicator, locale and value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataBox {
+    pub type_indicator: u32, // type set in the upper 8 bits, well-known type below
+    pub locale: u32,
+    pub value: Vec<u8>,
+}
+
+impl DataBox {
+    // Encode a value the way iTunes stores it under key
+    pub fn new(key: &MetadataKey, value: &MetadataValue) -> Self {
+        let (data_type, value) = match value {
+            MetadataValue::Text(text) => (DATA_TYPE_UTF8, text.as_bytes().to_vec()),
+            MetadataValue::Integer(integer) => {
+                // Shortest of 1, 2, 4 or 8 bytes holding the value
+                let bytes = integer.to_be_bytes();
+                let len = [1, 2, 4]
+                    .into_iter()
+                    .find(|len| *integer >> (len * 8 - 1) == *integer >> 63)
+                    .unwrap_or(8);
+                (DATA_TYPE_SIGNED_INTEGER, bytes[8 - len..].to_vec())
+            }
+            MetadataValue::Index { number, total } => {
+                let mut data = vec![0, 0];
+                data.extend_from_slice(&number.to_be_bytes());
+                data.extend_from_slice(&total.to_be_bytes());
+                // trkn has 2 more reserved bytes than disk
+                if *key != MetadataKey::DISC_NUMBER {
+                    data.extend_from_slice(&[0, 0]);
+                }
+                (DATA_TYPE_IMPLICIT, data)
+            }
+            MetadataValue::Image(image) => {
+                let data_type = match image.format {
+                    ImageFormat::Jpeg => DATA_TYPE_JPEG,
+                    ImageFormat::Png => DATA_TYPE_PNG,
+                    ImageFormat::Bmp => DATA_TYPE_BMP,
+                };
+                (data_type, image.data.clone())
+            }
+            MetadataValue::Binary {
+                type_indicator,
+                data,
+            } => (*type_indicator, data.clone()),
+        };
+        Self {
+            type_indicator: data_type,
+            locale: 0,
+            value,
+        }
+    }
+
+    pub fn data_type(&self) -> u32 {
+        self.type_indicator & 0x00FF_FFFF
+    }
+
+    // Decode the value, trkn and disk carry implicitly typed indexes
+    pub fn value(&self, key: &MetadataKey) -> MetadataValue {
+        let value = &self.value;
+        match self.data_type() {
+            DATA_TYPE_UTF8 => MetadataValue::Text(String::from_utf8_lossy(value).into_owned()),
+            DATA_TYPE_UTF16 => {
+                let units: Vec<u16> = value
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                MetadataValue::Text(String::from_utf16_lossy(&units))
+            }
+            DATA_TYPE_JPEG => self.image(ImageFormat::Jpeg),
+            DATA_TYPE_PNG => self.image(ImageFormat::Png),
+            DATA_TYPE_BMP => self.image(ImageFormat::Bmp),
+            DATA_TYPE_SIGNED_INTEGER if matches!(value.len(), 1..=8) => {
+                // Sign extend from the first byte
+                let fill = if value[0] & 0x80 != 0 { 0xFF } else { 0 };
+                let mut bytes = [fill; 8];
+                bytes[8 - value.len()..].copy_from_slice(value);
+                MetadataValue::Integer(i64::from_be_bytes(bytes))
+            }
+            DATA_TYPE_UNSIGNED_INTEGER if matches!(value.len(), 1..=7) => {
+                let mut bytes = [0; 8];
+                bytes[8 - value.len()..].copy_from_slice(value);
+                MetadataValue::Integer(i64::from_be_bytes(bytes))
+            }
+            DATA_TYPE_IMPLICIT
+                if (*key == MetadataKey::TRACK_NUMBER || *key == MetadataKey::DISC_NUMBER)
+                    && value.len() >= 6 =>
+            {
+                MetadataValue::Index {
+                    number: u16::from_be_bytes([value[2], value[3]]),
+                    total: u16::from_be_bytes([value[4], value[5]]),
+                }
+            }
+            _ => MetadataValue::Binary {
+                type_indicator: self.type_indicator,
+                data: value.clone(),
+            },
+        }
+    }
+
+    fn image(&self, format: ImageFormat) -> MetadataValue {
+        MetadataValue::Image(CoverArt {
+            format,
+            data: self.value.clone(),
+        })
+    }
+}
+
+impl Reader for DataBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let len = header
+            .size
+            .checked_sub(HEADER_LENGTH + 8)
+            .ok_or(Error::InvalidData("Data: box is too short".to_owned()))?;
+        let type_indicator = reader.read_u32()?;
+        let locale = reader.read_u32()?;
+        let value = reader.read_bytes(len as usize)?;
+        Ok(Self {
+            type_indicator,
+            locale,
+            value,
+        })
+    }
+}
+
+impl Writer for DataBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Data, |writer| {
+            writer.write_u32(self.type_indicator)?;
+            writer.write_u32(self.locale)?;
+            writer.write_bytes(&self.value)
+        })
+    }
+}
+
+// Apple item list
+#[derive(Clone, Debug, Default)]
+pub struct ItemListBox {
+    pub items: Vec<MetadataItem>,
+}
+
+impl Reader for ItemListBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut items: Vec<MetadataItem> = Vec::new();
+        read_children(reader, header, 0, |reader, child_header| {
+            items.push(MetadataItem::read(reader, child_header)?);
+            Ok(true)
+        })?;
+        Ok(Self { items })
+    }
+}
+
+impl Writer for ItemListBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::ItemList, |writer| {
+            self.items.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/genmeta.py: text, index, integer, cover art and freeform items
+    const METADATA: &[u8] = include_bytes!("../../testdata/metadata.mp4");
+
+    fn read_ilst(items: &[u8]) -> Result<ItemListBox, Error> {
+        let mut data = ((items.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(b"ilst");
+        data.extend_from_slice(items);
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        ItemListBox::read(&mut reader, header)
+    }
+
+    #[test]
+    fn items_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(METADATA)).unwrap();
+        let metadata = mp4.metadata();
+        assert_eq!(metadata.title(), Some("Title é"));
+        assert_eq!(metadata.album(), Some("Alb"));
+        assert_eq!(metadata.track_number(), Some((3, 12)));
+        assert_eq!(metadata.disc_number(), Some((1, 2)));
+        assert_eq!(metadata.get(&MetadataKey::Atom(0x746D706F)), Some(&MetadataValue::Integer(-120)));
+        assert_eq!(metadata.cover_art().count(), 2);
+        assert_eq!(
+            metadata.freeform("com.apple.iTunes", "iTunNORM"),
+            Some(&MetadataValue::Text(" 0000".to_owned()))
+        );
+
+        let ilst = mp4.moov.udta.as_ref().unwrap().meta.as_ref().unwrap().ilst.as_ref().unwrap();
+        let mut written: Vec<u8> = Vec::new();
+        ilst.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = METADATA.windows(4).position(|name| name == b"ilst").unwrap() - 4;
+        assert_eq!(&METADATA[start..start + written.len()], &written[..]);
+    }
+
+    #[test]
+    fn short_boxes_rejected() {
+        // ©nam item with a data box of 12 bytes, too short for its type and locale
+        let data = [0, 0, 0, 20, 0xA9, b'n', b'a', b'm', 0, 0, 0, 12, b'd', b'a', b't', b'a', 0, 0, 0, 1];
+        assert!(matches!(read_ilst(&data), Err(Error::InvalidData(_))));
+
+        // ---- item with a mean box of 8 bytes, too short for its version and flags
+        let data = [0, 0, 0, 20, b'-', b'-', b'-', b'-', 0, 0, 0, 8, b'm', b'e', b'a', b'n', 0, 0, 0, 0];
+        assert!(matches!(read_ilst(&data), Err(Error::InvalidData(_))));
+    }
+}
@@ -1,6 +1,33 @@
+use std::io::{Read, Seek};
 
-use crate::SkipBox;
+use crate::{BoxHeader, BoxReader, Error, Reader, HEADER_LENGTH};
 
-// https://developer.apple.com/documentation/quicktime-file-format/movie_header_atom
-// TODO
-pub type MediaDataBox = SkipBox;
\ No newline at end of file
+// https://developer.apple.com/documentation/quicktime-file-format/movie_data_atom
+#[derive(Clone, Debug)]
+pub struct MediaDataBox {
+    pub data_offset: u64,
+    pub data_size: u64,
+}
+
+impl Reader for MediaDataBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let data_offset = reader.stream_position()?;
+        // size 0 means the box extends to the end of the file
+        let data_size = if header.size == 0 {
+            reader.skip_to_end()? - data_offset
+        } else {
+            header.skip_content(reader, 0)?;
+            header.size - HEADER_LENGTH
+        };
+        Ok(Self {
+            data_offset,
+            data_size,
+        })
+    }
+}
+
+impl MediaDataBox {
+    pub fn contains(&self, offset: u64, size: u64) -> bool {
+        offset >= self.data_offset && offset + size <= self.data_offset + self.data_size
+    }
+}
@@ -1,9 +1,9 @@
 use std::{
     char::{decode_utf16, REPLACEMENT_CHARACTER},
-    io::{Read, Seek},
+    io::{Read, Seek, Write},
 };
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/media_header_atom
 #[derive(Clone, Debug)]
@@ -81,3 +81,37 @@ fn language_string(language_code: u16) -> String {
 
     lang_str
 }
+
+// Pack an ISO 639-2/T language code, three lowercase letters, on 15 bits
+pub fn language_code(language: &str) -> u16 {
+    let bytes = language.as_bytes();
+    if bytes.len() != 3 || !bytes.iter().all(|b| b.is_ascii_lowercase()) {
+        return language_code("und");
+    }
+    ((bytes[0] - 0x60) as u16) << 10 | ((bytes[1] - 0x60) as u16) << 5 | (bytes[2] - 0x60) as u16
+}
+
+impl Writer for MediaHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MediaHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            match self.version {
+                0 => {
+                    writer.write_u32(self.creation_time as u32)?;
+                    writer.write_u32(self.modification_time as u32)?;
+                    writer.write_u32(self.timescale)?;
+                    writer.write_u32(self.duration as u32)?;
+                }
+                1 => {
+                    writer.write_u64(self.creation_time)?;
+                    writer.write_u64(self.modification_time)?;
+                    writer.write_u32(self.timescale)?;
+                    writer.write_u64(self.duration)?;
+                }
+                _ => return Err(Error::InvalidData(format!("Mdhd: unknown version {:?}", self.version))),
+            }
+            writer.write_u16(self.language_code)?;
+            writer.write_u16(self.quality)
+        })
+    }
+}
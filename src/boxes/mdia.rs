@@ -1,39 +1,65 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{ListBox, BoxHeader, BoxReader, BoxContent, Error, HandlerBox, MediaHeaderBox, MediaInfoBox, Reader};
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    HandlerBox, MediaHeaderBox, MediaInfoBox, RawBox, Reader, Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/media_atom
 #[derive(Clone, Debug)]
 pub struct MediaBox {
     pub media_header: MediaHeaderBox,
     pub handler: Option<HandlerBox>,
-    pub info: Option<MediaInfoBox>
+    pub info: MediaInfoBox,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
 }
 
 impl Reader for MediaBox {
     fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
-        let content = ListBox::read(reader, header)?;
         let mut media_header: Option<MediaHeaderBox> = None;
         let mut handler: Option<HandlerBox> = None;
         let mut info: Option<MediaInfoBox> = None;
-        for child in content.children {
-            match child.content {
-                BoxContent::Mdhd(b) => media_header = Some(b),
-                BoxContent::Hdlr(b) => handler = Some(b),
-                BoxContent::Minf(b) => info = Some(b),
-                _ => {
-                    println!("Mdia: TODO not implemented {:?}", child);
-                },
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::MediaHeader => {
+                    media_header = Some(MediaHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::Handler => handler = Some(HandlerBox::read(reader, child_header)?),
+                BoxType::MediaInfo => info = Some(MediaInfoBox::read(reader, child_header)?),
+                _ => return Ok(false),
             }
-        }
+            Ok(true)
+        })?;
     
         if media_header.is_none() {
             return Err(Error::BoxNotFound("Mdia: mdhd box is mandatory".to_owned()));
         }
+        if info.is_none() {
+            return Err(Error::BoxNotFound("Mdia: minf box is mandatory".to_owned()));
+        }
         Ok(Self {
             media_header: media_header.unwrap(),
             handler,
-            info,
+            info: info.unwrap(),
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MediaBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Media, |writer| {
+            let children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::MediaHeader, &self.media_header),
+                (BoxType::Handler, &self.handler),
+                (BoxType::MediaInfo, &self.info),
+            ];
+            write_children(writer, &self.order, children, &self.extensions)
         })
     }
 }
@@ -0,0 +1,43 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// ISO/IEC 14496-12 8.8.2 Movie Extends Header Box
+#[derive(Clone, Debug)]
+pub struct MovieExtendsHeaderBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub fragment_duration: u64,
+}
+
+impl Reader for MovieExtendsHeaderBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let fragment_duration = match version {
+            0 => reader.read_u32()? as u64,
+            1 => reader.read_u64()?,
+            _ => return Err(Error::InvalidData(format!("Mehd: unknown version {:?}", version))),
+        };
+
+        Ok(Self {
+            version,
+            flags,
+
+            fragment_duration,
+        })
+    }
+}
+
+impl Writer for MovieExtendsHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MovieExtendsHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            match self.version {
+                0 => writer.write_u32(self.fragment_duration as u32),
+                1 => writer.write_u64(self.fragment_duration),
+                _ => Err(Error::InvalidData(format!("Mehd: unknown version {:?}", self.version))),
+            }
+        })
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    HandlerBox, ItemListBox, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+// ISO/IEC 14496-12 8.11.1 Meta Box, with the Apple mdir handler it holds an ilst
+#[derive(Clone, Debug)]
+pub struct MetaBox {
+    pub version: u8,
+    pub flags: u32,
+    pub quicktime: bool, // QuickTime meta is a plain box without version and flags
+
+    pub hdlr: HandlerBox,
+    pub ilst: Option<ItemListBox>,
+    // Child types in file order and the other child boxes (keys, free...), written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for MetaBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (mut version, mut flags) = reader.read_header_extra()?;
+        let mut hdlr: Option<HandlerBox> = None;
+        let mut ilst: Option<ItemListBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+
+        // QuickTime meta has no version and flags, these 4 bytes are the size of the hdlr box
+        // that comes first
+        let quicktime =
+            header.size >= HEADER_LENGTH + 8 && BoxType::from(reader.peek_u32()?) == BoxType::Handler;
+        let mut offset = 4;
+        if quicktime {
+            let size = (version as u64) << 24 | flags as u64;
+            if size < HEADER_LENGTH || size > header.size - HEADER_LENGTH {
+                return Err(Error::InvalidData(format!("Meta: invalid hdlr box size {:?}", size)));
+            }
+            let child_header = BoxHeader {
+                name: BoxType::from(reader.read_u32()?),
+                start: header.start + HEADER_LENGTH,
+                size,
+            };
+            hdlr = Some(HandlerBox::read(reader, child_header)?);
+            order.push(BoxType::Handler);
+            offset = child_header.size;
+            (version, flags) = (0, 0);
+        }
+
+        let extensions = read_children(reader, header, offset, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::Handler => hdlr = Some(HandlerBox::read(reader, child_header)?),
+                BoxType::ItemList => ilst = Some(ItemListBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if hdlr.is_none() {
+            return Err(Error::BoxNotFound("Meta: hdlr box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            version,
+            flags,
+            quicktime,
+            hdlr: hdlr.unwrap(),
+            ilst,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MetaBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Meta, |writer| {
+            if !self.quicktime {
+                writer.write_header_extra(self.version, self.flags)?;
+            }
+            // hdlr comes first in a new meta, a QuickTime meta cannot be read back otherwise
+            let children: Vec<(BoxType, &dyn ChildWriter)> =
+                vec![(BoxType::Handler, &self.hdlr), (BoxType::ItemList, &self.ilst)];
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/genmeta.py, both end with the moov box
+    const METADATA: &[u8] = include_bytes!("../../testdata/metadata.mp4");
+    const METADATA_QT: &[u8] = include_bytes!("../../testdata/metadata_qt.mp4");
+
+    fn read_meta(data: &[u8]) -> Result<MetaBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        MetaBox::read(&mut reader, header)
+    }
+
+    fn meta_box(content: &[u8]) -> Vec<u8> {
+        let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(b"meta");
+        data.extend_from_slice(content);
+        data
+    }
+
+    fn hdlr() -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        let hdlr = HandlerBox {
+            version: 0,
+            flags: 0,
+            component_type: String::new(),
+            handler: "mdir".to_owned(),
+            name: String::new(),
+            null_terminated: true,
+        };
+        hdlr.write(&mut BoxWriter::new(&mut data)).unwrap();
+        data
+    }
+
+    #[test]
+    fn both_meta_layouts_roundtrip() {
+        for (data, quicktime) in [(METADATA, false), (METADATA_QT, true)] {
+            let mp4 = Mp4::parse(&mut Cursor::new(data)).unwrap();
+            let meta = mp4.moov.udta.as_ref().unwrap().meta.as_ref().unwrap();
+            assert_eq!(meta.quicktime, quicktime);
+            assert_eq!(meta.hdlr.handler, "mdir");
+            assert_eq!(mp4.metadata().artist(), Some("Artist"));
+
+            let mut written: Vec<u8> = Vec::new();
+            mp4.moov.write(&mut BoxWriter::new(&mut written)).unwrap();
+            assert_eq!(&data[data.len() - written.len()..], &written[..]);
+        }
+    }
+
+    #[test]
+    fn child_order_kept() {
+        // free, hdlr, then an empty ilst
+        let mut content = vec![0, 0, 0, 0, 0, 0, 0, 12];
+        content.extend_from_slice(b"free");
+        content.extend_from_slice(&[0; 4]);
+        content.extend(hdlr());
+        content.extend_from_slice(&[0, 0, 0, 8]);
+        content.extend_from_slice(b"ilst");
+        let data = meta_box(&content);
+        let meta = read_meta(&data).unwrap();
+        assert_eq!(meta.order, vec![BoxType::Free, BoxType::Handler, BoxType::ItemList]);
+
+        let mut written: Vec<u8> = Vec::new();
+        meta.write(&mut BoxWriter::new(&mut written)).unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn flags_do_not_make_a_quicktime_meta() {
+        let mut content = vec![0, 0, 0, 1];
+        content.extend(hdlr());
+        let meta = read_meta(&meta_box(&content)).unwrap();
+        assert!(!meta.quicktime);
+        assert_eq!((meta.version, meta.flags), (0, 1));
+        assert_eq!(meta.hdlr.handler, "mdir");
+    }
+
+    #[test]
+    fn invalid_quicktime_hdlr_size_rejected() {
+        for size in [4u32, 1000] {
+            let mut content = hdlr();
+            content[..4].copy_from_slice(&size.to_be_bytes());
+            assert!(matches!(read_meta(&meta_box(&content)), Err(Error::InvalidData(_))));
+        }
+    }
+}
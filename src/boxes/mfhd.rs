@@ -0,0 +1,35 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// ISO/IEC 14496-12 8.8.5 Movie Fragment Header Box
+#[derive(Clone, Debug)]
+pub struct MovieFragmentHeaderBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub sequence_number: u32,
+}
+
+impl Reader for MovieFragmentHeaderBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let sequence_number = reader.read_u32()?;
+
+        Ok(Self {
+            version,
+            flags,
+
+            sequence_number,
+        })
+    }
+}
+
+impl Writer for MovieFragmentHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MovieFragmentHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.sequence_number)
+        })
+    }
+}
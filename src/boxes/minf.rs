@@ -1,6 +1,75 @@
+use std::io::{Read, Seek, Write};
 
-use crate::ListBox;
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter,
+    DataInfoBox, Error, NullMediaHeaderBox, RawBox, Reader, SampleTableBox, SoundInfoBox,
+    VideoInfoBox, Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/base_media_information_atom
+#[derive(Clone, Debug)]
+pub struct MediaInfoBox {
+    pub vmhd: Option<VideoInfoBox>,
+    pub smhd: Option<SoundInfoBox>,
+    pub nmhd: Option<NullMediaHeaderBox>,
+    pub dinf: Option<DataInfoBox>,
+    pub sample_table: SampleTableBox,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
 
-pub type MediaInfoBox = ListBox;
\ No newline at end of file
+impl Reader for MediaInfoBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut vmhd: Option<VideoInfoBox> = None;
+        let mut smhd: Option<SoundInfoBox> = None;
+        let mut nmhd: Option<NullMediaHeaderBox> = None;
+        let mut dinf: Option<DataInfoBox> = None;
+        let mut sample_table: Option<SampleTableBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::VideoInfo => vmhd = Some(VideoInfoBox::read(reader, child_header)?),
+                BoxType::SoundInfo => smhd = Some(SoundInfoBox::read(reader, child_header)?),
+                BoxType::NullMediaHeader => {
+                    nmhd = Some(NullMediaHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::DataInfo => dinf = Some(DataInfoBox::read(reader, child_header)?),
+                BoxType::SampleTable => {
+                    sample_table = Some(SampleTableBox::read(reader, child_header)?)
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if sample_table.is_none() {
+            return Err(Error::BoxNotFound("Minf: stbl box is mandatory".to_owned()));
+        };
+        Ok(Self {
+            vmhd,
+            smhd,
+            nmhd,
+            dinf,
+            sample_table: sample_table.unwrap(),
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MediaInfoBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MediaInfo, |writer| {
+            let children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::VideoInfo, &self.vmhd),
+                (BoxType::SoundInfo, &self.smhd),
+                (BoxType::NullMediaHeader, &self.nmhd),
+                (BoxType::DataInfo, &self.dinf),
+                (BoxType::SampleTable, &self.sample_table),
+            ];
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    MovieFragmentHeaderBox, ProtectionSystemHeaderBox, RawBox, Reader, TrackFragmentBox, Writer,
+};
+
+// ISO/IEC 14496-12 8.8.4 Movie Fragment Box
+#[derive(Clone, Debug)]
+pub struct MovieFragmentBox {
+    pub start: u64, // File offset of the moof box, base for data offsets
+    pub mfhd: MovieFragmentHeaderBox,
+    pub pssh: Vec<ProtectionSystemHeaderBox>,
+    pub trafs: Vec<TrackFragmentBox>,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for MovieFragmentBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut mfhd: Option<MovieFragmentHeaderBox> = None;
+        let mut pssh: Vec<ProtectionSystemHeaderBox> = Vec::new();
+        let mut trafs: Vec<TrackFragmentBox> = Vec::new();
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::MovieFragmentHeader => {
+                    mfhd = Some(MovieFragmentHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::ProtectionSystemHeader => {
+                    pssh.push(ProtectionSystemHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::TrackFragment => trafs.push(TrackFragmentBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if mfhd.is_none() {
+            return Err(Error::BoxNotFound("Moof: mfhd box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            start: header.start,
+            mfhd: mfhd.unwrap(),
+            pssh,
+            trafs,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MovieFragmentBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MovieFragment, |writer| {
+            let mut children: Vec<(BoxType, &dyn ChildWriter)> =
+                vec![(BoxType::MovieFragmentHeader, &self.mfhd)];
+            for child in self.pssh.iter() {
+                children.push((BoxType::ProtectionSystemHeader, child));
+            }
+            for child in self.trafs.iter() {
+                children.push((BoxType::TrackFragment, child));
+            }
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
@@ -1,35 +1,142 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{ListBox, BoxHeader, BoxReader, BoxContent, Error, MvhdBox, Reader, TrackBox};
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    MovieExtendsBox, MvhdBox, ProtectionSystemHeaderBox, RawBox, Reader, TrackBox, UserDataBox,
+    Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/movie_atom
 #[derive(Clone, Debug)]
 pub struct MoovBox {
     pub mvhd: MvhdBox,
+    pub pssh: Vec<ProtectionSystemHeaderBox>,
+    pub tracks: Vec<TrackBox>,
+    pub mvex: Option<MovieExtendsBox>,
+    pub udta: Option<UserDataBox>,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
 }
 
 impl Reader for MoovBox {
     fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
-        let content = ListBox::read(reader, header)?;
         let mut mvhd: Option<MvhdBox> = None;
+        let mut pssh: Vec<ProtectionSystemHeaderBox> = Vec::new();
         let mut tracks: Vec<TrackBox> = Vec::new();
-        for child in content.children {
-            match child.content {
-                BoxContent::Mvhd(b) => mvhd = Some(b),
-                BoxContent::Trak(b) => tracks.push(b),
-                _ => (),
+        let mut mvex: Option<MovieExtendsBox> = None;
+        let mut udta: Option<UserDataBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::MovieHeader => mvhd = Some(MvhdBox::read(reader, child_header)?),
+                BoxType::ProtectionSystemHeader => {
+                    pssh.push(ProtectionSystemHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::Track => tracks.push(TrackBox::read(reader, child_header)?),
+                BoxType::MovieExtends => mvex = Some(MovieExtendsBox::read(reader, child_header)?),
+                BoxType::UserData => udta = Some(UserDataBox::read(reader, child_header)?),
+                _ => return Ok(false),
             }
-        }
+            Ok(true)
+        })?;
 
         if mvhd.is_none() {
             return Err(Error::BoxNotFound("Moov: Mvhd box is mandatory".to_owned()));
         }
-        if tracks.len() == 0 {
+        if tracks.is_empty() {
             return Err(Error::BoxNotFound("Moov: No track found".to_owned()));
         }
     
         Ok(Self {
             mvhd: mvhd.unwrap(),
+            pssh,
+            tracks,
+            mvex,
+            udta,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for MoovBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Movie, |writer| {
+            let mut children: Vec<(BoxType, &dyn ChildWriter)> =
+                vec![(BoxType::MovieHeader, &self.mvhd)];
+            for pssh in self.pssh.iter() {
+                children.push((BoxType::ProtectionSystemHeader, pssh));
+            }
+            for track in self.tracks.iter() {
+                children.push((BoxType::Track, track));
+            }
+            children.push((BoxType::MovieExtends, &self.mvex));
+            children.push((BoxType::UserData, &self.udta));
+            write_children(writer, &self.order, children, &self.extensions)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleEntry, VideoCodec};
+
+    // testdata/gen.py and testdata/genextra.py, both end with the moov box
+    const PROGRESSIVE: &[u8] = include_bytes!("../../testdata/progressive.mp4");
+    const EXTRA_CHILDREN: &[u8] = include_bytes!("../../testdata/extra_children.mp4");
+
+    fn rewrite_moov(data: &[u8]) -> (Mp4, Vec<u8>, Vec<u8>) {
+        let mp4 = Mp4::parse(&mut Cursor::new(data)).unwrap();
+        let mut written: Vec<u8> = Vec::new();
+        mp4.moov.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = data.len() - written.len();
+        (mp4, data[start..].to_vec(), written)
+    }
+
+    fn fourcc(name: &[u8; 4]) -> BoxType {
+        BoxType::from(u32::from_be_bytes(*name))
+    }
+
+    fn names(extensions: &[RawBox]) -> Vec<BoxType> {
+        extensions.iter().map(|extension| extension.header.name).collect()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (_, original, written) = rewrite_moov(PROGRESSIVE);
+        assert_eq!(&original[4..8], b"moov");
+        assert_eq!(original, written);
+    }
+
+    #[test]
+    fn unknown_children_kept_in_order() {
+        let (mp4, original, written) = rewrite_moov(EXTRA_CHILDREN);
+        assert_eq!(&original[4..8], b"moov");
+        assert_eq!(original, written);
+
+        let moov = &mp4.moov;
+        assert_eq!(names(&moov.extensions), vec![fourcc(b"iods"), BoxType::Free]);
+        let track = &moov.tracks[0];
+        assert_eq!(names(&track.extensions), vec![BoxType::UserData, BoxType::Meta]);
+        assert_eq!(names(&track.media.extensions), vec![fourcc(b"elng")]);
+        let handler = track.media.handler.as_ref().unwrap();
+        assert_eq!(handler.name, "VideoHandler");
+        assert!(!handler.null_terminated);
+        let info = &track.media.info;
+        assert_eq!(names(&info.extensions), vec![fourcc(b"gmhd")]);
+        let stbl = &info.sample_table;
+        assert_eq!(
+            names(&stbl.extensions),
+            vec![fourcc(b"sgpd"), fourcc(b"sbgp"), fourcc(b"sdtp")]
+        );
+        match &stbl.stsd.entries[0] {
+            SampleEntry::Video(VideoCodec::Avc(entry)) => assert_eq!(entry.visual.pre_defined, 0),
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+}
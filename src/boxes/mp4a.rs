@@ -0,0 +1,208 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    esds::OBJECT_TYPE_MPEG4_AUDIO, read_children, stsd::AudioSampleEntry, BitReader, BoxHeader,
+    BoxReader, BoxType, BoxWriter, Error, EsdsBox, RawBox, Reader, Writer,
+};
+
+pub const AAC_SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+// ISO/IEC 14496-14 5.6 MPEG-4 Audio Sample Entry
+#[derive(Clone, Debug)]
+pub struct Mp4aSampleEntry {
+    pub audio: AudioSampleEntry,
+    pub esds: Option<EsdsBox>, // QuickTime files may nest it in a wave box
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, wave, chan...
+}
+
+impl Mp4aSampleEntry {
+    pub fn audio_specific_config(&self) -> Option<Result<AudioSpecificConfig, Error>> {
+        let config = self.esds.as_ref()?.es_descriptor.decoder_config.as_ref()?;
+        if !config.is_mpeg4_audio() {
+            return None;
+        }
+        Some(AudioSpecificConfig::parse(config.decoder_specific_info.as_ref()?))
+    }
+
+    // RFC 6381 codecs parameter: mp4a.OO[.A], the audio object type as signaled
+    pub fn codec_string(&self) -> Result<String, Error> {
+        let Some(esds) = &self.esds else {
+            return Err(Error::BoxNotFound("Mp4a: esds box is mandatory for the codec string".to_owned()));
+        };
+        let Some(config) = &esds.es_descriptor.decoder_config else {
+            return Err(Error::InvalidData("Mp4a: esds has no DecoderConfigDescriptor".to_owned()));
+        };
+        if config.object_type_indication != OBJECT_TYPE_MPEG4_AUDIO {
+            return Ok(format!("mp4a.{:02X}", config.object_type_indication));
+        }
+        let Some(info) = &config.decoder_specific_info else {
+            return Err(Error::InvalidData("Mp4a: esds has no AudioSpecificConfig".to_owned()));
+        };
+        let audio_object_type = read_audio_object_type(&mut BitReader::new(info))?;
+        Ok(format!("mp4a.40.{}", audio_object_type))
+    }
+}
+
+impl Reader for Mp4aSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut esds: Option<EsdsBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::ElementaryStreamDescriptor => {
+                    esds = Some(EsdsBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        Ok(Self {
+            audio,
+            esds,
+            extensions,
+        })
+    }
+}
+
+impl Writer for Mp4aSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Mp4a, |writer| {
+            self.audio.write(writer)?;
+            self.esds.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ISO/IEC 14496-3 1.6.2.1 AudioSpecificConfig
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AudioSpecificConfig {
+    pub audio_object_type: u8, // 2 for AAC LC
+    pub sampling_frequency_index: u8,
+    pub sampling_frequency: u32,
+    pub channel_configuration: u8,
+    pub extension_audio_object_type: Option<u8>, // 5 for SBR, 29 for PS
+    pub sbr_present: bool,
+    pub ps_present: bool,
+    pub extension_sampling_frequency: Option<u32>,
+    pub frame_length_flag: bool, // 960 samples per frame instead of 1024
+}
+
+fn read_audio_object_type(bits: &mut BitReader) -> Result<u8, Error> {
+    let audio_object_type = bits.read_u8(5)?;
+    if audio_object_type == 31 {
+        return Ok(32 + bits.read_u8(6)?);
+    }
+    Ok(audio_object_type)
+}
+
+fn read_sampling_frequency(bits: &mut BitReader) -> Result<(u8, u32), Error> {
+    let index = bits.read_u8(4)?;
+    let frequency = match index {
+        0x0F => bits.read_bits(24)?,
+        _ => *AAC_SAMPLING_FREQUENCIES.get(index as usize).ok_or_else(|| {
+            Error::InvalidData(format!("AudioSpecificConfig: invalid sampling frequency index {:?}", index))
+        })?,
+    };
+    Ok((index, frequency))
+}
+
+impl AudioSpecificConfig {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut bits = BitReader::new(data);
+        let mut config = AudioSpecificConfig {
+            audio_object_type: read_audio_object_type(&mut bits)?,
+            ..Default::default()
+        };
+        (config.sampling_frequency_index, config.sampling_frequency) = read_sampling_frequency(&mut bits)?;
+        config.channel_configuration = bits.read_u8(4)?;
+
+        // Explicit hierarchical signaling of SBR and PS
+        if config.audio_object_type == 5 || config.audio_object_type == 29 {
+            config.extension_audio_object_type = Some(5);
+            config.sbr_present = true;
+            config.ps_present = config.audio_object_type == 29;
+            config.extension_sampling_frequency = Some(read_sampling_frequency(&mut bits)?.1);
+            config.audio_object_type = read_audio_object_type(&mut bits)?;
+            if config.audio_object_type == 22 {
+                bits.skip_bits(4)?; // extensionChannelConfiguration
+            }
+        }
+
+        match config.audio_object_type {
+            1 | 2 | 3 | 4 | 6 | 7 | 17 | 19 | 20 | 21 | 22 | 23 => {
+                // GASpecificConfig
+                config.frame_length_flag = bits.read_bit()?;
+                if bits.read_bit()? {
+                    bits.skip_bits(14)?; // coreCoderDelay
+                }
+                let extension_flag = bits.read_bit()?;
+                if config.channel_configuration == 0 {
+                    // A program_config_element follows, the backward compatible
+                    // extension can not be located without parsing it
+                    return Ok(config);
+                }
+                if config.audio_object_type == 6 || config.audio_object_type == 20 {
+                    bits.skip_bits(3)?; // layerNr
+                }
+                if extension_flag {
+                    match config.audio_object_type {
+                        22 => bits.skip_bits(16)?, // numOfSubFrame, layer_length
+                        17 | 19 | 20 | 23 => bits.skip_bits(3)?, // Resilience flags
+                        _ => (),
+                    }
+                    bits.skip_bits(1)?; // extensionFlag3
+                }
+            }
+            _ => return Ok(config),
+        }
+
+        // Backward compatible signaling appended after the base configuration
+        if config.extension_audio_object_type.is_none() && bits.bits_left() >= 16 {
+            if bits.read_bits(11)? != 0x2B7 {
+                return Ok(config);
+            }
+            let extension_audio_object_type = read_audio_object_type(&mut bits)?;
+            if extension_audio_object_type == 5 {
+                config.sbr_present = bits.read_bit()?;
+                if config.sbr_present {
+                    config.extension_audio_object_type = Some(5);
+                    config.extension_sampling_frequency = Some(read_sampling_frequency(&mut bits)?.1);
+                    if bits.bits_left() >= 12 && bits.read_bits(11)? == 0x548 {
+                        config.ps_present = bits.read_bit()?;
+                        if config.ps_present {
+                            config.extension_audio_object_type = Some(29);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    // Number of output channels, 0 when defined by a program config element
+    pub fn channel_count(&self) -> u8 {
+        match self.channel_configuration {
+            1..=6 => self.channel_configuration,
+            7 | 12 | 14 => 8,
+            11 => 7,
+            13 => 24,
+            _ => 0,
+        }
+    }
+
+    // Sampling frequency after the SBR extension
+    pub fn output_sampling_frequency(&self) -> u32 {
+        match self.extension_sampling_frequency {
+            Some(frequency) if self.sbr_present => frequency,
+            _ => self.sampling_frequency,
+        }
+    }
+}
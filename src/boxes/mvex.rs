@@ -0,0 +1,62 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    MovieExtendsHeaderBox, RawBox, Reader, TrackExtendsBox, Writer,
+};
+
+// ISO/IEC 14496-12 8.8.1 Movie Extends Box
+#[derive(Clone, Debug)]
+pub struct MovieExtendsBox {
+    pub mehd: Option<MovieExtendsHeaderBox>,
+    pub trex: Vec<TrackExtendsBox>,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for MovieExtendsBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut mehd: Option<MovieExtendsHeaderBox> = None;
+        let mut trex: Vec<TrackExtendsBox> = Vec::new();
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::MovieExtendsHeader => {
+                    mehd = Some(MovieExtendsHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::TrackExtends => trex.push(TrackExtendsBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        Ok(Self {
+            mehd,
+            trex,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl MovieExtendsBox {
+    pub fn track_extends(&self, track_id: u32) -> Option<&TrackExtendsBox> {
+        self.trex.iter().find(|trex| trex.track_id == track_id)
+    }
+}
+
+impl Writer for MovieExtendsBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MovieExtends, |writer| {
+            let mut children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::MovieExtendsHeader, &self.mehd),
+            ];
+            for child in self.trex.iter() {
+                children.push((BoxType::TrackExtends, child));
+            }
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
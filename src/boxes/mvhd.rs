@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Matrix, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Matrix, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/movie_header_atom
 #[derive(Clone, Debug)]
@@ -92,3 +92,40 @@ impl Reader for MvhdBox {
         })
     }
 }
+
+impl Writer for MvhdBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::MovieHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            match self.version {
+                0 => {
+                    writer.write_u32(self.creation_time as u32)?;
+                    writer.write_u32(self.modification_time as u32)?;
+                    writer.write_u32(self.timescale)?;
+                    writer.write_u32(self.duration as u32)?;
+                }
+                1 => {
+                    writer.write_u64(self.creation_time)?;
+                    writer.write_u64(self.modification_time)?;
+                    writer.write_u32(self.timescale)?;
+                    writer.write_u64(self.duration)?;
+                }
+                _ => return Err(Error::InvalidData(format!("Mvhd: unknown version {:?}", self.version))),
+            }
+            writer.write_u32(self.rate)?;
+            writer.write_u16(self.volume)?;
+            writer.write_zeros(10)?; // Reserved
+
+            self.matrix.write(writer)?;
+
+            writer.write_u32(self.preview_time)?;
+            writer.write_u32(self.preview_duration)?;
+            writer.write_u32(self.poster_time)?;
+            writer.write_u32(self.selection_time)?;
+            writer.write_u32(self.selection_duration)?;
+            writer.write_u32(self.current_time)?;
+
+            writer.write_u32(self.next_track_id)
+        })
+    }
+}
@@ -0,0 +1,25 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// ISO/IEC 14496-12 8.4.5.2 Null Media Header Box, used by text and other non AV tracks
+#[derive(Clone, Debug)]
+pub struct NullMediaHeaderBox {
+    pub version: u8,
+    pub flags: u32,
+}
+
+impl Reader for NullMediaHeaderBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        Ok(Self { version, flags })
+    }
+}
+
+impl Writer for NullMediaHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::NullMediaHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)
+        })
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, stsd::AudioSampleEntry, BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox,
+    Reader, Writer, HEADER_LENGTH,
+};
+
+// Encapsulation of Opus in ISO Base Media File Format 4.3.1 Opus Sample Entry
+#[derive(Clone, Debug)]
+pub struct OpusSampleEntry {
+    pub audio: AudioSampleEntry,
+    pub config: OpusSpecificBox,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, chnl...
+}
+
+impl Reader for OpusSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let audio = AudioSampleEntry::read(reader, header)?;
+        let mut config: Option<OpusSpecificBox> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            audio.length(),
+            |reader, child_header| match child_header.name {
+                BoxType::OpusSpecific => {
+                    config = Some(OpusSpecificBox::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound("Opus: dOps box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            audio,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl Writer for OpusSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Opus, |writer| {
+            self.audio.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// Encapsulation of Opus in ISO Base Media File Format 4.3.2 Opus Specific Box
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct OpusSpecificBox {
+    pub version: u8,
+    pub output_channel_count: u8,
+    pub pre_skip: u16, // Samples at 48 kHz to discard
+    pub input_sample_rate: u32,
+    pub output_gain: i16, // Q7.8 in dB
+    pub channel_mapping_family: u8,
+    pub channel_mapping: Option<OpusChannelMapping>, // Present when the family is not 0
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct OpusChannelMapping {
+    pub stream_count: u8,
+    pub coupled_count: u8,
+    pub channel_mapping: Vec<u8>,
+}
+
+// Opus always decodes at 48 kHz
+pub const OPUS_SAMPLE_RATE: u32 = 48000;
+
+impl OpusSpecificBox {
+    // Identification header expected by Opus decoders, RFC 7845 5.1
+    pub fn opus_head(&self) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(self.output_channel_count);
+        head.extend_from_slice(&self.pre_skip.to_le_bytes());
+        head.extend_from_slice(&self.input_sample_rate.to_le_bytes());
+        head.extend_from_slice(&self.output_gain.to_le_bytes());
+        head.push(self.channel_mapping_family);
+        if let Some(mapping) = &self.channel_mapping {
+            head.push(mapping.stream_count);
+            head.push(mapping.coupled_count);
+            head.extend_from_slice(&mapping.channel_mapping);
+        }
+        head
+    }
+}
+
+impl Reader for OpusSpecificBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        if header.size < HEADER_LENGTH + 11 {
+            return Err(Error::InvalidData("DOps: box is too short".to_owned()));
+        }
+        let version = reader.read_u8()?;
+        let output_channel_count = reader.read_u8()?;
+        let pre_skip = reader.read_u16()?;
+        let input_sample_rate = reader.read_u32()?;
+        let output_gain = reader.read_u16()? as i16;
+        let channel_mapping_family = reader.read_u8()?;
+        let mut parsed_size = HEADER_LENGTH + 11;
+        let channel_mapping = match channel_mapping_family {
+            0 => None,
+            _ => {
+                parsed_size += 2 + output_channel_count as u64;
+                if parsed_size > header.size {
+                    return Err(Error::InvalidData("DOps: channel mapping overruns the box".to_owned()));
+                }
+                let stream_count = reader.read_u8()?;
+                let coupled_count = reader.read_u8()?;
+                let channel_mapping = reader.read_bytes(output_channel_count as usize)?;
+                Some(OpusChannelMapping {
+                    stream_count,
+                    coupled_count,
+                    channel_mapping,
+                })
+            }
+        };
+        if parsed_size < header.size {
+            reader.skip(header.size - parsed_size)?;
+        }
+
+        Ok(Self {
+            version,
+            output_channel_count,
+            pre_skip,
+            input_sample_rate,
+            output_gain,
+            channel_mapping_family,
+            channel_mapping,
+        })
+    }
+}
+
+impl Writer for OpusSpecificBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::OpusSpecific, |writer| {
+            writer.write_u8(self.version)?;
+            writer.write_u8(self.output_channel_count)?;
+            writer.write_u16(self.pre_skip)?;
+            writer.write_u32(self.input_sample_rate)?;
+            writer.write_u16(self.output_gain as u16)?;
+            writer.write_u8(self.channel_mapping_family)?;
+            if let Some(mapping) = &self.channel_mapping {
+                writer.write_u8(mapping.stream_count)?;
+                writer.write_u8(mapping.coupled_count)?;
+                writer.write_bytes(&mapping.channel_mapping)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{AudioCodec, Mp4, SampleEntry};
+
+    // testdata/gencodecs.py audio.mp4 audio: track 1 is 5.1 Opus with a family 1 channel mapping
+    const AUDIO: &[u8] = include_bytes!("../../testdata/audio.mp4");
+
+    fn read_dops(data: &[u8]) -> Result<OpusSpecificBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        OpusSpecificBox::read(&mut reader, header)
+    }
+
+    fn opus_entry(mp4: &Mp4) -> &OpusSampleEntry {
+        match &mp4.track(1).unwrap().sample_entries()[0] {
+            SampleEntry::Audio(AudioCodec::Opus(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    #[test]
+    fn opus_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let entry = opus_entry(&mp4);
+        let config = &entry.config;
+        assert_eq!((config.output_channel_count, config.pre_skip, config.input_sample_rate), (6, 312, 48000));
+        assert_eq!((config.output_gain, config.channel_mapping_family), (0, 1));
+        assert_eq!(
+            config.channel_mapping,
+            Some(OpusChannelMapping {
+                stream_count: 4,
+                coupled_count: 2,
+                channel_mapping: vec![0, 4, 1, 2, 3, 5],
+            })
+        );
+        let head = config.opus_head();
+        assert_eq!(&head[..10], b"OpusHead\x01\x06");
+        assert_eq!(&head[10..12], &312u16.to_le_bytes());
+        assert_eq!(head.len(), 19 + 2 + 6);
+
+        let mut written: Vec<u8> = Vec::new();
+        entry.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = AUDIO.windows(4).position(|name| name == b"Opus").unwrap() - 4;
+        assert_eq!(&AUDIO[start..start + written.len()], &written[..]);
+    }
+
+    #[test]
+    fn short_dops_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        opus_entry(&mp4).config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(&read_dops(&data).unwrap(), &opus_entry(&mp4).config);
+
+        // The box ends in the channel mapping
+        let mut truncated = data[..data.len() - 2].to_vec();
+        truncated[3] -= 2;
+        truncated.extend_from_slice(&[0; 2]);
+        assert!(matches!(read_dops(&truncated), Err(Error::InvalidData(_))));
+        let mut short = data[..16].to_vec();
+        short[3] = 16;
+        assert!(matches!(read_dops(&short), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer, HEADER_LENGTH};
+
+// 16 byte key or DRM system identifier, as stored in pssh and tenc
+pub type KeyId = [u8; 16];
+
+// https://dashif.org/identifiers/content_protection/
+pub const COMMON_SYSTEM_ID: KeyId = [
+    0x10, 0x77, 0xef, 0xec, 0xc0, 0xb2, 0x4d, 0x02, 0xac, 0xe3, 0x3c, 0x1e, 0x52, 0xe2, 0xfb, 0x4b,
+];
+pub const WIDEVINE_SYSTEM_ID: KeyId = [
+    0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21, 0xed,
+];
+pub const PLAYREADY_SYSTEM_ID: KeyId = [
+    0x9a, 0x04, 0xf0, 0x79, 0x98, 0x40, 0x42, 0x86, 0xab, 0x92, 0xe6, 0x5b, 0xe0, 0x88, 0x5f, 0x95,
+];
+pub const FAIRPLAY_SYSTEM_ID: KeyId = [
+    0x94, 0xce, 0x86, 0xfb, 0x07, 0xff, 0x4f, 0x43, 0xad, 0xb8, 0x93, 0xd2, 0xfa, 0x96, 0x8c, 0xa2,
+];
+
+pub(crate) fn read_key_id<T: Read + Seek>(reader: &mut BoxReader<T>) -> Result<KeyId, Error> {
+    let mut id = [0; 16];
+    id.copy_from_slice(&reader.read_bytes(16)?);
+    Ok(id)
+}
+
+// ISO/IEC 23001-7 8.1 Protection System Specific Header Box, in moov or moof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtectionSystemHeaderBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub system_id: KeyId,
+    pub key_ids: Vec<KeyId>, // Version 1 only
+    pub data: Vec<u8>,       // System specific, such as a Widevine or PlayReady header
+}
+
+impl Reader for ProtectionSystemHeaderBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let mut content_size = header
+            .size
+            .checked_sub(HEADER_LENGTH + 4 + 16)
+            .ok_or(Error::InvalidData("Pssh: box is too short".to_owned()))?;
+
+        let system_id = read_key_id(reader)?;
+        let mut key_ids = Vec::new();
+        if version > 0 {
+            let count = reader.read_u32()? as u64;
+            if 4 + count * 16 > content_size {
+                return Err(Error::InvalidData(format!("Pssh: invalid KID_count={:?}", count)));
+            }
+            for _ in 0..count {
+                key_ids.push(read_key_id(reader)?);
+            }
+            content_size -= 4 + count * 16;
+        }
+        let data_size = reader.read_u32()? as u64;
+        if 4 + data_size > content_size {
+            return Err(Error::InvalidData(format!("Pssh: invalid DataSize={:?}", data_size)));
+        }
+        let data = reader.read_bytes(data_size as usize)?;
+
+        Ok(Self {
+            version,
+            flags,
+            system_id,
+            key_ids,
+            data,
+        })
+    }
+}
+
+impl Writer for ProtectionSystemHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::ProtectionSystemHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_bytes(&self.system_id)?;
+            if self.version > 0 {
+                writer.write_u32(self.key_ids.len() as u32)?;
+                for key_id in self.key_ids.iter() {
+                    writer.write_bytes(key_id)?;
+                }
+            }
+            writer.write_u32(self.data.len() as u32)?;
+            writer.write_bytes(&self.data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/genenc.py: Widevine version 1 and PlayReady version 0 pssh in moov, PlayReady
+    // in every moof
+    const ENCRYPTED_FRAGMENTED: &[u8] = include_bytes!("../../testdata/encrypted_fragmented.mp4");
+
+    fn read_pssh(data: &[u8]) -> Result<ProtectionSystemHeaderBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        ProtectionSystemHeaderBox::read(&mut reader, header)
+    }
+
+    #[test]
+    fn pssh_from_moov_and_moof() {
+        let mp4 = Mp4::parse(&mut Cursor::new(ENCRYPTED_FRAGMENTED)).unwrap();
+        let pssh = mp4.pssh();
+        assert_eq!(pssh.len(), 4);
+        assert_eq!(pssh[0].version, 1);
+        assert_eq!(pssh[0].key_ids.len(), 2);
+        assert_eq!(pssh[0].data, b"wvdt");
+        assert_eq!(pssh[1].data, b"prh");
+        assert_eq!(pssh[3], pssh[1]);
+
+        let mut written: Vec<u8> = Vec::new();
+        pssh[0].write(&mut BoxWriter::new(&mut written)).unwrap();
+        assert_eq!(read_pssh(&written).unwrap(), *pssh[0]);
+    }
+
+    #[test]
+    fn short_pssh_rejected() {
+        let mut data = vec![0, 0, 0, 20, b'p', b's', b's', b'h', 0, 0, 0, 0];
+        data.extend_from_slice(&[0; 24]);
+        assert!(matches!(read_pssh(&data), Err(Error::InvalidData(_))));
+    }
+}
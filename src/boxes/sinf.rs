@@ -0,0 +1,339 @@
+use std::io::{Cursor, Read, Seek, Write};
+
+use crate::{
+    read_children,
+    stsd::{AUDIO_SAMPLE_ENTRY_LENGTH, VISUAL_SAMPLE_ENTRY_LENGTH},
+    BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox, Reader, SampleEntry,
+    TrackEncryptionBox, Writer, HEADER_LENGTH,
+};
+
+// ISO/IEC 14496-12 8.12.1 Protection Scheme Information Box
+#[derive(Clone, Debug)]
+pub struct ProtectionSchemeInfoBox {
+    pub original_format: BoxType, // From frma: avc1, mp4a...
+    pub scheme_type: Option<SchemeTypeBox>,
+    pub scheme_info: Option<SchemeInformationBox>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl ProtectionSchemeInfoBox {
+    // Track encryption defaults of Common Encryption schemes
+    pub fn tenc(&self) -> Option<&TrackEncryptionBox> {
+        self.scheme_info.as_ref()?.tenc.as_ref()
+    }
+}
+
+impl Reader for ProtectionSchemeInfoBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut original_format: Option<BoxType> = None;
+        let mut scheme_type: Option<SchemeTypeBox> = None;
+        let mut scheme_info: Option<SchemeInformationBox> = None;
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            match child_header.name {
+                BoxType::OriginalFormat => {
+                    original_format = Some(BoxType::from(reader.read_u32()?))
+                }
+                BoxType::SchemeType => {
+                    scheme_type = Some(SchemeTypeBox::read(reader, child_header)?)
+                }
+                BoxType::SchemeInformation => {
+                    scheme_info = Some(SchemeInformationBox::read(reader, child_header)?)
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if original_format.is_none() {
+            return Err(Error::BoxNotFound("Sinf: frma box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            original_format: original_format.unwrap(),
+            scheme_type,
+            scheme_info,
+            extensions,
+        })
+    }
+}
+
+impl Writer for ProtectionSchemeInfoBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::ProtectionSchemeInfo, |writer| {
+            writer.write_box(BoxType::OriginalFormat, |writer| {
+                writer.write_u32(u32::from(self.original_format))
+            })?;
+            self.scheme_type.write(writer)?;
+            self.scheme_info.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ISO/IEC 14496-12 8.12.5 Scheme Type Box
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemeTypeBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub scheme_type: String, // FourCC: cenc, cbc1, cens or cbcs for Common Encryption
+    pub scheme_version: u32, // 0x00010000 for Common Encryption
+    pub scheme_uri: Option<String>, // Present when flags & 1
+}
+
+impl Reader for SchemeTypeBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+
+        let scheme_type = reader.read_string(4)?;
+        let scheme_version = reader.read_u32()?;
+        let scheme_uri = match flags & 0x1 {
+            0 => None,
+            _ => {
+                let len = header
+                    .size
+                    .checked_sub(HEADER_LENGTH + 4 + 8)
+                    .ok_or(Error::InvalidData("Schm: box is too short".to_owned()))?;
+                Some(reader.read_string(len as usize)?)
+            }
+        };
+        Ok(Self {
+            version,
+            flags,
+            scheme_type,
+            scheme_version,
+            scheme_uri,
+        })
+    }
+}
+
+impl Writer for SchemeTypeBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SchemeType, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_fourcc(&self.scheme_type)?;
+            writer.write_u32(self.scheme_version)?;
+            match &self.scheme_uri {
+                Some(uri) => writer.write_cstring(uri),
+                None => Ok(()),
+            }
+        })
+    }
+}
+
+// ISO/IEC 14496-12 8.12.6 Scheme Information Box, holds tenc for Common Encryption
+#[derive(Clone, Debug, Default)]
+pub struct SchemeInformationBox {
+    pub tenc: Option<TrackEncryptionBox>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for SchemeInformationBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut tenc: Option<TrackEncryptionBox> = None;
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            match child_header.name {
+                BoxType::TrackEncryption => {
+                    tenc = Some(TrackEncryptionBox::read(reader, child_header)?)
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        Ok(Self { tenc, extensions })
+    }
+}
+
+impl Writer for SchemeInformationBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SchemeInformation, |writer| {
+            self.tenc.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// ISO/IEC 14496-12 8.12 encv and enca sample entries: the original sample entry renamed, with
+// a sinf box describing how it is protected
+#[derive(Clone, Debug)]
+pub struct ProtectedSampleEntry {
+    pub name: BoxType, // encv or enca
+    pub sinf: ProtectionSchemeInfoBox,
+    pub original: Box<SampleEntry>, // Read as its frma format, without the sinf box
+    pub sinf_position: usize,       // Child boxes of the original before sinf
+}
+
+// Size of the visual or audio fields before the child boxes of a sample entry content
+fn children_offset(name: BoxType, content: &[u8]) -> u64 {
+    match name {
+        BoxType::Enca => {
+            let version = content.get(8..10).map_or(0, |v| u16::from_be_bytes([v[0], v[1]]));
+            AUDIO_SAMPLE_ENTRY_LENGTH
+                + match version {
+                    1 => 16,
+                    2 => 36,
+                    _ => 0,
+                }
+        }
+        _ => VISUAL_SAMPLE_ENTRY_LENGTH,
+    }
+}
+
+impl Reader for ProtectedSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut content = reader.read_bytes((header.size - HEADER_LENGTH) as usize)?;
+        let offset = children_offset(header.name, &content);
+
+        // Child offsets are relative to the content
+        let mut sinf: Option<(BoxHeader, ProtectionSchemeInfoBox)> = None;
+        let mut sinf_position = 0;
+        {
+            let mut src = Cursor::new(&content);
+            let mut reader = BoxReader::new(&mut src);
+            reader.skip(offset)?;
+            let content_header = BoxHeader { start: 0, ..header };
+            read_children(&mut reader, content_header, offset, |reader, child_header| {
+                match child_header.name {
+                    BoxType::ProtectionSchemeInfo => {
+                        let info = ProtectionSchemeInfoBox::read(reader, child_header)?;
+                        sinf = Some((child_header, info));
+                    }
+                    _ if sinf.is_none() => {
+                        sinf_position += 1;
+                        return Ok(false);
+                    }
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            })?;
+        }
+        if sinf.is_none() {
+            return Err(Error::BoxNotFound(format!("{:?}: sinf box is mandatory", header.name)));
+        }
+        let (sinf_header, sinf) = sinf.unwrap();
+        if matches!(sinf.original_format, BoxType::Encv | BoxType::Enca) {
+            return Err(Error::InvalidData(format!(
+                "{:?}: invalid original format {:?}",
+                header.name, sinf.original_format
+            )));
+        }
+
+        // Read what remains as the original sample entry
+        let start = sinf_header.start as usize;
+        content.drain(start..(start + sinf_header.size as usize).min(content.len()));
+        let original_header = BoxHeader {
+            name: sinf.original_format,
+            start: header.start,
+            size: content.len() as u64 + HEADER_LENGTH,
+        };
+        let mut src = Cursor::new(content);
+        let original = SampleEntry::read(&mut BoxReader::new(&mut src), original_header)?;
+        Ok(Self {
+            name: header.name,
+            sinf,
+            original: Box::new(original),
+            sinf_position,
+        })
+    }
+}
+
+// Content of a written box, after a 16 byte header when the size is 1 for a 64 bit largesize
+fn box_content(data: &[u8]) -> &[u8] {
+    let header_length = match data.get(..4) {
+        Some([0, 0, 0, 1]) => HEADER_LENGTH + 8,
+        _ => HEADER_LENGTH,
+    };
+    &data[(header_length as usize).min(data.len())..]
+}
+
+// The sinf box is written back among the children of the original sample entry, at the
+// position it was read from
+impl Writer for ProtectedSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.name, |writer| {
+            let mut original: Vec<u8> = Vec::new();
+            self.original.write(&mut BoxWriter::new(&mut original))?;
+            let content = box_content(&original);
+            let mut position = children_offset(self.name, content) as usize;
+            for _ in 0..self.sinf_position {
+                let Some(size) = content.get(position..position + 4) else {
+                    break;
+                };
+                let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+                position += size.max(HEADER_LENGTH as usize);
+            }
+            let position = position.min(content.len());
+            writer.write_bytes(&content[..position])?;
+            self.sinf.write(writer)?;
+            writer.write_bytes(&content[position..])?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{KeyId, Mp4, SampleEntry};
+
+    // testdata/genenc.py: encv with sinf between avcC and pasp, enca with sinf last
+    const ENCRYPTED: &[u8] = include_bytes!("../../testdata/encrypted.mp4");
+    const ENCRYPTED_FRAGMENTED: &[u8] = include_bytes!("../../testdata/encrypted_fragmented.mp4");
+
+    fn protected(entry: &SampleEntry) -> &ProtectedSampleEntry {
+        match entry {
+            SampleEntry::Protected(entry) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    fn moov_bytes(data: &[u8]) -> &[u8] {
+        let start = data.windows(4).position(|name| name == b"moov").unwrap() - 4;
+        let size = u32::from_be_bytes([data[start], data[start + 1], data[start + 2], data[start + 3]]);
+        &data[start..start + size as usize]
+    }
+
+    #[test]
+    fn protected_entries_roundtrip() {
+        for data in [ENCRYPTED, ENCRYPTED_FRAGMENTED] {
+            let mp4 = Mp4::parse(&mut Cursor::new(data)).unwrap();
+            let video = protected(&mp4.track(1).unwrap().sample_entries()[0]);
+            assert_eq!(video.name, BoxType::Encv);
+            assert_eq!(video.sinf.original_format, BoxType::Avc1);
+            assert_eq!(video.sinf.scheme_type.as_ref().unwrap().scheme_type, "cenc");
+            let kid: KeyId = std::array::from_fn(|i| i as u8);
+            assert_eq!(video.sinf.tenc().unwrap().default_kid, kid);
+            assert_eq!(video.sinf_position, 1);
+            let audio = protected(&mp4.track(2).unwrap().sample_entries()[0]);
+            assert_eq!(audio.sinf.scheme_type.as_ref().unwrap().scheme_type, "cbcs");
+            assert_eq!(audio.sinf_position, 1);
+            assert_eq!(mp4.key_ids().len(), 2);
+
+            let mut written: Vec<u8> = Vec::new();
+            mp4.moov.write(&mut BoxWriter::new(&mut written)).unwrap();
+            assert_eq!(moov_bytes(data), &written[..]);
+        }
+    }
+
+    #[test]
+    fn content_after_largesize_header() {
+        let mut data = vec![0, 0, 0, 1, b'e', b'n', b'c', b'v', 0, 0, 0, 0, 0, 0, 0, 19];
+        data.extend_from_slice(b"abc");
+        assert_eq!(box_content(&data), b"abc");
+        assert_eq!(box_content(&data[4..]), &data[12..]);
+    }
+
+    #[test]
+    fn short_scheme_type_rejected() {
+        // schm with a scheme_uri flag and no room for its version
+        let mut data = vec![0, 0, 0, 16, b's', b'c', b'h', b'm', 0, 0, 0, 1, b'c', b'e', b'n', b'c'];
+        data.extend_from_slice(&[0; 8]);
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert!(matches!(SchemeTypeBox::read(&mut reader, header), Err(Error::InvalidData(_))));
+    }
+}
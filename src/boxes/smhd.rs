@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sound_media_information_header_atom
 #[derive(Clone, Debug)]
@@ -26,3 +26,13 @@ impl Reader for SoundInfoBox {
         })
     }
 }
+
+impl Writer for SoundInfoBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SoundInfo, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u16(self.balance)?;
+            writer.write_zeros(2) // Reserved
+        })
+    }
+}
@@ -1,6 +1,106 @@
+use std::io::{Read, Seek, Write};
 
-use crate::ListBox;
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter,
+    ChunkOffset64Box, ChunkOffsetBox, CompositionOffsetBox, Error, RawBox, Reader,
+    SampleDescriptionBox, SampleSizeBox, SampleToChunkBox, SyncSampleBox, TimeToSampleBox, Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sample_table_atom
-pub type SampleTableBox = ListBox;
+#[derive(Clone, Debug)]
+pub struct SampleTableBox {
+    pub stsd: SampleDescriptionBox,
+    pub stts: TimeToSampleBox,
+    pub stsc: SampleToChunkBox,
+    pub stsz: SampleSizeBox,
+    pub stco: Option<ChunkOffsetBox>,
+    pub co64: Option<ChunkOffset64Box>,
+    pub ctts: Option<CompositionOffsetBox>,
+    pub stss: Option<SyncSampleBox>,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
 
+impl Reader for SampleTableBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut stsd: Option<SampleDescriptionBox> = None;
+        let mut stts: Option<TimeToSampleBox> = None;
+        let mut stsc: Option<SampleToChunkBox> = None;
+        let mut stsz: Option<SampleSizeBox> = None;
+        let mut stco: Option<ChunkOffsetBox> = None;
+        let mut co64: Option<ChunkOffset64Box> = None;
+        let mut ctts: Option<CompositionOffsetBox> = None;
+        let mut stss: Option<SyncSampleBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::SampleDescription => {
+                    stsd = Some(SampleDescriptionBox::read(reader, child_header)?)
+                }
+                BoxType::TimeToSample => stts = Some(TimeToSampleBox::read(reader, child_header)?),
+                BoxType::SampleToChunk => {
+                    stsc = Some(SampleToChunkBox::read(reader, child_header)?)
+                }
+                BoxType::SampleSize => stsz = Some(SampleSizeBox::read(reader, child_header)?),
+                BoxType::ChunkOffset => stco = Some(ChunkOffsetBox::read(reader, child_header)?),
+                BoxType::ChunkOffset64 => {
+                    co64 = Some(ChunkOffset64Box::read(reader, child_header)?)
+                }
+                BoxType::CompositionOffset => {
+                    ctts = Some(CompositionOffsetBox::read(reader, child_header)?)
+                }
+                BoxType::SyncSample => stss = Some(SyncSampleBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if stsd.is_none() {
+            return Err(Error::BoxNotFound("Stbl: stsd box is mandatory".to_owned()));
+        };
+        if stts.is_none() {
+            return Err(Error::BoxNotFound("Stbl: stts box is mandatory".to_owned()));
+        };
+        if stsc.is_none() {
+            return Err(Error::BoxNotFound("Stbl: stsc box is mandatory".to_owned()));
+        };
+        if stsz.is_none() {
+            return Err(Error::BoxNotFound("Stbl: stsz box is mandatory".to_owned()));
+        };
+        if stco.is_none() && co64.is_none() {
+            return Err(Error::BoxNotFound("Stbl: stco or co64 box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            stsd: stsd.unwrap(),
+            stts: stts.unwrap(),
+            stsc: stsc.unwrap(),
+            stsz: stsz.unwrap(),
+            stco,
+            co64,
+            ctts,
+            stss,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for SampleTableBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SampleTable, |writer| {
+            let children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::SampleDescription, &self.stsd),
+                (BoxType::TimeToSample, &self.stts),
+                (BoxType::SyncSample, &self.stss),
+                (BoxType::CompositionOffset, &self.ctts),
+                (BoxType::SampleToChunk, &self.stsc),
+                (BoxType::SampleSize, &self.stsz),
+                (BoxType::ChunkOffset, &self.stco),
+                (BoxType::ChunkOffset64, &self.co64),
+            ];
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sample-to-chunk_atom
 #[derive(Clone, Debug)]
@@ -27,3 +27,16 @@ impl Reader for ChunkOffsetBox {
         })
     }
 }
+
+impl Writer for ChunkOffsetBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::ChunkOffset, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.table.len() as u32)?;
+            for &offset in self.table.iter() {
+                writer.write_u32(offset)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sample-to-chunk_atom
 #[derive(Clone, Debug)]
@@ -27,3 +27,18 @@ impl Reader for SampleToChunkBox {
         })
     }
 }
+
+impl Writer for SampleToChunkBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SampleToChunk, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.table.len() as u32)?;
+            for &(first_chunk, samples_per_chunk, description_id) in self.table.iter() {
+                writer.write_u32(first_chunk)?;
+                writer.write_u32(samples_per_chunk)?;
+                writer.write_u32(description_id)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,46 +1,701 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, BoxType, Error, Reader};
+use crate::{
+    opus::OPUS_SAMPLE_RATE, Ac3SampleEntry, AlacSampleEntry, Av1SampleEntry, AvcSampleEntry,
+    BoxHeader, BoxReader, BoxType, BoxWriter, Ec3SampleEntry, Error, FlacSampleEntry,
+    HevcSampleEntry, Mp4aSampleEntry, OpusSampleEntry, ProtectedSampleEntry,
+    ProtectionSchemeInfoBox, RawBox, Reader, VpSampleEntry, Writer, HEADER_LENGTH,
+};
 
-// https://developer.apple.com/documentation/quicktime-file-format/video_sample_description
+// https://developer.apple.com/documentation/quicktime-file-format/sample_description_atom
 #[derive(Clone, Debug)]
-pub struct VideoSampleDescriptionBox {
+pub struct SampleDescriptionBox {
     pub version: u8,
     pub flags: u32,
 
-    pub codec: VideoCodec,
+    pub entries: Vec<SampleEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SampleEntry {
+    Video(VideoCodec),
+    Audio(AudioCodec),
+    Protected(ProtectedSampleEntry), // encv or enca
+    Unknown(RawBox), // Sample entry kept as raw content
 }
 
 #[derive(Clone, Debug)]
 pub enum VideoCodec {
-    Unknown(BoxHeader),
+    Avc(AvcSampleEntry),
+    Hevc(HevcSampleEntry),
+    Av1(Av1SampleEntry),
+    Vp9(VpSampleEntry),
+    Vp8(VpSampleEntry),
 }
 
-impl Reader for VideoSampleDescriptionBox {
-    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+#[derive(Clone, Debug)]
+pub enum AudioCodec {
+    Mp4a(Mp4aSampleEntry),
+    Opus(OpusSampleEntry),
+    Flac(FlacSampleEntry),
+    Ac3(Ac3SampleEntry),
+    Ec3(Ec3SampleEntry),
+    Alac(AlacSampleEntry),
+}
+
+impl SampleEntry {
+    pub fn name(&self) -> BoxType {
+        match self {
+            SampleEntry::Video(VideoCodec::Avc(entry)) => entry.name,
+            SampleEntry::Video(VideoCodec::Hevc(entry)) => entry.name,
+            SampleEntry::Video(VideoCodec::Av1(_)) => BoxType::Av01,
+            SampleEntry::Video(VideoCodec::Vp9(entry) | VideoCodec::Vp8(entry)) => entry.name,
+            SampleEntry::Audio(AudioCodec::Mp4a(_)) => BoxType::Mp4a,
+            SampleEntry::Audio(AudioCodec::Opus(_)) => BoxType::Opus,
+            SampleEntry::Audio(AudioCodec::Flac(_)) => BoxType::Flac,
+            SampleEntry::Audio(AudioCodec::Ac3(_)) => BoxType::Ac3,
+            SampleEntry::Audio(AudioCodec::Ec3(_)) => BoxType::Ec3,
+            SampleEntry::Audio(AudioCodec::Alac(_)) => BoxType::Alac,
+            SampleEntry::Protected(entry) => entry.name,
+            SampleEntry::Unknown(raw) => raw.header.name,
+        }
+    }
+
+    // RFC 6381 codecs parameter, as used in HLS CODECS and MSE isTypeSupported
+    pub fn codec_string(&self) -> Result<String, Error> {
+        match self {
+            SampleEntry::Video(VideoCodec::Avc(entry)) => Ok(entry.codec_string()),
+            SampleEntry::Video(VideoCodec::Hevc(entry)) => Ok(entry.codec_string()),
+            SampleEntry::Video(VideoCodec::Av1(entry)) => Ok(entry.codec_string()),
+            SampleEntry::Video(VideoCodec::Vp9(entry) | VideoCodec::Vp8(entry)) => {
+                Ok(entry.codec_string())
+            }
+            SampleEntry::Audio(AudioCodec::Mp4a(entry)) => entry.codec_string(),
+            // Codecs without parameters
+            SampleEntry::Audio(_) => Ok(self.name().to_string()),
+            // Encrypted streams are signaled with the codec of the original format
+            SampleEntry::Protected(entry) => entry.original.codec_string(),
+            SampleEntry::Unknown(raw) => Err(Error::InvalidData(format!(
+                "{}: unsupported sample entry, no codec string",
+                raw.header.name
+            ))),
+        }
+    }
+
+    // Protected entries answer for their original sample entry
+    pub fn video(&self) -> Option<&VideoCodec> {
+        match self {
+            SampleEntry::Video(codec) => Some(codec),
+            SampleEntry::Protected(entry) => entry.original.video(),
+            _ => None,
+        }
+    }
+
+    pub fn audio(&self) -> Option<&AudioCodec> {
+        match self {
+            SampleEntry::Audio(codec) => Some(codec),
+            SampleEntry::Protected(entry) => entry.original.audio(),
+            _ => None,
+        }
+    }
+
+    pub fn protection(&self) -> Option<&ProtectionSchemeInfoBox> {
+        match self {
+            SampleEntry::Protected(entry) => Some(&entry.sinf),
+            _ => None,
+        }
+    }
+}
+
+impl SampleDescriptionBox {
+    // Entry referenced by a 1 based sample_description_index
+    pub fn entry(&self, description_index: u32) -> Option<&SampleEntry> {
+        self.entries.get((description_index as usize).checked_sub(1)?)
+    }
+}
+
+impl AudioCodec {
+    pub fn audio(&self) -> &AudioSampleEntry {
+        match self {
+            AudioCodec::Mp4a(entry) => &entry.audio,
+            AudioCodec::Opus(entry) => &entry.audio,
+            AudioCodec::Flac(entry) => &entry.audio,
+            AudioCodec::Ac3(entry) => &entry.audio,
+            AudioCodec::Ec3(entry) => &entry.audio,
+            AudioCodec::Alac(entry) => &entry.audio,
+        }
+    }
+
+    // Channel count from the decoder configuration, the sample entry one is often a placeholder
+    pub fn channel_count(&self) -> u32 {
+        let count = match self {
+            AudioCodec::Mp4a(entry) => match entry.audio_specific_config() {
+                Some(Ok(config)) => config.channel_count() as u32,
+                _ => 0,
+            },
+            AudioCodec::Opus(entry) => entry.config.output_channel_count as u32,
+            AudioCodec::Flac(entry) => match entry.config.stream_info() {
+                Some(Ok(info)) => info.channels as u32,
+                _ => 0,
+            },
+            AudioCodec::Ac3(entry) => entry.config.channel_count() as u32,
+            AudioCodec::Ec3(entry) => entry.config.channel_count() as u32,
+            AudioCodec::Alac(entry) => entry.config.num_channels as u32,
+        };
+        match count {
+            0 => self.audio().channels(),
+            _ => count,
+        }
+    }
+
+    // Decoded sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        let rate = match self {
+            AudioCodec::Mp4a(entry) => match entry.audio_specific_config() {
+                Some(Ok(config)) => config.output_sampling_frequency(),
+                _ => 0,
+            },
+            AudioCodec::Opus(_) => OPUS_SAMPLE_RATE,
+            AudioCodec::Flac(entry) => match entry.config.stream_info() {
+                Some(Ok(info)) => info.sample_rate,
+                _ => 0,
+            },
+            AudioCodec::Ac3(entry) => entry.config.sample_rate(),
+            AudioCodec::Ec3(entry) => entry.config.sample_rate(),
+            AudioCodec::Alac(entry) => entry.config.sample_rate,
+        };
+        match rate {
+            0 => self.audio().sample_rate() as u32,
+            _ => rate,
+        }
+    }
+
+    // Bits per decoded sample for lossless codecs, None when the decoder chooses
+    pub fn bit_depth(&self) -> Option<u8> {
+        match self {
+            AudioCodec::Flac(entry) => match entry.config.stream_info() {
+                Some(Ok(info)) => Some(info.bits_per_sample),
+                _ => Some(self.audio().sample_size as u8),
+            },
+            AudioCodec::Alac(entry) => Some(entry.config.bit_depth),
+            _ => None,
+        }
+    }
+
+    // Configuration bytes in the form decoders expect them
+    pub fn decoder_specific_info(&self) -> Option<Vec<u8>> {
+        match self {
+            AudioCodec::Mp4a(entry) => entry
+                .esds
+                .as_ref()?
+                .es_descriptor
+                .decoder_config
+                .as_ref()?
+                .decoder_specific_info
+                .clone(),
+            AudioCodec::Opus(entry) => Some(entry.config.opus_head()),
+            AudioCodec::Flac(entry) => Some(entry.config.stream_header()),
+            AudioCodec::Ac3(entry) => Some(entry.config.to_bytes()),
+            AudioCodec::Ec3(entry) => Some(entry.config.to_bytes()),
+            AudioCodec::Alac(entry) => Some(entry.config.magic_cookie()),
+        }
+    }
+}
+
+impl Reader for SampleDescriptionBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
         let (version, flags) = reader.read_header_extra()?;
 
         let entry_count = reader.read_u32()?;
-        if entry_count != 1 {
+        if entry_count == 0 {
+            return Err(Error::InvalidData("Stsd: entry_count is 0".to_owned()));
+        }
+        // Each entry is at least a box header
+        if entry_count as u64 * HEADER_LENGTH > header.size {
             return Err(Error::InvalidData(format!("Stsd: invalid entry_count={:?}", entry_count)));
         }
-        let child_header = BoxHeader::read(reader)?;
-        let codec = match child_header.name {
-            BoxType::DataRef => {
-                child_header.skip_content(reader, 0)?;
-                VideoCodec::Unknown(child_header)
+        let mut entries: Vec<SampleEntry> = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let child_header = BoxHeader::read(reader)?;
+            entries.push(SampleEntry::read(reader, child_header)?);
+        }
+
+        Ok(Self {
+            version,
+            flags,
+            entries,
+        })
+    }
+}
+
+impl Reader for SampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, child_header: BoxHeader) -> Result<Self, Error> {
+        let entry = match child_header.name {
+            BoxType::Avc1 | BoxType::Avc3 => {
+                SampleEntry::Video(VideoCodec::Avc(AvcSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Hvc1 | BoxType::Hev1 => {
+                SampleEntry::Video(VideoCodec::Hevc(HevcSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Av01 => {
+                SampleEntry::Video(VideoCodec::Av1(Av1SampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Vp09 => {
+                SampleEntry::Video(VideoCodec::Vp9(VpSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Vp08 => {
+                SampleEntry::Video(VideoCodec::Vp8(VpSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Mp4a => {
+                SampleEntry::Audio(AudioCodec::Mp4a(Mp4aSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Opus => {
+                SampleEntry::Audio(AudioCodec::Opus(OpusSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Flac => {
+                SampleEntry::Audio(AudioCodec::Flac(FlacSampleEntry::read(reader, child_header)?))
             }
-            _ => {
-                child_header.skip_content(reader, 0)?;
-                VideoCodec::Unknown(child_header)
-                // return Err(Error::InvalidBox(format!("Stsd: invalid codec {:?}", child_header.name)));
+            BoxType::Ac3 => {
+                SampleEntry::Audio(AudioCodec::Ac3(Ac3SampleEntry::read(reader, child_header)?))
             }
+            BoxType::Ec3 => {
+                SampleEntry::Audio(AudioCodec::Ec3(Ec3SampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Alac => {
+                SampleEntry::Audio(AudioCodec::Alac(AlacSampleEntry::read(reader, child_header)?))
+            }
+            BoxType::Encv | BoxType::Enca => {
+                SampleEntry::Protected(ProtectedSampleEntry::read(reader, child_header)?)
+            }
+            _ => SampleEntry::Unknown(RawBox::read(reader, child_header)?),
         };
+        Ok(entry)
+    }
+}
+
+impl Writer for SampleDescriptionBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SampleDescription, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.entries.len() as u32)?;
+            self.entries.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+impl Writer for SampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        match self {
+            SampleEntry::Video(codec) => codec.write(writer),
+            SampleEntry::Audio(codec) => codec.write(writer),
+            SampleEntry::Protected(entry) => entry.write(writer),
+            SampleEntry::Unknown(raw) => raw.write(writer),
+        }
+    }
+}
+
+impl Writer for VideoCodec {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        match self {
+            VideoCodec::Avc(entry) => entry.write(writer),
+            VideoCodec::Hevc(entry) => entry.write(writer),
+            VideoCodec::Av1(entry) => entry.write(writer),
+            VideoCodec::Vp9(entry) | VideoCodec::Vp8(entry) => entry.write(writer),
+        }
+    }
+}
+
+impl Writer for AudioCodec {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        match self {
+            AudioCodec::Mp4a(entry) => entry.write(writer),
+            AudioCodec::Opus(entry) => entry.write(writer),
+            AudioCodec::Flac(entry) => entry.write(writer),
+            AudioCodec::Ac3(entry) => entry.write(writer),
+            AudioCodec::Ec3(entry) => entry.write(writer),
+            AudioCodec::Alac(entry) => entry.write(writer),
+        }
+    }
+}
+
+// ISO/IEC 14496-12 12.1.3 Visual Sample Entry, common to every video codec
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisualSampleEntry {
+    pub data_reference_index: u16,
+    pub reserved: [u8; 16], // pre_defined and reserved, QuickTime version, vendor and qualities
+    pub width: u16,
+    pub height: u16,
+    pub horizontal_resolution: u32, // 16.16 fix point, pixels per inch
+    pub vertical_resolution: u32,   // 16.16 fix point, pixels per inch
+    pub frame_count: u16,
+    pub compressor_name: [u8; 32], // Pascal string as read, its length byte first
+    pub depth: u16,
+    pub pre_defined: u16,
+}
+
+// Size of the visual sample entry fields before the child boxes
+pub const VISUAL_SAMPLE_ENTRY_LENGTH: u64 = 78;
+
+impl Default for VisualSampleEntry {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 1,
+            reserved: [0; 16],
+            width: 0,
+            height: 0,
+            horizontal_resolution: 0x00480000, // 72 dpi
+            vertical_resolution: 0x00480000,
+            frame_count: 1,
+            compressor_name: [0; 32],
+            depth: 0x0018,
+            pre_defined: 0xFFFF, // -1
+        }
+    }
+}
+
+impl VisualSampleEntry {
+    pub fn compressor_name(&self) -> String {
+        let len = (self.compressor_name[0] as usize).min(31);
+        String::from_utf8_lossy(&self.compressor_name[1..1 + len]).into_owned()
+    }
+
+    // Names longer than 31 bytes are truncated
+    pub fn set_compressor_name(&mut self, name: &str) {
+        let len = name.len().min(31);
+        self.compressor_name = [0; 32];
+        self.compressor_name[0] = len as u8;
+        self.compressor_name[1..1 + len].copy_from_slice(&name.as_bytes()[..len]);
+    }
+}
+
+impl Reader for VisualSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        reader.skip(6)?; // Reserved
+        let data_reference_index = reader.read_u16()?;
+        let mut reserved = [0; 16];
+        reserved.copy_from_slice(&reader.read_bytes(16)?);
+        let width = reader.read_u16()?;
+        let height = reader.read_u16()?;
+        let horizontal_resolution = reader.read_u32()?;
+        let vertical_resolution = reader.read_u32()?;
+        reader.skip(4)?; // Reserved
+        let frame_count = reader.read_u16()?;
+        let mut compressor_name = [0; 32];
+        compressor_name.copy_from_slice(&reader.read_bytes(32)?);
+        let depth = reader.read_u16()?;
+        let pre_defined = reader.read_u16()?;
 
         Ok(Self {
+            data_reference_index,
+            reserved,
+            width,
+            height,
+            horizontal_resolution,
+            vertical_resolution,
+            frame_count,
+            compressor_name,
+            depth,
+            pre_defined,
+        })
+    }
+}
+
+impl Writer for VisualSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_zeros(6)?; // Reserved
+        writer.write_u16(self.data_reference_index)?;
+        writer.write_bytes(&self.reserved)?;
+        writer.write_u16(self.width)?;
+        writer.write_u16(self.height)?;
+        writer.write_u32(self.horizontal_resolution)?;
+        writer.write_u32(self.vertical_resolution)?;
+        writer.write_zeros(4)?; // Reserved
+        writer.write_u16(self.frame_count)?;
+        writer.write_bytes(&self.compressor_name)?;
+        writer.write_u16(self.depth)?;
+        writer.write_u16(self.pre_defined)?;
+        Ok(VISUAL_SAMPLE_ENTRY_LENGTH)
+    }
+}
+
+// ISO/IEC 14496-12 12.2.3 Audio Sample Entry, common to every audio codec
+// https://developer.apple.com/documentation/quicktime-file-format/sound_sample_description_version_1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioSampleEntry {
+    pub data_reference_index: u16,
+    pub version: u16, // QuickTime sound description version, 0 for ISO files
+    pub revision: u16,
+    pub vendor: u32,
+    pub channel_count: u16,
+    pub sample_size: u16,
+    pub compression_id: u16,
+    pub packet_size: u16,
+    pub sample_rate: u32,      // 16.16 fix point
+    pub qt_extension: Vec<u8>, // QuickTime version 1 or 2 fields
+}
+
+// Size of the audio sample entry fields before the child boxes, version 0
+pub const AUDIO_SAMPLE_ENTRY_LENGTH: u64 = 28;
+
+impl AudioSampleEntry {
+    pub fn length(&self) -> u64 {
+        AUDIO_SAMPLE_ENTRY_LENGTH + self.qt_extension.len() as u64
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        if self.version == 2 && self.qt_extension.len() >= 12 {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&self.qt_extension[4..12]);
+            return f64::from_bits(u64::from_be_bytes(bytes));
+        }
+        (self.sample_rate >> 16) as f64
+    }
+
+    pub fn channels(&self) -> u32 {
+        if self.version == 2 && self.qt_extension.len() >= 16 {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&self.qt_extension[12..16]);
+            return u32::from_be_bytes(bytes);
+        }
+        self.channel_count as u32
+    }
+}
+
+impl Default for AudioSampleEntry {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 1,
+            version: 0,
+            revision: 0,
+            vendor: 0,
+            channel_count: 2,
+            sample_size: 16,
+            compression_id: 0,
+            packet_size: 0,
+            sample_rate: 0,
+            qt_extension: Vec::new(),
+        }
+    }
+}
+
+impl Reader for AudioSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        reader.skip(6)?; // Reserved
+        let data_reference_index = reader.read_u16()?;
+        let version = reader.read_u16()?;
+        let revision = reader.read_u16()?;
+        let vendor = reader.read_u32()?;
+        let channel_count = reader.read_u16()?;
+        let sample_size = reader.read_u16()?;
+        let compression_id = reader.read_u16()?;
+        let packet_size = reader.read_u16()?;
+        let sample_rate = reader.read_u32()?;
+        let qt_extension = match version {
+            1 => reader.read_bytes(16)?,
+            2 => reader.read_bytes(36)?,
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            data_reference_index,
             version,
-            flags,
-            codec,
+            revision,
+            vendor,
+            channel_count,
+            sample_size,
+            compression_id,
+            packet_size,
+            sample_rate,
+            qt_extension,
         })
     }
 }
+
+impl Writer for AudioSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_zeros(6)?; // Reserved
+        writer.write_u16(self.data_reference_index)?;
+        writer.write_u16(self.version)?;
+        writer.write_u16(self.revision)?;
+        writer.write_u32(self.vendor)?;
+        writer.write_u16(self.channel_count)?;
+        writer.write_u16(self.sample_size)?;
+        writer.write_u16(self.compression_id)?;
+        writer.write_u16(self.packet_size)?;
+        writer.write_u32(self.sample_rate)?;
+        writer.write_bytes(&self.qt_extension)?;
+        Ok(self.length())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleIndex, Track};
+
+    // testdata/gen.py multi_entry.mp4 multi: two avc1 descriptions, the second video chunk uses the 1280x720 one
+    const MULTI_ENTRY: &[u8] = include_bytes!("../../testdata/multi_entry.mp4");
+    // testdata/gencodecs.py audio.mp4 audio: Opus, fLaC, ac-3, ec-3 and alac, all with stereo 16 bit or 24 bit
+    // placeholders in the audio sample entry
+    const AUDIO: &[u8] = include_bytes!("../../testdata/audio.mp4");
+
+    fn mp4a(track: &mut Track) -> &mut Mp4aSampleEntry {
+        match &mut track.media.info.sample_table.stsd.entries[0] {
+            SampleEntry::Audio(AudioCodec::Mp4a(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    fn codec_strings(data: &[u8]) -> Vec<String> {
+        let mp4 = Mp4::parse(&mut Cursor::new(data)).unwrap();
+        mp4.moov.tracks.iter().map(|track| track.codec_string().unwrap()).collect()
+    }
+
+    fn read_stsd(data: &[u8]) -> Result<SampleDescriptionBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        SampleDescriptionBox::read(&mut reader, header)
+    }
+
+    fn width(entry: &SampleEntry) -> u16 {
+        match entry.video() {
+            Some(VideoCodec::Avc(entry)) => entry.visual.width,
+            _ => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    #[test]
+    fn entries_by_description_index() {
+        let mp4 = Mp4::parse(&mut Cursor::new(MULTI_ENTRY)).unwrap();
+        let track = mp4.track(1).unwrap();
+        let stsd = &track.media.info.sample_table.stsd;
+        let widths: Vec<u16> = stsd.entries.iter().map(width).collect();
+        assert_eq!(widths, vec![640, 1280]);
+        assert!(stsd.entry(0).is_none() && stsd.entry(3).is_none());
+
+        let index = mp4.sample_index(1).unwrap();
+        let descriptions: Vec<u32> = index.iter().map(|sample| sample.description_index).collect();
+        assert_eq!(descriptions, vec![1, 1, 1, 2, 2, 2]);
+        let widths: Vec<u16> = index.iter().map(|sample| width(track.sample_entry(sample).unwrap())).collect();
+        assert_eq!(widths, vec![640, 640, 640, 1280, 1280, 1280]);
+
+        let mut written: Vec<u8> = Vec::new();
+        stsd.write(&mut BoxWriter::new(&mut written)).unwrap();
+        let start = MULTI_ENTRY.windows(4).position(|name| name == b"stsd").unwrap() - 4;
+        assert_eq!(&MULTI_ENTRY[start..start + written.len()], &written[..]);
+        assert_eq!(read_stsd(&written).unwrap().entries.len(), 2);
+
+        // A chunk pointing past the descriptions
+        let mut stbl = track.media.info.sample_table.clone();
+        stbl.stsc.table[1].2 = 3;
+        assert!(matches!(SampleIndex::new(&stbl, 12800), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn invalid_entry_count_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(MULTI_ENTRY)).unwrap();
+        let mut stsd = mp4.track(1).unwrap().media.info.sample_table.stsd.clone();
+        stsd.entries.clear();
+        let mut data: Vec<u8> = Vec::new();
+        stsd.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert!(matches!(read_stsd(&data), Err(Error::InvalidData(_))));
+
+        // More entries than headers fit in the box
+        data[15] = 3;
+        assert!(matches!(read_stsd(&data), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn audio_from_decoder_configuration() {
+        let mp4 = Mp4::parse(&mut Cursor::new(AUDIO)).unwrap();
+        let codecs: Vec<&AudioCodec> =
+            (1..=5).map(|track_id| mp4.track(track_id).unwrap().sample_entries()[0].audio().unwrap()).collect();
+        let channels: Vec<u32> = codecs.iter().map(|codec| codec.channel_count()).collect();
+        assert_eq!(channels, vec![6, 2, 6, 8, 2]);
+        let rates: Vec<u32> = codecs.iter().map(|codec| codec.sample_rate()).collect();
+        assert_eq!(rates, vec![48000, 96000, 48000, 48000, 96000]);
+        let depths: Vec<Option<u8>> = codecs.iter().map(|codec| codec.bit_depth()).collect();
+        assert_eq!(depths, vec![None, Some(24), None, None, Some(24)]);
+
+        let configs: Vec<Vec<u8>> = codecs.iter().map(|codec| codec.decoder_specific_info().unwrap()).collect();
+        assert_eq!(&configs[0][..8], b"OpusHead");
+        assert_eq!(&configs[1][..4], b"fLaC");
+        assert_eq!(configs[2].len(), 3);
+        assert_eq!(configs[3].len(), 2 + 4 + 2);
+        assert_eq!(configs[4].len(), 24);
+    }
+
+    #[test]
+    fn codec_strings_from_fixtures() {
+        assert_eq!(codec_strings(include_bytes!("../../testdata/progressive.mp4")), vec!["avc1.64001F", "mp4a.40.2"]);
+        assert_eq!(codec_strings(include_bytes!("../../testdata/hevc.mp4")), vec!["hvc1.1.6.L93.90", "mp4a.40.2"]);
+        assert_eq!(
+            codec_strings(include_bytes!("../../testdata/av1.mp4")),
+            vec!["av01.0.08M.08", "av01.0.13M.10.0.110.09.16.09.0"]
+        );
+        assert_eq!(
+            codec_strings(include_bytes!("../../testdata/vp.mp4")),
+            vec!["vp09.00.31.08", "vp09.02.51.10.01.09.16.09.01", "vp08.00.10.08.01.02.01.01.00"]
+        );
+        assert_eq!(codec_strings(AUDIO), vec!["Opus", "fLaC", "ac-3", "ec-3", "alac"]);
+        // Encrypted tracks are signaled with their original format
+        assert_eq!(codec_strings(include_bytes!("../../testdata/encrypted.mp4")), vec!["avc1.64001F", "mp4a.40.2"]);
+    }
+
+    #[test]
+    fn missing_configuration_has_no_codec_string() {
+        let mp4 = Mp4::parse(&mut Cursor::new(include_bytes!("../../testdata/progressive.mp4"))).unwrap();
+        let mut audio = mp4.track(2).unwrap().clone();
+        let mut esds = mp4a(&mut audio).esds.take().unwrap();
+        assert!(matches!(audio.codec_string(), Err(Error::BoxNotFound(_))));
+        if let Some(config) = esds.es_descriptor.decoder_config.as_mut() {
+            config.object_type_indication = 0x6B; // MPEG-1 audio
+        }
+        mp4a(&mut audio).esds = Some(esds);
+        assert_eq!(audio.codec_string().unwrap(), "mp4a.6B");
+        audio.media.info.sample_table.stsd.entries.clear();
+        assert!(matches!(audio.codec_string(), Err(Error::BoxNotFound(_))));
+
+        // Unknown sample entries are kept raw
+        let mut data: Vec<u8> = Vec::new();
+        mp4.track(1).unwrap().sample_entries()[0].write(&mut BoxWriter::new(&mut data)).unwrap();
+        data[4..8].copy_from_slice(b"xvid");
+        let mut src = Cursor::new(&data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let entry = SampleEntry::read(&mut reader, header).unwrap();
+        assert!(matches!(entry, SampleEntry::Unknown(_)));
+        assert!(matches!(entry.codec_string(), Err(Error::InvalidData(_))));
+    }
+    #[test]
+    fn visual_reserved_bytes_and_compressor_name_kept() {
+        // testdata/gen.py, QuickTime vendor and qualities, a name that is not UTF-8 and bytes after it
+        let progressive = include_bytes!("../../testdata/progressive.mp4");
+        let start = progressive.windows(4).position(|name| name == b"stsd").unwrap() - 4;
+        let size = u32::from_be_bytes(progressive[start..start + 4].try_into().unwrap()) as usize;
+        let mut data = progressive[start..start + size].to_vec();
+        let avc1 = data.windows(4).position(|name| name == b"avc1").unwrap() - 4;
+        data[avc1 + 16..avc1 + 32].copy_from_slice(b"\0\x01\0\x02appl\0\0\x02\0\0\0\x02\0");
+        data[avc1 + 50..avc1 + 82].copy_from_slice(&[[4, b'x', 0xFF, 0xFE, b'y'].as_slice(), &[7; 27]].concat());
+
+        let stsd = read_stsd(&data).unwrap();
+        let visual = match &stsd.entries[0] {
+            SampleEntry::Video(VideoCodec::Avc(entry)) => &entry.visual,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        };
+        assert_eq!(&visual.reserved[4..8], b"appl");
+        assert_eq!(visual.compressor_name(), "x\u{FFFD}\u{FFFD}y");
+        let mut written: Vec<u8> = Vec::new();
+        stsd.write(&mut BoxWriter::new(&mut written)).unwrap();
+        assert_eq!(written, data);
+
+        let mut visual = visual.clone();
+        visual.set_compressor_name("AVC Coding");
+        assert_eq!(visual.compressor_name(), "AVC Coding");
+        assert_eq!(visual.compressor_name[..12], *b"\x0aAVC Coding\0");
+    }
+}
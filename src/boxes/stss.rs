@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sample-to-chunk_atom
 #[derive(Clone, Debug)]
@@ -27,3 +27,16 @@ impl Reader for SyncSampleBox {
         })
     }
 }
+
+impl Writer for SyncSampleBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SyncSample, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.samples.len() as u32)?;
+            for &sample in self.samples.iter() {
+                writer.write_u32(sample)?;
+            }
+            Ok(())
+        })
+    }
+}
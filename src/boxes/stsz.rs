@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/sample_size_atom
 #[derive(Clone, Debug)]
@@ -9,6 +9,7 @@ pub struct SampleSizeBox {
     pub flags: u32,
 
     pub sample_size: u32,
+    pub sample_count: u32,
     pub sample_sizes: Vec<u32>,
 }
 
@@ -31,7 +32,24 @@ impl Reader for SampleSizeBox {
             flags,
 
             sample_size,
+            sample_count,
             sample_sizes,
         })
     }
 }
+
+impl Writer for SampleSizeBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::SampleSize, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.sample_size)?;
+            writer.write_u32(self.sample_count)?;
+            if self.sample_size == 0 {
+                for &size in self.sample_sizes.iter() {
+                    writer.write_u32(size)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
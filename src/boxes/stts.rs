@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/time-to-sample_atom/time-to-sample_table
 #[derive(Clone, Debug)]
@@ -27,3 +27,17 @@ impl Reader for TimeToSampleBox {
         })
     }
 }
+
+impl Writer for TimeToSampleBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TimeToSample, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.table.len() as u32)?;
+            for &(sample_count, sample_duration) in self.table.iter() {
+                writer.write_u32(sample_count)?;
+                writer.write_u32(sample_duration)?;
+            }
+            Ok(())
+        })
+    }
+}
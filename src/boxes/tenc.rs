@@ -0,0 +1,79 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    pssh::read_key_id, BoxHeader, BoxReader, BoxType, BoxWriter, Error, KeyId, Reader, Writer,
+};
+
+// ISO/IEC 23001-7 8.2 Track Encryption Box, defaults for the samples of a protected track
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackEncryptionBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub default_crypt_byte_block: u8, // Pattern of cens and cbcs, version 1 only
+    pub default_skip_byte_block: u8,
+    pub default_is_protected: u8,
+    pub default_per_sample_iv_size: u8, // 0, 8 or 16, 0 when a constant IV is used
+    pub default_kid: KeyId,
+    pub default_constant_iv: Vec<u8>, // Only for protected tracks without per sample IVs
+}
+
+impl TrackEncryptionBox {
+    pub fn is_protected(&self) -> bool {
+        self.default_is_protected != 0
+    }
+
+    fn has_constant_iv(&self) -> bool {
+        self.default_is_protected == 1 && self.default_per_sample_iv_size == 0
+    }
+}
+
+impl Reader for TrackEncryptionBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+
+        reader.skip(1)?; // Reserved
+        let pattern = reader.read_u8()?;
+        let (default_crypt_byte_block, default_skip_byte_block) = match version {
+            0 => (0, 0), // Reserved
+            _ => (pattern >> 4, pattern & 0x0F),
+        };
+        let mut tenc = Self {
+            version,
+            flags,
+            default_crypt_byte_block,
+            default_skip_byte_block,
+            default_is_protected: reader.read_u8()?,
+            default_per_sample_iv_size: reader.read_u8()?,
+            default_kid: read_key_id(reader)?,
+            default_constant_iv: Vec::new(),
+        };
+        if tenc.has_constant_iv() {
+            let len = reader.read_u8()?;
+            tenc.default_constant_iv = reader.read_bytes(len as usize)?;
+        }
+        Ok(tenc)
+    }
+}
+
+impl Writer for TrackEncryptionBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackEncryption, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u8(0)?;
+            let pattern = match self.version {
+                0 => 0,
+                _ => (self.default_crypt_byte_block << 4) | (self.default_skip_byte_block & 0x0F),
+            };
+            writer.write_u8(pattern)?;
+            writer.write_u8(self.default_is_protected)?;
+            writer.write_u8(self.default_per_sample_iv_size)?;
+            writer.write_bytes(&self.default_kid)?;
+            if self.has_constant_iv() {
+                writer.write_u8(self.default_constant_iv.len() as u8)?;
+                writer.write_bytes(&self.default_constant_iv)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// ISO/IEC 14496-12 8.8.12 Track Fragment Decode Time Box
+#[derive(Clone, Debug)]
+pub struct TrackFragmentDecodeTimeBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub base_media_decode_time: u64,
+}
+
+impl Reader for TrackFragmentDecodeTimeBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let base_media_decode_time = match version {
+            0 => reader.read_u32()? as u64,
+            1 => reader.read_u64()?,
+            _ => return Err(Error::InvalidData(format!("Tfdt: unknown version {:?}", version))),
+        };
+
+        Ok(Self {
+            version,
+            flags,
+
+            base_media_decode_time,
+        })
+    }
+}
+
+impl Writer for TrackFragmentDecodeTimeBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackFragmentDecodeTime, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            match self.version {
+                0 => writer.write_u32(self.base_media_decode_time as u32),
+                1 => writer.write_u64(self.base_media_decode_time),
+                _ => Err(Error::InvalidData(format!("Tfdt: unknown version {:?}", self.version))),
+            }
+        })
+    }
+}
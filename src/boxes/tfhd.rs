@@ -0,0 +1,95 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+pub const TFHD_BASE_DATA_OFFSET: u32 = 0x000001;
+pub const TFHD_SAMPLE_DESCRIPTION_INDEX: u32 = 0x000002;
+pub const TFHD_DEFAULT_SAMPLE_DURATION: u32 = 0x000008;
+pub const TFHD_DEFAULT_SAMPLE_SIZE: u32 = 0x000010;
+pub const TFHD_DEFAULT_SAMPLE_FLAGS: u32 = 0x000020;
+pub const TFHD_DURATION_IS_EMPTY: u32 = 0x010000;
+pub const TFHD_DEFAULT_BASE_IS_MOOF: u32 = 0x020000;
+
+// ISO/IEC 14496-12 8.8.7 Track Fragment Header Box
+#[derive(Clone, Debug, Default)]
+pub struct TrackFragmentHeaderBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub track_id: u32,
+    pub base_data_offset: Option<u64>,
+    pub sample_description_index: Option<u32>,
+    pub default_sample_duration: Option<u32>,
+    pub default_sample_size: Option<u32>,
+    pub default_sample_flags: Option<u32>,
+}
+
+impl Reader for TrackFragmentHeaderBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let track_id = reader.read_u32()?;
+        let base_data_offset = match flags & TFHD_BASE_DATA_OFFSET {
+            0 => None,
+            _ => Some(reader.read_u64()?),
+        };
+        let sample_description_index = match flags & TFHD_SAMPLE_DESCRIPTION_INDEX {
+            0 => None,
+            _ => Some(reader.read_u32()?),
+        };
+        let default_sample_duration = match flags & TFHD_DEFAULT_SAMPLE_DURATION {
+            0 => None,
+            _ => Some(reader.read_u32()?),
+        };
+        let default_sample_size = match flags & TFHD_DEFAULT_SAMPLE_SIZE {
+            0 => None,
+            _ => Some(reader.read_u32()?),
+        };
+        let default_sample_flags = match flags & TFHD_DEFAULT_SAMPLE_FLAGS {
+            0 => None,
+            _ => Some(reader.read_u32()?),
+        };
+
+        Ok(Self {
+            version,
+            flags,
+
+            track_id,
+            base_data_offset,
+            sample_description_index,
+            default_sample_duration,
+            default_sample_size,
+            default_sample_flags,
+        })
+    }
+}
+
+impl TrackFragmentHeaderBox {
+    pub fn default_base_is_moof(&self) -> bool {
+        self.flags & TFHD_DEFAULT_BASE_IS_MOOF != 0
+    }
+}
+
+impl Writer for TrackFragmentHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackFragmentHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.track_id)?;
+            if let Some(base_data_offset) = self.base_data_offset {
+                writer.write_u64(base_data_offset)?;
+            }
+            if let Some(sample_description_index) = self.sample_description_index {
+                writer.write_u32(sample_description_index)?;
+            }
+            if let Some(default_sample_duration) = self.default_sample_duration {
+                writer.write_u32(default_sample_duration)?;
+            }
+            if let Some(default_sample_size) = self.default_sample_size {
+                writer.write_u32(default_sample_size)?;
+            }
+            if let Some(default_sample_flags) = self.default_sample_flags {
+                writer.write_u32(default_sample_flags)?;
+            }
+            Ok(())
+        })
+    }
+}
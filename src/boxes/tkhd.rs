@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Matrix, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Matrix, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/track_header_atom
 #[derive(Clone, Debug)]
@@ -76,3 +76,40 @@ impl Reader for TrackHeaderBox {
         })
     }
 }
+
+impl Writer for TrackHeaderBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackHeader, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            match self.version {
+                0 => {
+                    writer.write_u32(self.creation_time as u32)?;
+                    writer.write_u32(self.modification_time as u32)?;
+                    writer.write_u32(self.track_id)?;
+                    writer.write_u32(0)?; // Reserved
+                    writer.write_u32(self.duration as u32)?;
+                }
+                1 => {
+                    writer.write_u64(self.creation_time)?;
+                    writer.write_u64(self.modification_time)?;
+                    writer.write_u32(self.track_id)?;
+                    writer.write_u32(0)?; // Reserved
+                    writer.write_u64(self.duration)?;
+                }
+                _ => return Err(Error::InvalidData(format!("Tkhd: unknown version {:?}", self.version))),
+            }
+            writer.write_zeros(8)?; // Reserved
+
+            writer.write_u16(self.layer)?;
+            writer.write_u16(self.alternate_group)?;
+            writer.write_u16(self.volume)?;
+
+            writer.write_zeros(2)?; // Reserved
+
+            self.matrix.write(writer)?;
+
+            writer.write_u32(self.width)?;
+            writer.write_u32(self.height)
+        })
+    }
+}
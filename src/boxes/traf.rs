@@ -0,0 +1,66 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, Error,
+    RawBox, Reader, TrackFragmentDecodeTimeBox, TrackFragmentHeaderBox, TrackRunBox, Writer,
+};
+
+// ISO/IEC 14496-12 8.8.6 Track Fragment Box
+#[derive(Clone, Debug)]
+pub struct TrackFragmentBox {
+    pub tfhd: TrackFragmentHeaderBox,
+    pub tfdt: Option<TrackFragmentDecodeTimeBox>,
+    pub truns: Vec<TrackRunBox>,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for TrackFragmentBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut tfhd: Option<TrackFragmentHeaderBox> = None;
+        let mut tfdt: Option<TrackFragmentDecodeTimeBox> = None;
+        let mut truns: Vec<TrackRunBox> = Vec::new();
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::TrackFragmentHeader => {
+                    tfhd = Some(TrackFragmentHeaderBox::read(reader, child_header)?)
+                }
+                BoxType::TrackFragmentDecodeTime => {
+                    tfdt = Some(TrackFragmentDecodeTimeBox::read(reader, child_header)?)
+                }
+                BoxType::TrackRun => truns.push(TrackRunBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if tfhd.is_none() {
+            return Err(Error::BoxNotFound("Traf: tfhd box is mandatory".to_owned()));
+        }
+        Ok(Self {
+            tfhd: tfhd.unwrap(),
+            tfdt,
+            truns,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for TrackFragmentBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackFragment, |writer| {
+            let mut children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::TrackFragmentHeader, &self.tfhd),
+                (BoxType::TrackFragmentDecodeTime, &self.tfdt),
+            ];
+            for child in self.truns.iter() {
+                children.push((BoxType::TrackRun, child));
+            }
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
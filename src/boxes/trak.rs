@@ -1,6 +1,181 @@
+use std::io::{Read, Seek, Write};
 
-use crate::ListBox;
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChildWriter, EditBox,
+    Error, MediaBox, MediaTime, PresentationTimeline, ProtectionSchemeInfoBox, RawBox, Reader,
+    Sample, SampleEntry, SampleIndex, SampleReader, SeekResult, TrackHeaderBox, TrackReferenceBox,
+    Writer,
+};
 
 // https://developer.apple.com/documentation/quicktime-file-format/track_atom
+#[derive(Clone, Debug)]
+pub struct TrackBox {
+    pub tkhd: TrackHeaderBox,
+    pub tref: Option<TrackReferenceBox>,
+    pub edts: Option<EditBox>,
+    pub media: MediaBox,
+    // Child types in file order and the children without a typed field, written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
 
-pub type TrackBox = ListBox;
+pub type Track = TrackBox;
+
+impl Reader for TrackBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut tkhd: Option<TrackHeaderBox> = None;
+        let mut tref: Option<TrackReferenceBox> = None;
+        let mut edts: Option<EditBox> = None;
+        let mut media: Option<MediaBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::TrackHeader => tkhd = Some(TrackHeaderBox::read(reader, child_header)?),
+                BoxType::TrackReference => {
+                    tref = Some(TrackReferenceBox::read(reader, child_header)?)
+                }
+                BoxType::Edit => edts = Some(EditBox::read(reader, child_header)?),
+                BoxType::Media => media = Some(MediaBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if tkhd.is_none() {
+            return Err(Error::BoxNotFound("Trak: tkhd box is mandatory".to_owned()));
+        };
+        if media.is_none() {
+            return Err(Error::BoxNotFound("Trak: mdia box is mandatory".to_owned()));
+        };
+        Ok(Self {
+            tkhd: tkhd.unwrap(),
+            tref,
+            edts,
+            media: media.unwrap(),
+            order,
+            extensions,
+        })
+    }
+}
+
+impl TrackBox {
+    pub fn track_id(&self) -> u32 {
+        self.tkhd.track_id
+    }
+
+    // Track enabled flag of tkhd, disabled tracks such as chapter text are not played
+    pub fn is_enabled(&self) -> bool {
+        self.tkhd.flags & 1 != 0
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.media.media_header.timescale
+    }
+
+    // Media duration from mdhd
+    pub fn duration(&self) -> MediaTime {
+        MediaTime::new(self.media.media_header.duration as i64, self.timescale())
+    }
+
+    // Duration of the presentation from tkhd, which is in movie timescale
+    pub fn presentation_duration(&self, movie_timescale: u32) -> MediaTime {
+        MediaTime::new(self.tkhd.duration as i64, movie_timescale)
+    }
+
+    // Track ids this track references with a type, empty without tref
+    pub fn references(&self, kind: BoxType) -> &[u32] {
+        match &self.tref {
+            Some(tref) => tref.get(kind),
+            None => &[],
+        }
+    }
+
+    pub fn sample_entries(&self) -> &[SampleEntry] {
+        &self.media.info.sample_table.stsd.entries
+    }
+
+    // Sample description applying to a sample
+    pub fn sample_entry(&self, sample: &Sample) -> Option<&SampleEntry> {
+        self.media.info.sample_table.stsd.entry(sample.description_index)
+    }
+
+    // Protection of the first encv or enca sample description
+    pub fn protection(&self) -> Option<&ProtectionSchemeInfoBox> {
+        self.sample_entries().iter().find_map(|entry| entry.protection())
+    }
+
+    // RFC 6381 codec string of the first sample description
+    pub fn codec_string(&self) -> Result<String, Error> {
+        match self.sample_entries().first() {
+            Some(entry) => entry.codec_string(),
+            None => Err(Error::BoxNotFound("Stsd: no sample entry".to_owned())),
+        }
+    }
+
+    pub fn sample_index(&self) -> Result<SampleIndex, Error> {
+        SampleIndex::new(&self.media.info.sample_table, self.timescale())
+    }
+
+    // Presented samples after applying the edit list, movie_timescale comes from mvhd
+    pub fn presentation_timeline(
+        &self,
+        movie_timescale: u32,
+    ) -> Result<PresentationTimeline, Error> {
+        Ok(self.timeline(movie_timescale, &self.sample_index()?))
+    }
+
+    pub(crate) fn timeline(&self, movie_timescale: u32, index: &SampleIndex) -> PresentationTimeline {
+        let edit_list = self.edts.as_ref().and_then(|edts| edts.list.as_ref());
+        PresentationTimeline::new(edit_list, movie_timescale, self.timescale(), index)
+    }
+
+    // Sync sample at or before a time on the media timeline. This builds the sample index,
+    // keep a SampleIndex or a Seeker around to seek repeatedly.
+    pub fn seek(&self, time: MediaTime) -> Result<Option<SeekResult>, Error> {
+        Ok(self.sample_index()?.seek(time))
+    }
+
+    pub fn samples<'a, T: Read + Seek>(&self, src: &'a mut T) -> Result<SampleReader<'a, T>, Error> {
+        Ok(SampleReader::new(src, self.sample_index()?))
+    }
+}
+
+impl Writer for TrackBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::Track, |writer| {
+            let children: Vec<(BoxType, &dyn ChildWriter)> = vec![
+                (BoxType::TrackHeader, &self.tkhd),
+                (BoxType::TrackReference, &self.tref),
+                (BoxType::Edit, &self.edts),
+                (BoxType::Media, &self.media),
+            ];
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{MediaTime, Mp4};
+
+    // testdata/gen.py, sync samples 1 and 4 of the video track
+    const PROGRESSIVE: &[u8] = include_bytes!("../../testdata/progressive.mp4");
+
+    #[test]
+    fn seek_to_the_sync_sample() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let track = mp4.track(1).unwrap();
+        let result = track.seek(MediaTime::new(2048, 12800)).unwrap().unwrap();
+        assert_eq!((result.sample, result.sync_sample), (3, 3));
+        assert_eq!(result.time, MediaTime::new(2048, 12800));
+        assert_eq!(Some(result), track.sample_index().unwrap().seek(MediaTime::new(2048, 12800)));
+
+        // Before the second sync sample decoding starts at the first one
+        let result = track.seek(MediaTime::new(100, 1000)).unwrap().unwrap();
+        assert_eq!(result.sync_sample, 0);
+        assert_eq!(result.file_offset, 40);
+    }
+}
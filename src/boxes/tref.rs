@@ -0,0 +1,132 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children, BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer, HEADER_LENGTH,
+};
+
+// ISO/IEC 14496-12 8.3.3 Track Reference Box, maps each reference type to the referenced
+// track ids. Types are kept in file order so the box is written back unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct TrackReferenceBox {
+    pub references: Vec<TrackReference>,
+}
+
+// Tracks referenced with one type, such as BoxType::Chapter, Timecode, Hint or Subtitle
+#[derive(Clone, Debug)]
+pub struct TrackReference {
+    pub kind: BoxType,
+    pub track_ids: Vec<u32>,
+}
+
+impl TrackReferenceBox {
+    pub fn new(kind: BoxType, track_ids: Vec<u32>) -> Self {
+        Self {
+            references: vec![TrackReference { kind, track_ids }],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.references.iter().all(|reference| reference.track_ids.is_empty())
+    }
+
+    // Track ids referenced with a type, empty when there is none
+    pub fn get(&self, kind: BoxType) -> &[u32] {
+        match self.references.iter().find(|reference| reference.kind == kind) {
+            Some(reference) => &reference.track_ids,
+            None => &[],
+        }
+    }
+
+    // Reference types pointing at a track
+    pub fn kinds_of(&self, track_id: u32) -> impl Iterator<Item = BoxType> + '_ {
+        self.references
+            .iter()
+            .filter(move |reference| reference.track_ids.contains(&track_id))
+            .map(|reference| reference.kind)
+    }
+
+    // Add a track id to the list of its type, once
+    pub fn insert(&mut self, kind: BoxType, track_id: u32) {
+        match self.references.iter_mut().find(|reference| reference.kind == kind) {
+            Some(reference) if reference.track_ids.contains(&track_id) => (),
+            Some(reference) => reference.track_ids.push(track_id),
+            None => self.references.push(TrackReference {
+                kind,
+                track_ids: vec![track_id],
+            }),
+        }
+    }
+}
+
+impl Reader for TrackReferenceBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut references: Vec<TrackReference> = Vec::new();
+        read_children(reader, header, 0, |reader, child_header| {
+            let count = (child_header.size - HEADER_LENGTH) / 4;
+            let mut track_ids = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                track_ids.push(reader.read_u32()?);
+            }
+            reader.skip((child_header.size - HEADER_LENGTH) % 4)?;
+            references.push(TrackReference {
+                kind: child_header.name,
+                track_ids,
+            });
+            Ok(true)
+        })?;
+        Ok(Self { references })
+    }
+}
+
+impl Writer for TrackReferenceBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackReference, |writer| {
+            for reference in self.references.iter() {
+                writer.write_box(reference.kind, |writer| {
+                    for &track_id in reference.track_ids.iter() {
+                        writer.write_u32(track_id)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn read_tref(data: &[u8]) -> Result<TrackReferenceBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        TrackReferenceBox::read(&mut reader, header)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut tref = TrackReferenceBox::new(BoxType::Chapter, vec![3]);
+        tref.insert(BoxType::Synchronization, 1);
+        tref.insert(BoxType::Synchronization, 2);
+        tref.insert(BoxType::Synchronization, 1);
+        let mut data: Vec<u8> = Vec::new();
+        tref.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(data.len(), 8 + 12 + 16);
+
+        let tref = read_tref(&data).unwrap();
+        assert_eq!(tref.get(BoxType::Chapter), &[3]);
+        assert_eq!(tref.get(BoxType::Synchronization), &[1, 2]);
+        assert!(tref.get(BoxType::Hint).is_empty());
+        assert_eq!(tref.kinds_of(2).collect::<Vec<_>>(), vec![BoxType::Synchronization]);
+    }
+
+    #[test]
+    fn short_child_rejected() {
+        let data = [0, 0, 0, 16, b't', b'r', b'e', b'f', 0, 0, 0, 4, b'c', b'h', b'a', b'p'];
+        assert!(matches!(read_tref(&data), Err(Error::InvalidData(_))));
+    }
+}
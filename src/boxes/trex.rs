@@ -0,0 +1,46 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
+
+// ISO/IEC 14496-12 8.8.3 Track Extends Box
+#[derive(Clone, Debug, Default)]
+pub struct TrackExtendsBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub track_id: u32,
+    pub default_sample_description_index: u32,
+    pub default_sample_duration: u32,
+    pub default_sample_size: u32,
+    pub default_sample_flags: u32,
+}
+
+impl Reader for TrackExtendsBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, _header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+
+        Ok(Self {
+            version,
+            flags,
+
+            track_id: reader.read_u32()?,
+            default_sample_description_index: reader.read_u32()?,
+            default_sample_duration: reader.read_u32()?,
+            default_sample_size: reader.read_u32()?,
+            default_sample_flags: reader.read_u32()?,
+        })
+    }
+}
+
+impl Writer for TrackExtendsBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackExtends, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.track_id)?;
+            writer.write_u32(self.default_sample_description_index)?;
+            writer.write_u32(self.default_sample_duration)?;
+            writer.write_u32(self.default_sample_size)?;
+            writer.write_u32(self.default_sample_flags)
+        })
+    }
+}
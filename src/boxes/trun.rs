@@ -0,0 +1,193 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer, HEADER_LENGTH};
+
+pub const TRUN_DATA_OFFSET: u32 = 0x000001;
+pub const TRUN_FIRST_SAMPLE_FLAGS: u32 = 0x000004;
+pub const TRUN_SAMPLE_DURATION: u32 = 0x000100;
+pub const TRUN_SAMPLE_SIZE: u32 = 0x000200;
+pub const TRUN_SAMPLE_FLAGS: u32 = 0x000400;
+pub const TRUN_SAMPLE_COMPOSITION_TIME_OFFSET: u32 = 0x000800;
+
+// sample_is_non_sync_sample bit of the sample flags
+pub const SAMPLE_FLAG_NON_SYNC: u32 = 0x00010000;
+
+// ISO/IEC 14496-12 8.8.8 Track Fragment Run Box
+#[derive(Clone, Debug, Default)]
+pub struct TrackRunBox {
+    pub version: u8,
+    pub flags: u32,
+
+    pub data_offset: Option<i32>,
+    pub first_sample_flags: Option<u32>,
+    pub entries: Vec<TrackRunEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrackRunEntry {
+    pub sample_duration: Option<u32>,
+    pub sample_size: Option<u32>,
+    pub sample_flags: Option<u32>,
+    // Unsigned in version 0, signed in version 1
+    pub sample_composition_time_offset: Option<i64>,
+}
+
+impl Reader for TrackRunBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        // Every entry field present in the flags takes 4 bytes
+        let entry_size = 4 * (flags
+            & (TRUN_SAMPLE_DURATION | TRUN_SAMPLE_SIZE | TRUN_SAMPLE_FLAGS | TRUN_SAMPLE_COMPOSITION_TIME_OFFSET))
+            .count_ones() as u64;
+        let fields_size = 4 * ((flags & (TRUN_DATA_OFFSET | TRUN_FIRST_SAMPLE_FLAGS)).count_ones() as u64);
+        let entries_size = header
+            .size
+            .checked_sub(HEADER_LENGTH + 4 + 4 + fields_size)
+            .ok_or(Error::InvalidData("Trun: box is too short".to_owned()))?;
+        let sample_count = reader.read_u32()?;
+        let data_offset = match flags & TRUN_DATA_OFFSET {
+            0 => None,
+            _ => Some(reader.read_i32()?),
+        };
+        let first_sample_flags = match flags & TRUN_FIRST_SAMPLE_FLAGS {
+            0 => None,
+            _ => Some(reader.read_u32()?),
+        };
+        if sample_count as u64 * entry_size > entries_size {
+            return Err(Error::InvalidData(format!("Trun: {:?} samples overrun the box", sample_count)));
+        }
+        let mut entries = Vec::new();
+        entries.try_reserve_exact(sample_count as usize).map_err(|_| {
+            Error::InvalidData(format!("Trun: no memory for {:?} samples", sample_count))
+        })?;
+        for _ in 0..sample_count {
+            let mut entry = TrackRunEntry::default();
+            if flags & TRUN_SAMPLE_DURATION != 0 {
+                entry.sample_duration = Some(reader.read_u32()?);
+            }
+            if flags & TRUN_SAMPLE_SIZE != 0 {
+                entry.sample_size = Some(reader.read_u32()?);
+            }
+            if flags & TRUN_SAMPLE_FLAGS != 0 {
+                entry.sample_flags = Some(reader.read_u32()?);
+            }
+            if flags & TRUN_SAMPLE_COMPOSITION_TIME_OFFSET != 0 {
+                entry.sample_composition_time_offset = Some(match version {
+                    0 => reader.read_u32()? as i64,
+                    _ => reader.read_i32()? as i64,
+                });
+            }
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            version,
+            flags,
+
+            data_offset,
+            first_sample_flags,
+            entries,
+        })
+    }
+}
+
+impl Writer for TrackRunBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::TrackRun, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u32(self.entries.len() as u32)?;
+            if let Some(data_offset) = self.data_offset {
+                writer.write_i32(data_offset)?;
+            }
+            if let Some(first_sample_flags) = self.first_sample_flags {
+                writer.write_u32(first_sample_flags)?;
+            }
+            for entry in self.entries.iter() {
+                if let Some(sample_duration) = entry.sample_duration {
+                    writer.write_u32(sample_duration)?;
+                }
+                if let Some(sample_size) = entry.sample_size {
+                    writer.write_u32(sample_size)?;
+                }
+                if let Some(sample_flags) = entry.sample_flags {
+                    writer.write_u32(sample_flags)?;
+                }
+                if let Some(offset) = entry.sample_composition_time_offset {
+                    let out_of_range = || {
+                        Error::InvalidData(format!(
+                            "Trun: composition offset {:?} out of range for version {:?}",
+                            offset, self.version
+                        ))
+                    };
+                    match self.version {
+                        0 => writer.write_u32(u32::try_from(offset).map_err(|_| out_of_range())?)?,
+                        _ => writer.write_i32(i32::try_from(offset).map_err(|_| out_of_range())?)?,
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn trun(version: u8, offset: u32) -> Vec<u8> {
+        let flags = TRUN_SAMPLE_SIZE | TRUN_SAMPLE_COMPOSITION_TIME_OFFSET;
+        let mut data = vec![0, 0, 0, 24];
+        data.extend_from_slice(b"trun");
+        data.extend_from_slice(&((version as u32) << 24 | flags).to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&100u32.to_be_bytes());
+        data.extend_from_slice(&offset.to_be_bytes());
+        data
+    }
+
+    fn read_trun(data: &[u8]) -> Result<TrackRunBox, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        TrackRunBox::read(&mut reader, header)
+    }
+
+    fn write_trun(trun: &TrackRunBox) -> Result<Vec<u8>, Error> {
+        let mut data: Vec<u8> = Vec::new();
+        trun.write(&mut BoxWriter::new(&mut data))?;
+        Ok(data)
+    }
+
+    #[test]
+    fn unsigned_offsets_in_version_0() {
+        let data = trun(0, 0x8000_0000);
+        let trun = read_trun(&data).unwrap();
+        assert_eq!(trun.entries[0].sample_size, Some(100));
+        assert_eq!(trun.entries[0].sample_composition_time_offset, Some(0x8000_0000));
+        assert_eq!(write_trun(&trun).unwrap(), data);
+    }
+
+    #[test]
+    fn signed_offsets_in_version_1() {
+        let data = trun(1, -512i32 as u32);
+        let mut trun = read_trun(&data).unwrap();
+        assert_eq!(trun.entries[0].sample_composition_time_offset, Some(-512));
+        assert_eq!(write_trun(&trun).unwrap(), data);
+
+        trun.version = 0;
+        assert!(matches!(write_trun(&trun), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn sample_count_past_the_box_rejected() {
+        let mut data = trun(0, 0);
+        data[12..16].copy_from_slice(&0xFFFFFFF0u32.to_be_bytes());
+        assert!(matches!(read_trun(&data), Err(Error::InvalidData(_))));
+        data[12..16].copy_from_slice(&2u32.to_be_bytes());
+        assert!(matches!(read_trun(&data), Err(Error::InvalidData(_))));
+        let short = [0, 0, 0, 12, b't', b'r', b'u', b'n', 0, 0, 0, 0];
+        assert!(matches!(read_trun(&short), Err(Error::InvalidData(_))));
+    }
+}
@@ -1,5 +1,91 @@
-use crate::ListBox;
+use std::io::{Read, Seek, Write};
 
-// https://developer.apple.com/documentation/quicktime-file-format/movie_header_atom
+use crate::{
+    read_children, write_children, BoxHeader, BoxReader, BoxType, BoxWriter, ChapterListBox,
+    ChildWriter, Error, MetaBox, RawBox, Reader, Writer,
+};
 
-pub type UserDataBox = ListBox;
+// https://developer.apple.com/documentation/quicktime-file-format/user_data_atom
+#[derive(Clone, Debug, Default)]
+pub struct UserDataBox {
+    pub meta: Option<MetaBox>,
+    pub chpl: Option<ChapterListBox>,
+    // Child types in file order and the other user data (©xyz, name...), written back as read
+    pub order: Vec<BoxType>,
+    pub extensions: Vec<RawBox>,
+}
+
+impl Reader for UserDataBox {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let mut meta: Option<MetaBox> = None;
+        let mut chpl: Option<ChapterListBox> = None;
+        let mut order: Vec<BoxType> = Vec::new();
+        let extensions = read_children(reader, header, 0, |reader, child_header| {
+            order.push(child_header.name);
+            match child_header.name {
+                BoxType::Meta => meta = Some(MetaBox::read(reader, child_header)?),
+                BoxType::ChapterList => chpl = Some(ChapterListBox::read(reader, child_header)?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        Ok(Self {
+            meta,
+            chpl,
+            order,
+            extensions,
+        })
+    }
+}
+
+impl Writer for UserDataBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::UserData, |writer| {
+            let children: Vec<(BoxType, &dyn ChildWriter)> =
+                vec![(BoxType::Meta, &self.meta), (BoxType::ChapterList, &self.chpl)];
+            write_children(writer, &self.order, children, &self.extensions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn raw_box(name: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(name);
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn child_order_kept() {
+        // An empty chpl between two other user data boxes, meta last
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"mdir");
+        hdlr.extend_from_slice(&[0; 13]);
+        let mut meta = vec![0; 4];
+        meta.extend(raw_box(b"hdlr", &hdlr));
+        let mut content = raw_box(b"\xa9xyz", b"+48.8+002.3/");
+        content.extend(raw_box(b"chpl", &[1, 0, 0, 0, 0, 0, 0, 0, 0]));
+        content.extend(raw_box(b"name", b"Clip"));
+        content.extend(raw_box(b"meta", &meta));
+        let data = raw_box(b"udta", &content);
+
+        let mut src = Cursor::new(&data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let udta = UserDataBox::read(&mut reader, header).unwrap();
+        assert!(udta.chpl.is_some());
+        assert_eq!(udta.meta.as_ref().unwrap().hdlr.handler, "mdir");
+        let name = BoxType::from(u32::from_be_bytes(*b"name"));
+        assert_eq!(udta.order[1..], [BoxType::ChapterList, name, BoxType::Meta]);
+
+        let mut written: Vec<u8> = Vec::new();
+        udta.write(&mut BoxWriter::new(&mut written)).unwrap();
+        assert_eq!(written, data);
+    }
+}
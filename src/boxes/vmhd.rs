@@ -1,6 +1,6 @@
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxType, BoxWriter, Error, Reader, Writer};
 
 // https://developer.apple.com/documentation/quicktime-file-format/video_media_information_header_atom
 #[derive(Clone, Debug)]
@@ -32,3 +32,15 @@ impl Reader for VideoInfoBox {
         })
     }
 }
+
+impl Writer for VideoInfoBox {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::VideoInfo, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u16(self.graphics_mode)?;
+            writer.write_u16(self.op_color.0)?;
+            writer.write_u16(self.op_color.1)?;
+            writer.write_u16(self.op_color.2)
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    read_children,
+    stsd::{VisualSampleEntry, VISUAL_SAMPLE_ENTRY_LENGTH},
+    BoxHeader, BoxReader, BoxType, BoxWriter, Error, RawBox, Reader, Writer, HEADER_LENGTH,
+};
+
+// VP Codec ISO Media File Format Binding 2.2 VP Sample Entry, vp08 or vp09
+#[derive(Clone, Debug)]
+pub struct VpSampleEntry {
+    pub name: BoxType,
+    pub visual: VisualSampleEntry,
+    pub config: VpCodecConfigurationRecord,
+    pub extensions: Vec<RawBox>, // Other child boxes: btrt, pasp, colr...
+}
+
+impl Reader for VpSampleEntry {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let visual = VisualSampleEntry::read(reader, header)?;
+        let mut config: Option<VpCodecConfigurationRecord> = None;
+        let extensions = read_children(
+            reader,
+            header,
+            VISUAL_SAMPLE_ENTRY_LENGTH,
+            |reader, child_header| match child_header.name {
+                BoxType::VpConfiguration => {
+                    config = Some(VpCodecConfigurationRecord::read(reader, child_header)?);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            },
+        )?;
+
+        if config.is_none() {
+            return Err(Error::BoxNotFound(format!("{:?}: vpcC box is mandatory", header.name)));
+        }
+        Ok(Self {
+            name: header.name,
+            visual,
+            config: config.unwrap(),
+            extensions,
+        })
+    }
+}
+
+impl VpSampleEntry {
+    // VP Codec ISO Media File Format Binding codecs parameter: vp09.PP.LL.DD[.CC.cp.tc.mc.FF]
+    pub fn codec_string(&self) -> String {
+        let config = &self.config;
+        let mut codec = format!(
+            "{}.{:02}.{:02}.{:02}",
+            self.name, config.profile, config.level, config.bit_depth
+        );
+        // Optional fields are only written when they differ from the defaults
+        let is_default = config.chroma_subsampling == 1
+            && config.colour_primaries == 1
+            && config.transfer_characteristics == 1
+            && config.matrix_coefficients == 1
+            && !config.video_full_range_flag;
+        if !is_default {
+            codec.push_str(&format!(
+                ".{:02}.{:02}.{:02}.{:02}.{:02}",
+                config.chroma_subsampling,
+                config.colour_primaries,
+                config.transfer_characteristics,
+                config.matrix_coefficients,
+                config.video_full_range_flag as u8
+            ));
+        }
+        codec
+    }
+}
+
+impl Writer for VpSampleEntry {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(self.name, |writer| {
+            self.visual.write(writer)?;
+            self.config.write(writer)?;
+            self.extensions.write(writer)?;
+            Ok(())
+        })
+    }
+}
+
+// VP Codec ISO Media File Format Binding 2.3 VP Codec Configuration Box
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct VpCodecConfigurationRecord {
+    pub version: u8,
+    pub flags: u32,
+
+    pub profile: u8,
+    pub level: u8, // 10 times the VP9 level, 31 for level 3.1
+    pub bit_depth: u8,
+    pub chroma_subsampling: u8, // 0: 4:2:0 vertical, 1: 4:2:0 colocated, 2: 4:2:2, 3: 4:4:4
+    pub video_full_range_flag: bool,
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub codec_initialization_data: Vec<u8>, // Empty for VP8 and VP9
+}
+
+impl Reader for VpCodecConfigurationRecord {
+    fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error> {
+        let (version, flags) = reader.read_header_extra()?;
+        let mut config = VpCodecConfigurationRecord {
+            version,
+            flags,
+            profile: reader.read_u8()?,
+            level: reader.read_u8()?,
+            ..Default::default()
+        };
+        match version {
+            // Draft layout: colorSpace and transferFunction on 4 bits, no colour primaries
+            0 => {
+                let depth_space = reader.read_u8()?;
+                let chroma_transfer = reader.read_u8()?;
+                config.bit_depth = depth_space >> 4;
+                config.matrix_coefficients = depth_space & 0x0F;
+                config.chroma_subsampling = chroma_transfer >> 4;
+                config.transfer_characteristics = chroma_transfer & 0x0F;
+                config.video_full_range_flag = reader.read_u8()? & 0x80 != 0;
+                config.colour_primaries = 2; // Unspecified
+            }
+            _ => {
+                let depth_chroma = reader.read_u8()?;
+                config.bit_depth = depth_chroma >> 4;
+                config.chroma_subsampling = (depth_chroma >> 1) & 0x07;
+                config.video_full_range_flag = depth_chroma & 0x01 != 0;
+                config.colour_primaries = reader.read_u8()?;
+                config.transfer_characteristics = reader.read_u8()?;
+                config.matrix_coefficients = reader.read_u8()?;
+            }
+        }
+        let len = reader.read_u16()?;
+        let fixed_size = if version == 0 { 7 } else { 8 };
+        let parsed_size = HEADER_LENGTH + 4 + fixed_size + len as u64;
+        if parsed_size > header.size {
+            return Err(Error::InvalidData(format!("VpcC: initialization data of {:?} bytes overruns the box", len)));
+        }
+        config.codec_initialization_data = reader.read_bytes(len as usize)?;
+
+        // Skip trailing bytes
+        if parsed_size < header.size {
+            reader.skip(header.size - parsed_size)?;
+        }
+        Ok(config)
+    }
+}
+
+impl Writer for VpCodecConfigurationRecord {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        writer.write_box(BoxType::VpConfiguration, |writer| {
+            writer.write_header_extra(self.version, self.flags)?;
+            writer.write_u8(self.profile)?;
+            writer.write_u8(self.level)?;
+            match self.version {
+                0 => {
+                    writer.write_u8(self.bit_depth << 4 | self.matrix_coefficients & 0x0F)?;
+                    writer.write_u8(self.chroma_subsampling << 4 | self.transfer_characteristics & 0x0F)?;
+                    writer.write_u8((self.video_full_range_flag as u8) << 7)?;
+                }
+                _ => {
+                    writer.write_u8(
+                        self.bit_depth << 4
+                            | (self.chroma_subsampling & 0x07) << 1
+                            | self.video_full_range_flag as u8,
+                    )?;
+                    writer.write_u8(self.colour_primaries)?;
+                    writer.write_u8(self.transfer_characteristics)?;
+                    writer.write_u8(self.matrix_coefficients)?;
+                }
+            }
+            writer.write_u16(self.codec_initialization_data.len() as u16)?;
+            writer.write_bytes(&self.codec_initialization_data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, SampleEntry, VideoCodec};
+
+    // testdata/gencodecs.py vp.mp4 vp: vp09 8 bit BT.709, vp09 10 bit PQ full range with pasp,
+    // vp08 with a version 0 vpcC
+    const VP: &[u8] = include_bytes!("../../testdata/vp.mp4");
+
+    fn vp_entry(mp4: &Mp4, track_id: u32) -> &VpSampleEntry {
+        match &mp4.track(track_id).unwrap().sample_entries()[0] {
+            SampleEntry::Video(VideoCodec::Vp9(entry) | VideoCodec::Vp8(entry)) => entry,
+            entry => panic!("unexpected sample entry {:?}", entry),
+        }
+    }
+
+    fn read_vpcc(data: &[u8]) -> Result<VpCodecConfigurationRecord, Error> {
+        let mut src = Cursor::new(data);
+        let mut reader = BoxReader::new(&mut src);
+        let header = BoxHeader::read(&mut reader)?;
+        VpCodecConfigurationRecord::read(&mut reader, header)
+    }
+
+    #[test]
+    fn vp_entries_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(VP)).unwrap();
+        assert!(matches!(mp4.track(3).unwrap().sample_entries()[0], SampleEntry::Video(VideoCodec::Vp8(_))));
+
+        let sdr = &vp_entry(&mp4, 1).config;
+        assert_eq!((sdr.version, sdr.profile, sdr.level, sdr.bit_depth, sdr.chroma_subsampling), (1, 0, 31, 8, 1));
+        assert_eq!((sdr.colour_primaries, sdr.transfer_characteristics, sdr.matrix_coefficients), (1, 1, 1));
+        assert!(!sdr.video_full_range_flag && sdr.codec_initialization_data.is_empty());
+
+        let hdr = vp_entry(&mp4, 2);
+        assert_eq!((hdr.visual.width, hdr.visual.height), (3840, 2160));
+        let config = &hdr.config;
+        assert_eq!((config.profile, config.level, config.bit_depth), (2, 51, 10));
+        assert_eq!((config.colour_primaries, config.transfer_characteristics, config.matrix_coefficients), (9, 16, 9));
+        assert!(config.video_full_range_flag);
+        assert_eq!(hdr.extensions.len(), 1);
+
+        // The draft layout has no colour primaries
+        let draft = &vp_entry(&mp4, 3).config;
+        assert_eq!((draft.version, draft.profile, draft.level, draft.bit_depth), (0, 0, 10, 8));
+        assert_eq!(draft.chroma_subsampling, 1);
+        assert_eq!((draft.colour_primaries, draft.transfer_characteristics, draft.matrix_coefficients), (2, 1, 1));
+
+        let starts: Vec<usize> = (0..VP.len() - 4).filter(|&i| &VP[i..i + 3] == b"vp0").map(|i| i - 4).collect();
+        for (track_id, start) in [(1, starts[0]), (2, starts[1]), (3, starts[2])] {
+            let mut written: Vec<u8> = Vec::new();
+            vp_entry(&mp4, track_id).write(&mut BoxWriter::new(&mut written)).unwrap();
+            assert_eq!(&VP[start..start + written.len()], &written[..]);
+        }
+    }
+
+    #[test]
+    fn overrunning_initialization_data_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(VP)).unwrap();
+        let mut config = vp_entry(&mp4, 1).config.clone();
+        config.codec_initialization_data = vec![1, 2, 3];
+        let mut data: Vec<u8> = Vec::new();
+        config.write(&mut BoxWriter::new(&mut data)).unwrap();
+        assert_eq!(read_vpcc(&data).unwrap(), config);
+
+        // Claim 4 bytes of initialization data with 3 in the box
+        data[19] = 4;
+        data.push(0);
+        assert!(matches!(read_vpcc(&data), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    BoxHeader, BoxType, BoxWriter, ChapterListBox, ChapterListEntry, Error, MediaTime, RawBox,
+    SampleEntry, CHPL_TIMESCALE,
+};
+
+// A chapter runs from its start to the start of the next one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chapter {
+    pub start: MediaTime,
+    pub title: String,
+}
+
+impl Chapter {
+    pub fn new(start: MediaTime, title: &str) -> Self {
+        Self {
+            start,
+            title: title.to_owned(),
+        }
+    }
+}
+
+// QuickTime text and 3GPP timed text samples: a 16 bit length then the text, UTF-16 when it
+// starts with a byte order mark, else UTF-8. Modifier boxes after the text are ignored.
+pub(crate) fn decode_text_sample(data: &[u8]) -> String {
+    if data.len() < 2 {
+        return String::new();
+    }
+    let len = u16::from_be_bytes([data[0], data[1]]) as usize;
+    let text = &data[2..(2 + len).min(data.len())];
+    let utf16 = |from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> =
+            text[2..].chunks_exact(2).map(|unit| from_bytes([unit[0], unit[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    match text {
+        [0xFE, 0xFF, ..] => utf16(u16::from_be_bytes),
+        [0xFF, 0xFE, ..] => utf16(u16::from_le_bytes),
+        _ => String::from_utf8_lossy(text).into_owned(),
+    }
+}
+
+pub(crate) fn encode_text_sample(text: &str) -> Vec<u8> {
+    // Texts longer than 65535 bytes are cut on a character boundary
+    let mut len = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    let text = &text.as_bytes()[..len];
+    let mut data = Vec::with_capacity(text.len() + 2);
+    data.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    data.extend_from_slice(text);
+    data
+}
+
+// 3GPP TS 26.245 tx3g sample entry with a single default font, enough for chapter titles
+pub(crate) fn text_sample_entry() -> Result<SampleEntry, Error> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut writer = BoxWriter::new(&mut data);
+    writer.write_zeros(6)?; // Reserved
+    writer.write_u16(1)?; // Data reference index
+    writer.write_u32(0)?; // Display flags
+    writer.write_u8(1)?; // Horizontal justification: center
+    writer.write_u8(0xFF)?; // Vertical justification: bottom
+    writer.write_u32(0)?; // Background color
+    writer.write_zeros(8)?; // Default text box
+    // Default style: font 1, 18 points, opaque white
+    writer.write_u16(0)?;
+    writer.write_u16(0)?;
+    writer.write_u16(1)?;
+    writer.write_u8(0)?;
+    writer.write_u8(18)?;
+    writer.write_u32(0xFFFFFFFF)?;
+    writer.write_box(BoxType::FontTable, |writer| {
+        writer.write_u16(1)?;
+        writer.write_u16(1)?;
+        writer.write_u8(10)?;
+        writer.write_bytes(b"Sans-Serif")
+    })?;
+    Ok(SampleEntry::Unknown(RawBox {
+        header: BoxHeader {
+            name: BoxType::Tx3g,
+            start: 0,
+            size: 0,
+        },
+        data,
+    }))
+}
+
+// Nero chapter list, negative starts are clamped to 0
+pub(crate) fn chapter_list(chapters: &[Chapter]) -> ChapterListBox {
+    ChapterListBox {
+        version: 1,
+        flags: 0,
+        chapters: chapters
+            .iter()
+            .map(|chapter| ChapterListEntry {
+                start: chapter.start.rescale(CHPL_TIMESCALE).value.max(0) as u64,
+                title: chapter.title.clone(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Mp4, Mp4Muxer, TrackConfig};
+
+    // testdata/gen.py, its mp4a sample entry is reused by the muxed track
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    #[test]
+    fn text_sample_roundtrip() {
+        let data = encode_text_sample("Chapter é");
+        assert_eq!(&data[..2], &[0, 10]);
+        assert_eq!(decode_text_sample(&data), "Chapter é");
+
+        let mut utf16 = vec![0, 6, 0xFE, 0xFF, 0, b'O', 0, b'k'];
+        utf16.extend_from_slice(b"styl"); // Modifier boxes are ignored
+        assert_eq!(decode_text_sample(&utf16), "Ok");
+    }
+
+    #[test]
+    fn long_text_cut_on_a_character_boundary() {
+        // 65535 falls in the middle of the last two bytes character
+        let text = "é".repeat(40000);
+        let data = encode_text_sample(&text);
+        assert_eq!(u16::from_be_bytes([data[0], data[1]]), 65534);
+        assert_eq!(decode_text_sample(&data), "é".repeat(32767));
+    }
+
+    #[test]
+    fn muxed_chapters_read_back() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let config = TrackConfig {
+            handler: "soun".to_owned(),
+            timescale: 48000,
+            language: "und".to_owned(),
+            width: 0,
+            height: 0,
+            codec: mp4.track(2).unwrap().sample_entries()[0].clone(),
+        };
+        let mut dst = Cursor::new(Vec::new());
+        let mut muxer = Mp4Muxer::new(&mut dst);
+        let track_id = muxer.add_track(config).unwrap();
+        for i in 0..100u64 {
+            muxer.push(track_id, &[0; 8], i * 960, i as i64 * 960, true).unwrap();
+        }
+        let long_title = "ü".repeat(200);
+        muxer.chapters = vec![
+            Chapter::new(MediaTime::new(1, 1), "Second"),
+            Chapter::new(MediaTime::new(0, 1), &long_title),
+        ];
+        muxer.finish().unwrap();
+
+        let mut src = Cursor::new(dst.into_inner());
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        let chapters = mp4.chapters(&mut src).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter::new(MediaTime::new(0, 1000), &long_title),
+                Chapter::new(MediaTime::new(1000, 1000), "Second"),
+            ]
+        );
+        // The Nero list keeps 127 characters of the title in its 255 bytes
+        let chpl = mp4.moov.udta.as_ref().unwrap().chpl.as_ref().unwrap();
+        assert_eq!(chpl.chapters[0].title, "ü".repeat(127));
+        assert_eq!(chpl.chapters[1].start, 10_000_000);
+    }
+}
@@ -1,9 +1,9 @@
 use std::{
     fmt,
-    io::{Read, Seek},
+    io::{Read, Seek, Write},
 };
 
-use crate::{BoxHeader, BoxReader, Error, Reader};
+use crate::{BoxHeader, BoxReader, BoxWriter, Error, Reader, Writer};
 
 #[derive(Clone, Copy)]
 pub struct Matrix {
@@ -33,7 +33,16 @@ impl Reader for Matrix {
         })
     }
 }
-    
+
+impl Writer for Matrix {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        for value in [self.a, self.b, self.u, self.c, self.d, self.v, self.x, self.y, self.w] {
+            writer.write_i32(value)?;
+        }
+        Ok(36)
+    }
+}
+
 impl Matrix {
     fn write_matrix(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
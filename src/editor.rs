@@ -0,0 +1,577 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::{
+    parse, BoxType, BoxWriter, ChunkOffset64Box, ChunkOffsetBox, CoverArt, DataBox, Error,
+    HandlerBox, ItemListBox, MetaBox, Metadata, MetadataItem, MetadataKey, MetadataValue, MoovBox,
+    Reader, UserDataBox, Writer, HEADER_LENGTH,
+};
+
+// Free space left after a moov that grew in front of the media data, for later edits
+const DEFAULT_PADDING: u32 = 1024;
+
+// Size of the buffer used to shift the media data
+const COPY_BUFFER_SIZE: u64 = 1 << 20;
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::InvalidData(error.to_string())
+}
+
+// Top level box position in the file
+#[derive(Clone, Copy, Debug)]
+struct FileBox {
+    name: BoxType,
+    start: u64,
+    size: u64,    // header included
+    to_end: bool, // size 0 in the file, the box extends to the end of the file
+}
+
+impl FileBox {
+    fn end(&self) -> u64 {
+        self.start + self.size
+    }
+
+    fn is_padding(&self) -> bool {
+        matches!(self.name, BoxType::Free | BoxType::Skip)
+    }
+}
+
+// Name, header length and whole box
+type BoxBytes<'a> = (BoxType, usize, &'a [u8]);
+
+// Box at the start of data
+fn split_box(data: &[u8]) -> Result<BoxBytes<'_>, Error> {
+    let error = || Error::InvalidData("Mp4Editor: truncated box".to_owned());
+    let word = |offset: usize| -> Result<u32, Error> {
+        let bytes = data.get(offset..offset + 4).ok_or_else(error)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let name = BoxType::from(word(4)?);
+    let (header_length, size) = match word(0)? {
+        0 => (8, data.len()),
+        1 => (16, ((word(8)? as u64) << 32 | word(12)? as u64) as usize),
+        size => (8, size as usize),
+    };
+    if size < header_length || size > data.len() {
+        return Err(error());
+    }
+    Ok((name, header_length, &data[..size]))
+}
+
+// Child boxes of a box content, trailing bytes too short for a box are dropped
+fn children(content: &[u8]) -> Result<Vec<BoxBytes<'_>>, Error> {
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while offset + HEADER_LENGTH as usize <= content.len() {
+        let child = split_box(&content[offset..])?;
+        offset += child.2.len();
+        boxes.push(child);
+    }
+    Ok(boxes)
+}
+
+fn make_box(name: BoxType, content: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::with_capacity(content.len() + HEADER_LENGTH as usize);
+    BoxWriter::new(&mut data).write_box(name, |writer| writer.write_bytes(content))?;
+    Ok(data)
+}
+
+fn read_box<B: Reader>(data: &[u8]) -> Result<B, Error> {
+    let mut cursor = Cursor::new(data);
+    let mut parser = parse(&mut cursor);
+    let header = parser.next_header()?;
+    B::read(parser.get_reader(), header)
+}
+
+fn write_box<B: Writer>(value: &B) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    value.write(&mut BoxWriter::new(&mut data))?;
+    Ok(data)
+}
+
+// Add shift to the chunk offsets at or after from, stco tables that overflow become co64
+fn shift_chunk_offsets(content: &[u8], from: u64, shift: u64) -> Result<Vec<u8>, Error> {
+    let mut patched = Vec::with_capacity(content.len());
+    for (name, header_length, data) in children(content)? {
+        let shift_offset = |offset: u64| if offset >= from { offset + shift } else { offset };
+        match name {
+            BoxType::Track | BoxType::Media | BoxType::MediaInfo | BoxType::SampleTable => {
+                let content = shift_chunk_offsets(&data[header_length..], from, shift)?;
+                patched.extend(make_box(name, &content)?);
+            }
+            BoxType::ChunkOffset => {
+                let stco: ChunkOffsetBox = read_box(data)?;
+                let table: Vec<u64> =
+                    stco.table.iter().map(|offset| shift_offset(*offset as u64)).collect();
+                if table.iter().all(|offset| *offset <= u32::MAX as u64) {
+                    let table = table.into_iter().map(|offset| offset as u32).collect();
+                    patched.extend(write_box(&ChunkOffsetBox { table, ..stco })?);
+                } else {
+                    let co64 = ChunkOffset64Box {
+                        version: 0,
+                        flags: 0,
+                        table,
+                    };
+                    patched.extend(write_box(&co64)?);
+                }
+            }
+            BoxType::ChunkOffset64 => {
+                let mut co64: ChunkOffset64Box = read_box(data)?;
+                co64.table.iter_mut().for_each(|offset| *offset = shift_offset(*offset));
+                patched.extend(write_box(&co64)?);
+            }
+            _ => patched.extend_from_slice(data),
+        }
+    }
+    Ok(patched)
+}
+
+// Rewrites the iTunes metadata of a file in place. Only moov is written back, with its other
+// boxes copied byte for byte. Size changes are absorbed by free or skip padding inside
+// udta/meta or around moov. When that is not enough moov moves to the end of the file, or
+// with fast_start the data following moov is shifted and the chunk offsets patched.
+#[derive(Debug)]
+pub struct Mp4Editor<'a, T: 'a> {
+    pub fast_start: bool, // Keep moov in front of the media data, shifting it if needed
+    pub padding: u32,     // Free space left after a shifted moov
+    file: &'a mut T,
+    boxes: Vec<FileBox>,
+    moov: usize,
+    moov_data: Vec<u8>,
+    udta: UserDataBox,
+    file_size: u64,
+}
+
+impl<'a, T: Read + Write + Seek> Mp4Editor<'a, T> {
+    pub fn new(file: &'a mut T) -> Result<Self, Error> {
+        let file_size = file.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let mut boxes = Vec::new();
+        let mut start = 0;
+        while start + HEADER_LENGTH <= file_size {
+            file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+            let mut parser = parse(&mut *file);
+            let header = parser.next_header()?;
+            let header_length = parser.get_reader().stream_position()? - start;
+            // Large sizes do not count their own 8 bytes
+            let size = match header.size {
+                0 => file_size - start,
+                size => size + header_length - HEADER_LENGTH,
+            };
+            if size < header_length || start + size > file_size {
+                return Err(Error::InvalidData(format!(
+                    "Mp4Editor: {:?} box overruns the file",
+                    header.name
+                )));
+            }
+            boxes.push(FileBox {
+                name: header.name,
+                start,
+                size,
+                to_end: header.size == 0,
+            });
+            start += size;
+        }
+
+        let Some(moov) = boxes.iter().position(|b| b.name == BoxType::Movie) else {
+            return Err(Error::BoxNotFound("Mp4Editor: moov box is mandatory".to_owned()));
+        };
+        let mut moov_data = vec![0; boxes[moov].size as usize];
+        file.seek(SeekFrom::Start(boxes[moov].start)).map_err(io_error)?;
+        file.read_exact(&mut moov_data).map_err(io_error)?;
+        let udta = read_box::<MoovBox>(&moov_data)?.udta;
+
+        Ok(Self {
+            fast_start: false,
+            padding: DEFAULT_PADDING,
+            file,
+            boxes,
+            moov,
+            moov_data,
+            udta: udta.unwrap_or_default(),
+            file_size,
+        })
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        match self.udta.meta.as_ref().and_then(|meta| meta.ilst.as_ref()) {
+            Some(ilst) => Metadata::new(ilst),
+            None => Metadata::default(),
+        }
+    }
+
+    fn ilst(&mut self) -> &mut ItemListBox {
+        let meta = self.udta.meta.get_or_insert_with(|| MetaBox {
+            version: 0,
+            flags: 0,
+            quicktime: false,
+            hdlr: HandlerBox {
+                version: 0,
+                flags: 0,
+                component_type: String::new(),
+                handler: "mdir".to_owned(),
+                name: String::new(),
+                null_terminated: true,
+            },
+            ilst: None,
+            order: Vec::new(),
+            extensions: Vec::new(),
+        });
+        meta.ilst.get_or_insert_with(ItemListBox::default)
+    }
+
+    // Replace every value of a tag, keeping the position of its first item
+    pub fn set_tag(&mut self, key: MetadataKey, value: MetadataValue) {
+        let item = MetadataItem {
+            data: vec![DataBox::new(&key, &value)],
+            key,
+            extensions: Vec::new(),
+        };
+        let items = &mut self.ilst().items;
+        let index = items.iter().position(|other| other.key == item.key).unwrap_or(items.len());
+        items.retain(|other| other.key != item.key);
+        items.insert(index, item);
+    }
+
+    // Returns false when the tag was not set
+    pub fn remove_tag(&mut self, key: &MetadataKey) -> bool {
+        let Some(ilst) = self.udta.meta.as_mut().and_then(|meta| meta.ilst.as_mut()) else {
+            return false;
+        };
+        let count = ilst.items.len();
+        ilst.items.retain(|item| item.key != *key);
+        ilst.items.len() != count
+    }
+
+    pub fn set_cover_art(&mut self, cover: CoverArt) {
+        self.set_tag(MetadataKey::COVER_ART, MetadataValue::Image(cover));
+    }
+
+    // Serialize udta, resizing a free box inside it to keep the size of the previous one
+    fn udta_data(&mut self, previous_size: usize) -> Result<Vec<u8>, Error> {
+        let data = write_box(&self.udta)?;
+        let delta = data.len() as i64 - previous_size as i64;
+        let padding = self
+            .udta
+            .meta
+            .iter_mut()
+            .flat_map(|meta| meta.extensions.iter_mut())
+            .chain(self.udta.extensions.iter_mut())
+            .find(|raw| matches!(raw.header.name, BoxType::Free | BoxType::Skip));
+        match padding {
+            Some(padding) if delta != 0 && padding.data.len() as i64 >= delta => {
+                let len = (padding.data.len() as i64 - delta) as usize;
+                padding.data.resize(len, 0);
+                write_box(&self.udta)
+            }
+            _ => Ok(data),
+        }
+    }
+
+    // moov with the edited udta in place of the previous one
+    fn moov_data(&mut self) -> Result<Vec<u8>, Error> {
+        let moov_data = std::mem::take(&mut self.moov_data);
+        let (_, header_length, _) = split_box(&moov_data)?;
+        let moov = children(&moov_data[header_length..])?;
+        let previous = moov.iter().find(|(name, _, _)| *name == BoxType::UserData);
+        let udta = self.udta_data(previous.map_or(0, |(_, _, data)| data.len()))?;
+
+        // The udta stays where it was, a new one goes after the other children
+        let mut content = Vec::with_capacity(moov_data.len() + udta.len());
+        let mut udta = Some(udta);
+        for (name, _, data) in moov.iter() {
+            match name {
+                BoxType::UserData => content.extend(udta.take().unwrap_or_default()),
+                _ => content.extend_from_slice(data),
+            }
+        }
+        let has_content = self.udta.meta.is_some() || !self.udta.extensions.is_empty();
+        if let Some(udta) = udta.filter(|_| has_content) {
+            content.extend(udta);
+        }
+        make_box(BoxType::Movie, &content)
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        self.file.write_all(data).map_err(io_error)
+    }
+
+    // Zero filled free box of size bytes, header included
+    fn write_free(&mut self, offset: u64, size: u64) -> Result<(), Error> {
+        let mut header = Vec::new();
+        let mut writer = BoxWriter::new(&mut header);
+        let content_size = if size > u32::MAX as u64 {
+            writer.write_u32(1)?;
+            writer.write_u32(BoxType::Free.into())?;
+            writer.write_u64(size)?;
+            size - HEADER_LENGTH - 8
+        } else {
+            writer.write_u32(size as u32)?;
+            writer.write_u32(BoxType::Free.into())?;
+            size - HEADER_LENGTH
+        };
+        self.write_at(offset, &header)?;
+        let zeros = vec![0; content_size.min(COPY_BUFFER_SIZE) as usize];
+        let mut remaining = content_size;
+        while remaining > 0 {
+            let len = remaining.min(COPY_BUFFER_SIZE);
+            self.file.write_all(&zeros[..len as usize]).map_err(io_error)?;
+            remaining -= len;
+        }
+        Ok(())
+    }
+
+    // Move the bytes from start to the end of the file by shift, from the end backwards
+    fn shift_data(&mut self, start: u64, shift: u64) -> Result<(), Error> {
+        let mut buf = vec![0; COPY_BUFFER_SIZE as usize];
+        let mut end = self.file_size;
+        while end > start {
+            let len = (end - start).min(COPY_BUFFER_SIZE);
+            end -= len;
+            let buf = &mut buf[..len as usize];
+            self.file.seek(SeekFrom::Start(end)).map_err(io_error)?;
+            self.file.read_exact(buf).map_err(io_error)?;
+            self.write_at(end + shift, buf)?;
+        }
+        Ok(())
+    }
+
+    // Write the changes to the file
+    pub fn save(mut self) -> Result<(), Error> {
+        let moov = self.moov_data()?;
+        let size = moov.len() as u64;
+
+        // moov and the padding around it
+        let mut first = self.moov;
+        while first > 0 && self.boxes[first - 1].is_padding() {
+            first -= 1;
+        }
+        let mut last = self.moov;
+        while last + 1 < self.boxes.len() && self.boxes[last + 1].is_padding() {
+            last += 1;
+        }
+        let start = self.boxes[first].start;
+        let end = self.boxes[last].end();
+        let available = end - start;
+
+        if size == available || size + HEADER_LENGTH <= available {
+            self.write_at(start, &moov)?;
+            if size < available {
+                self.write_free(start + size, available - size)?;
+            }
+            return self.file.flush().map_err(io_error);
+        }
+        if end == self.file_size {
+            // moov is last, the file can grow. A remainder too small for a free box gets one
+            // that extends past the previous end.
+            self.write_at(start, &moov)?;
+            if size < available {
+                let mut padding = available - size;
+                if padding < HEADER_LENGTH {
+                    padding += HEADER_LENGTH;
+                }
+                self.write_free(start + size, padding)?;
+            }
+            return self.file.flush().map_err(io_error);
+        }
+        if self.boxes[last + 1..].iter().any(|b| b.name == BoxType::MovieFragment) {
+            return Err(Error::InvalidData(
+                "Mp4Editor: no room to grow moov in front of movie fragments".to_owned(),
+            ));
+        }
+
+        if self.fast_start {
+            // Patched offsets can turn stco into co64 and grow moov again
+            let padding = (self.padding as u64).max(HEADER_LENGTH);
+            let (_, header_length, _) = split_box(&moov)?;
+            let mut shift = 0;
+            let moov = loop {
+                let content = shift_chunk_offsets(&moov[header_length..], end, shift)?;
+                let patched = make_box(BoxType::Movie, &content)?;
+                let needed = patched.len() as u64 + padding - available;
+                if needed <= shift {
+                    break patched;
+                }
+                shift = needed;
+            };
+            self.shift_data(end, shift)?;
+            self.write_at(start, &moov)?;
+            let size = moov.len() as u64;
+            self.write_free(start + size, available + shift - size)?;
+        } else {
+            // An open ended last box needs its size before moov is appended after it
+            let last_box = *self.boxes.last().unwrap();
+            if last_box.to_end {
+                if last_box.size > u32::MAX as u64 {
+                    return Err(Error::InvalidData(format!(
+                        "Mp4Editor: cannot append moov after a {:?} box to the end of the file",
+                        last_box.name
+                    )));
+                }
+                self.write_at(last_box.start, &(last_box.size as u32).to_be_bytes())?;
+            }
+            self.write_free(start, available)?;
+            let file_size = self.file_size;
+            self.write_at(file_size, &moov)?;
+        }
+        self.file.flush().map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageFormat, Mp4};
+
+    // testdata/genmeta.py: tagged progressive.mp4 with 16 bytes of free in meta, moov last
+    const METADATA: &[u8] = include_bytes!("../testdata/metadata.mp4");
+    // The same file with moov and 32 bytes of free in front of mdat
+    const FAST_START: &[u8] = include_bytes!("../testdata/metadata_faststart.mp4");
+    const METADATA_QT: &[u8] = include_bytes!("../testdata/metadata_qt.mp4");
+    // testdata/genfrag.py: moov followed by two moof and mdat pairs
+    const FRAGMENTED: &[u8] = include_bytes!("../testdata/fragmented.mp4");
+
+    fn edit(data: &[u8], fast_start: bool, change: impl FnOnce(&mut Mp4Editor<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut file = Cursor::new(data.to_vec());
+        let mut editor = Mp4Editor::new(&mut file).unwrap();
+        editor.fast_start = fast_start;
+        change(&mut editor);
+        editor.save().unwrap();
+        file.into_inner()
+    }
+
+    // Top level boxes as (name, size)
+    fn layout(data: &[u8]) -> Vec<(BoxType, usize)> {
+        children(data).unwrap().iter().map(|(name, _, data)| (*name, data.len())).collect()
+    }
+
+    fn payloads(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut src = Cursor::new(data);
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        let mut payloads = Vec::new();
+        for track_id in [1, 2] {
+            payloads.extend(mp4.samples(track_id, &mut src).unwrap().map(|sample| sample.unwrap().1));
+        }
+        payloads
+    }
+
+    fn tags(data: &[u8]) -> Metadata {
+        Mp4::parse(&mut Cursor::new(data)).unwrap().metadata()
+    }
+
+    fn cover() -> CoverArt {
+        CoverArt {
+            format: ImageFormat::Png,
+            data: vec![0x89; 4000],
+        }
+    }
+
+    #[test]
+    fn small_edits_absorbed_by_meta_padding() {
+        let edited = edit(FAST_START, false, |editor| {
+            editor.set_tag(MetadataKey::TITLE, MetadataValue::Text("Short".to_owned()));
+            editor.set_tag(MetadataKey::ARTIST, MetadataValue::Text("Artist and band".to_owned()));
+        });
+        assert_eq!(layout(&edited), layout(FAST_START));
+        assert_eq!(&edited[1655 + 32..], &FAST_START[1655 + 32..]);
+        let metadata = tags(&edited);
+        assert_eq!((metadata.title(), metadata.artist()), (Some("Short"), Some("Artist and band")));
+        assert_eq!(metadata.album(), Some("Alb"));
+        assert_eq!(metadata.len(), 9);
+        assert_eq!(payloads(&edited), payloads(FAST_START));
+
+        // The tag keeps its position, removing it twice only works once
+        let edited = edit(&edited, false, |editor| {
+            assert_eq!(editor.ilst().items[0].key, MetadataKey::TITLE);
+            assert!(editor.remove_tag(&MetadataKey::YEAR));
+            assert!(!editor.remove_tag(&MetadataKey::YEAR));
+        });
+        assert_eq!(layout(&edited), layout(FAST_START));
+        assert_eq!(tags(&edited).year(), None);
+    }
+
+    #[test]
+    fn fast_start_shifts_the_media_data() {
+        let edited = edit(FAST_START, true, |editor| editor.set_cover_art(cover()));
+        let names: Vec<BoxType> = layout(&edited).iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec![BoxType::FileType, BoxType::Movie, BoxType::Free, BoxType::MediaData]);
+        assert!(layout(&edited)[2].1 >= DEFAULT_PADDING as usize);
+        assert_eq!(&edited[edited.len() - 111..], &FAST_START[FAST_START.len() - 111..]);
+        assert_eq!(tags(&edited).cover_art().collect::<Vec<_>>(), vec![&cover()]);
+        assert_eq!(payloads(&edited), payloads(FAST_START));
+
+        // The padding left behind takes the next edit in place
+        let again = edit(&edited, true, |editor| {
+            editor.set_tag(MetadataKey::GENRE, MetadataValue::Text("Jazz".to_owned()))
+        });
+        assert_eq!(again.len(), edited.len());
+        assert_eq!(payloads(&again), payloads(FAST_START));
+    }
+
+    #[test]
+    fn moov_moves_to_the_end() {
+        let edited = edit(FAST_START, false, |editor| editor.set_cover_art(cover()));
+        let layout = layout(&edited);
+        let names: Vec<BoxType> = layout.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec![BoxType::FileType, BoxType::Free, BoxType::MediaData, BoxType::Movie]);
+        assert_eq!(layout[1].1, 1655 + 32);
+        assert_eq!(tags(&edited).cover_art().count(), 1);
+        assert_eq!(payloads(&edited), payloads(FAST_START));
+
+        // A moov already last grows with the file
+        let edited = edit(METADATA, false, |editor| editor.set_cover_art(cover()));
+        assert_eq!(&edited[..143], &METADATA[..143]);
+        assert_eq!(tags(&edited).cover_art().count(), 1);
+        assert_eq!(payloads(&edited), payloads(METADATA));
+    }
+
+    // Names of the moov children of a file with moov last
+    fn moov_children(data: &[u8], moov_start: usize) -> Vec<BoxType> {
+        children(&data[moov_start + 8..]).unwrap().iter().map(|(name, _, _)| *name).collect()
+    }
+
+    #[test]
+    fn udta_keeps_its_position() {
+        // METADATA with udta moved right after mvhd, moov stays the same size
+        let mut moov = children(&METADATA[143 + 8..]).unwrap();
+        let udta = moov.pop().unwrap();
+        assert_eq!(udta.0, BoxType::UserData);
+        moov.insert(1, udta);
+        let mut data = METADATA[..143 + 8].to_vec();
+        moov.iter().for_each(|(_, _, child)| data.extend_from_slice(child));
+        let order = vec![BoxType::MovieHeader, BoxType::UserData, BoxType::Track, BoxType::Track];
+        assert_eq!(moov_children(&data, 143), order);
+
+        let edited = edit(&data, false, |editor| editor.set_cover_art(cover()));
+        assert_eq!(moov_children(&edited, 143), order);
+        assert_eq!(tags(&edited).cover_art().count(), 1);
+
+        // Without udta a new one is appended
+        let progressive = include_bytes!("../testdata/progressive.mp4");
+        assert!(!moov_children(progressive, 143).contains(&BoxType::UserData));
+        let edited = edit(progressive, false, |editor| {
+            editor.set_tag(MetadataKey::TITLE, MetadataValue::Text("Title".to_owned()))
+        });
+        assert_eq!(moov_children(&edited, 143).last(), Some(&BoxType::UserData));
+        assert_eq!(tags(&edited).title(), Some("Title"));
+    }
+
+    #[test]
+    fn quicktime_meta_stays_quicktime() {
+        let edited = edit(METADATA_QT, false, |editor| {
+            editor.set_tag(MetadataKey::TITLE, MetadataValue::Text("QuickTime".to_owned()))
+        });
+        let mp4 = Mp4::parse(&mut Cursor::new(&edited)).unwrap();
+        assert!(mp4.moov.udta.as_ref().unwrap().meta.as_ref().unwrap().quicktime);
+        assert_eq!(mp4.metadata().title(), Some("QuickTime"));
+        assert_eq!(payloads(&edited), payloads(METADATA_QT));
+    }
+
+    #[test]
+    fn moov_cannot_grow_in_front_of_fragments() {
+        let mut file = Cursor::new(FRAGMENTED.to_vec());
+        let mut editor = Mp4Editor::new(&mut file).unwrap();
+        editor.set_cover_art(cover());
+        assert!(matches!(editor.save(), Err(Error::InvalidData(_))));
+        assert_eq!(file.into_inner(), FRAGMENTED);
+    }
+}
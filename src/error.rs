@@ -1,3 +1,4 @@
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Error {
@@ -10,22 +11,22 @@ pub enum Error {
     InternalError(),
 }
 
-impl Error {
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::InvalidBox(str) => str.clone(),
-            Error::InvalidData(str) => str.clone(),
-            Error::EOF() => "End of File".to_owned(),
-            Error::InvalidBoxType() => "Invalid box type".to_owned(),
-            Error::FileNotFound() => "File not found".to_owned(),
-            Error::BoxNotFound(str) => str.clone(),
-            Error::InternalError() => "Internal error".to_owned(),
+            Error::InvalidBox(str) => f.write_str(str),
+            Error::InvalidData(str) => f.write_str(str),
+            Error::EOF() => f.write_str("End of File"),
+            Error::InvalidBoxType() => f.write_str("Invalid box type"),
+            Error::FileNotFound() => f.write_str("File not found"),
+            Error::BoxNotFound(str) => f.write_str(str),
+            Error::InternalError() => f.write_str("Internal error"),
         }
     }
 }
-impl Into<String> for Error {
-    fn into(self) -> String {
-        self.to_string()
-    }
 
+impl From<Error> for String {
+    fn from(error: Error) -> String {
+        error.to_string()
+    }
 }
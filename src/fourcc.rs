@@ -10,6 +10,7 @@ pub struct FourCC {
 
 impl FourCC {
     #[inline]
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(fourcc: &str) -> u32 {
         let bytes = fourcc.as_bytes();
         u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::{
+    trun::SAMPLE_FLAG_NON_SYNC, Error, MovieExtendsBox, MovieFragmentBox, Sample,
+    TrackExtendsBox,
+};
+
+// Samples of a movie fragment, resolved per track from trex defaults,
+// tfhd overrides and trun entries
+#[derive(Clone, Debug)]
+pub struct Fragment {
+    pub sequence_number: u32,
+    pub moof: MovieFragmentBox,
+    pub tracks: Vec<FragmentTrack>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FragmentTrack {
+    pub track_id: u32,
+    pub samples: Vec<Sample>,
+}
+
+// Position of the next sample of a track, carried from one fragment to the next
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FragmentCursor {
+    pub index: u32,
+    pub decode_time: u64,
+}
+
+impl Fragment {
+    pub fn new(
+        moof: MovieFragmentBox,
+        mvex: Option<&MovieExtendsBox>,
+        cursors: &mut HashMap<u32, FragmentCursor>,
+    ) -> Result<Self, Error> {
+        let mut tracks: Vec<FragmentTrack> = Vec::with_capacity(moof.trafs.len());
+        // Without base-data-offset or default-base-is-moof, a traf continues
+        // where the data of the previous one ended
+        let mut previous_data_end = moof.start;
+        for traf in moof.trafs.iter() {
+            let tfhd = &traf.tfhd;
+            let default_trex = TrackExtendsBox::default();
+            let trex = mvex
+                .and_then(|mvex| mvex.track_extends(tfhd.track_id))
+                .unwrap_or(&default_trex);
+            let cursor = cursors.entry(tfhd.track_id).or_default();
+            if let Some(tfdt) = &traf.tfdt {
+                cursor.decode_time = tfdt.base_media_decode_time;
+            }
+
+            let base_data_offset = match tfhd.base_data_offset {
+                Some(offset) => offset,
+                None if tfhd.default_base_is_moof() => moof.start,
+                None => previous_data_end,
+            };
+            let description_index = tfhd
+                .sample_description_index
+                .unwrap_or(trex.default_sample_description_index)
+                .max(1);
+            let default_duration = tfhd
+                .default_sample_duration
+                .unwrap_or(trex.default_sample_duration);
+            let default_size = tfhd.default_sample_size.unwrap_or(trex.default_sample_size);
+            let default_flags = tfhd
+                .default_sample_flags
+                .unwrap_or(trex.default_sample_flags);
+
+            let mut samples = Vec::new();
+            let mut file_offset = base_data_offset;
+            for trun in traf.truns.iter() {
+                if let Some(data_offset) = trun.data_offset {
+                    file_offset = base_data_offset.checked_add_signed(data_offset as i64).ok_or(
+                        Error::InvalidData("Fragment: trun data offset out of range".to_owned()),
+                    )?;
+                }
+                for (i, entry) in trun.entries.iter().enumerate() {
+                    let duration = entry.sample_duration.unwrap_or(default_duration);
+                    let size = entry.sample_size.unwrap_or(default_size);
+                    let flags = match (i, trun.first_sample_flags) {
+                        (0, Some(first_sample_flags)) => entry.sample_flags.unwrap_or(first_sample_flags),
+                        _ => entry.sample_flags.unwrap_or(default_flags),
+                    };
+                    let offset = entry.sample_composition_time_offset.unwrap_or(0);
+                    let composition_time = i64::try_from(cursor.decode_time)
+                        .ok()
+                        .and_then(|decode_time| decode_time.checked_add(offset))
+                        .ok_or(Error::InvalidData("Fragment: composition time out of range".to_owned()))?;
+                    samples.push(Sample {
+                        index: cursor.index,
+                        file_offset,
+                        size,
+                        decode_time: cursor.decode_time,
+                        composition_time,
+                        duration,
+                        is_sync: flags & SAMPLE_FLAG_NON_SYNC == 0,
+                        description_index,
+                    });
+                    file_offset = file_offset
+                        .checked_add(size as u64)
+                        .ok_or(Error::InvalidData("Fragment: sample offset out of range".to_owned()))?;
+                    cursor.index += 1;
+                    cursor.decode_time = cursor
+                        .decode_time
+                        .checked_add(duration as u64)
+                        .ok_or(Error::InvalidData("Fragment: decode time out of range".to_owned()))?;
+                }
+            }
+            previous_data_end = file_offset;
+
+            match tracks.iter_mut().find(|track| track.track_id == tfhd.track_id) {
+                Some(track) => track.samples.append(&mut samples),
+                None => tracks.push(FragmentTrack {
+                    track_id: tfhd.track_id,
+                    samples,
+                }),
+            }
+        }
+
+        Ok(Self {
+            sequence_number: moof.mfhd.sequence_number,
+            moof,
+            tracks,
+        })
+    }
+
+    pub fn track(&self, track_id: u32) -> Option<&FragmentTrack> {
+        self.tracks.iter().find(|track| track.track_id == track_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{BoxWriter, Mp4, Writer};
+
+    // testdata/genfrag.py: two moof + mdat fragments of 3 video and 2 audio samples
+    const FRAGMENTED: &[u8] = include_bytes!("../testdata/fragmented.mp4");
+
+    #[test]
+    fn samples_from_trex_tfhd_and_trun() {
+        let mp4 = Mp4::parse(&mut Cursor::new(FRAGMENTED)).unwrap();
+        assert!(mp4.is_fragmented());
+        assert_eq!(mp4.fragments.len(), 2);
+
+        let fragment = &mp4.fragments[1];
+        assert_eq!(fragment.sequence_number, 2);
+        let video = &fragment.track(1).unwrap().samples;
+        assert_eq!(video.len(), 3);
+        assert_eq!(video[0].index, 3);
+        assert_eq!(
+            video.iter().map(|sample| sample.decode_time).collect::<Vec<_>>(),
+            vec![1536, 2048, 2560]
+        );
+        assert_eq!(video[0].composition_time, 2048);
+        assert_eq!(video.iter().map(|sample| sample.size).collect::<Vec<_>>(), vec![21, 21, 22]);
+        let sync: Vec<bool> = video.iter().map(|sample| sample.is_sync).collect();
+        assert_eq!(sync, vec![true, false, false]);
+        // Video data starts right after the mdat header, audio follows it
+        let mdat_start = fragment.moof.start as usize + write_moof(fragment).len();
+        assert_eq!(video[0].file_offset as usize, mdat_start + 8);
+        let audio = &fragment.track(2).unwrap().samples;
+        assert_eq!(audio.len(), 2);
+        assert_eq!(audio[0].file_offset, video[2].file_offset + 22);
+        assert_eq!(audio[1].decode_time, 3072);
+        assert!(audio.iter().all(|sample| sample.is_sync && sample.size == 7));
+    }
+
+    fn write_moof(fragment: &Fragment) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        fragment.moof.write(&mut BoxWriter::new(&mut data)).unwrap();
+        data
+    }
+
+    #[test]
+    fn moof_roundtrip() {
+        let mp4 = Mp4::parse(&mut Cursor::new(FRAGMENTED)).unwrap();
+        for fragment in mp4.fragments.iter() {
+            let data = write_moof(fragment);
+            let start = fragment.moof.start as usize;
+            assert_eq!(data, FRAGMENTED[start..start + data.len()]);
+        }
+    }
+
+    #[test]
+    fn out_of_range_times_rejected() {
+        // The first tfdt is a version 1 box with a 64 bit base media decode time
+        let tfdt = FRAGMENTED.windows(4).position(|name| name == b"tfdt").unwrap() + 8;
+        for base_media_decode_time in [u64::MAX - 10, i64::MAX as u64 + 1] {
+            let mut data = FRAGMENTED.to_vec();
+            data[tfdt..tfdt + 8].copy_from_slice(&base_media_decode_time.to_be_bytes());
+            assert!(matches!(Mp4::parse(&mut Cursor::new(&data)), Err(Error::InvalidData(_))));
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use crate::{remove_emulation_prevention, BitReader, Error};
+
+pub const AVC_NAL_SPS: u8 = 7;
+pub const AVC_NAL_PPS: u8 = 8;
+
+// Sample aspect ratios indexed by aspect_ratio_idc, ITU-T H.264 Table E-1
+const AVC_SAMPLE_ASPECT_RATIOS: [(u16, u16); 17] = [
+    (0, 0),
+    (1, 1),
+    (12, 11),
+    (10, 11),
+    (16, 11),
+    (40, 33),
+    (24, 11),
+    (20, 11),
+    (32, 11),
+    (80, 33),
+    (18, 11),
+    (15, 11),
+    (64, 33),
+    (160, 99),
+    (4, 3),
+    (3, 2),
+    (2, 1),
+];
+const EXTENDED_SAR: u8 = 255;
+
+// ITU-T H.264 7.3.2.1.1 Sequence parameter set
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AvcSequenceParameterSet {
+    pub profile_idc: u8,
+    pub constraint_set_flags: u8,
+    pub level_idc: u8,
+    pub seq_parameter_set_id: u32,
+    pub chroma_format_idc: u32, // 0: monochrome, 1: 4:2:0, 2: 4:2:2, 3: 4:4:4
+    pub separate_colour_plane_flag: bool,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub log2_max_frame_num: u8,
+    pub pic_order_cnt_type: u32,
+    pub max_num_ref_frames: u32,
+    pub pic_width_in_mbs: u32,
+    pub pic_height_in_map_units: u32,
+    pub frame_mbs_only_flag: bool, // False for interlaced coding
+    pub frame_cropping: Option<FrameCropping>,
+    pub vui: Option<AvcVuiParameters>,
+}
+
+// Crop offsets in crop units
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct FrameCropping {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+// ITU-T H.264 E.1.1 VUI parameters
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AvcVuiParameters {
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+    pub colour_description: Option<ColourDescription>,
+    pub timing_info: Option<TimingInfo>,
+    pub max_num_reorder_frames: Option<u32>, // Present with the bitstream restrictions
+    pub max_dec_frame_buffering: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ColourDescription {
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+}
+
+impl ColourDescription {
+    // PQ (SMPTE ST 2084) or HLG (ARIB STD-B67) transfer characteristics
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer_characteristics, 16 | 18)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct TimingInfo {
+    pub num_units_in_tick: u32,
+    pub time_scale: u32,
+    pub fixed_frame_rate_flag: bool,
+}
+
+// Check the NAL unit type and return the RBSP after the one byte header
+fn avc_rbsp(nal: &[u8], nal_unit_type: u8) -> Result<Vec<u8>, Error> {
+    let header = *nal.first().ok_or(Error::EOF())?;
+    if header & 0x1F != nal_unit_type {
+        return Err(Error::InvalidData(format!(
+            "H264: expected NAL unit type {:?}, found {:?}",
+            nal_unit_type,
+            header & 0x1F
+        )));
+    }
+    Ok(remove_emulation_prevention(&nal[1..]))
+}
+
+fn skip_scaling_list(bits: &mut BitReader, size: u32) -> Result<(), Error> {
+    let mut last_scale: i32 = 8;
+    let mut next_scale: i32 = 8;
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta_scale = bits.read_se()?;
+            next_scale = (last_scale + delta_scale + 256) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Ok(())
+}
+
+// ITU-T H.264 E.1.2 HRD parameters
+fn skip_hrd_parameters(bits: &mut BitReader) -> Result<(), Error> {
+    let cpb_cnt = bits.read_ue_max("H264: cpb_cnt_minus1", 31)? + 1;
+    bits.skip_bits(8)?; // bit_rate_scale, cpb_size_scale
+    for _ in 0..cpb_cnt {
+        bits.read_ue()?; // bit_rate_value_minus1
+        bits.read_ue()?; // cpb_size_value_minus1
+        bits.skip_bits(1)?; // cbr_flag
+    }
+    bits.skip_bits(20)?; // Delay and time offset lengths
+    Ok(())
+}
+
+impl AvcSequenceParameterSet {
+    // Parse a SPS NAL unit, header included
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let rbsp = avc_rbsp(nal, AVC_NAL_SPS)?;
+        let mut bits = BitReader::new(&rbsp);
+        let mut sps = AvcSequenceParameterSet {
+            profile_idc: bits.read_u8(8)?,
+            constraint_set_flags: bits.read_u8(8)?,
+            level_idc: bits.read_u8(8)?,
+            seq_parameter_set_id: bits.read_ue_max("H264: seq_parameter_set_id", 31)?,
+            chroma_format_idc: 1,
+            bit_depth_luma: 8,
+            bit_depth_chroma: 8,
+            ..Default::default()
+        };
+
+        let has_chroma_info = matches!(
+            sps.profile_idc,
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+        );
+        if has_chroma_info {
+            sps.chroma_format_idc = bits.read_ue_max("H264: chroma_format_idc", 3)?;
+            if sps.chroma_format_idc == 3 {
+                sps.separate_colour_plane_flag = bits.read_bit()?;
+            }
+            sps.bit_depth_luma = bits.read_ue_max("H264: bit_depth_luma_minus8", 6)? as u8 + 8;
+            sps.bit_depth_chroma = bits.read_ue_max("H264: bit_depth_chroma_minus8", 6)? as u8 + 8;
+            bits.skip_bits(1)?; // qpprime_y_zero_transform_bypass_flag
+            if bits.read_bit()? {
+                let count = if sps.chroma_format_idc == 3 { 12 } else { 8 };
+                for i in 0..count {
+                    if bits.read_bit()? {
+                        skip_scaling_list(&mut bits, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        sps.log2_max_frame_num = bits.read_ue_max("H264: log2_max_frame_num_minus4", 12)? as u8 + 4;
+        sps.pic_order_cnt_type = bits.read_ue_max("H264: pic_order_cnt_type", 2)?;
+        match sps.pic_order_cnt_type {
+            0 => {
+                bits.read_ue_max("H264: log2_max_pic_order_cnt_lsb_minus4", 12)?;
+            }
+            1 => {
+                bits.skip_bits(1)?; // delta_pic_order_always_zero_flag
+                bits.read_se()?; // offset_for_non_ref_pic
+                bits.read_se()?; // offset_for_top_to_bottom_field
+                for _ in 0..bits.read_ue_max("H264: num_ref_frames_in_pic_order_cnt_cycle", 255)? {
+                    bits.read_se()?; // offset_for_ref_frame
+                }
+            }
+            _ => (),
+        }
+        sps.max_num_ref_frames = bits.read_ue_max("H264: max_num_ref_frames", 16)?;
+        bits.skip_bits(1)?; // gaps_in_frame_num_value_allowed_flag
+        sps.pic_width_in_mbs = bits.read_ue()? + 1;
+        sps.pic_height_in_map_units = bits.read_ue()? + 1;
+        sps.frame_mbs_only_flag = bits.read_bit()?;
+        if !sps.frame_mbs_only_flag {
+            bits.skip_bits(1)?; // mb_adaptive_frame_field_flag
+        }
+        bits.skip_bits(1)?; // direct_8x8_inference_flag
+        if bits.read_bit()? {
+            sps.frame_cropping = Some(FrameCropping {
+                left: bits.read_ue()?,
+                right: bits.read_ue()?,
+                top: bits.read_ue()?,
+                bottom: bits.read_ue()?,
+            });
+        }
+        sps.check_dimensions()?;
+        if bits.read_bit()? {
+            sps.vui = Some(AvcVuiParameters::parse(&mut bits)?);
+        }
+        Ok(sps)
+    }
+
+    // The coded size must fit in u32 and the crop must leave at least one
+    // sample, ITU-T H.264 7.4.2.1.1
+    fn check_dimensions(&self) -> Result<(), Error> {
+        let field_factor = 2 - self.frame_mbs_only_flag as u32;
+        let coded_width = self.pic_width_in_mbs.checked_mul(16);
+        let coded_height = self
+            .pic_height_in_map_units
+            .checked_mul(16)
+            .and_then(|height| height.checked_mul(field_factor));
+        let (Some(coded_width), Some(coded_height)) = (coded_width, coded_height) else {
+            return Err(Error::InvalidData(format!(
+                "H264: coded size too large {:?}x{:?} macroblocks",
+                self.pic_width_in_mbs, self.pic_height_in_map_units
+            )));
+        };
+        let crop = self.frame_cropping.unwrap_or_default();
+        let (crop_unit_x, crop_unit_y) = self.crop_units();
+        let crop_x = crop.left.checked_add(crop.right).and_then(|x| x.checked_mul(crop_unit_x));
+        let crop_y = crop.top.checked_add(crop.bottom).and_then(|y| y.checked_mul(crop_unit_y));
+        if !matches!(crop_x, Some(x) if x < coded_width) || !matches!(crop_y, Some(y) if y < coded_height) {
+            return Err(Error::InvalidData(format!("H264: frame cropping larger than the coded size {:?}", crop)));
+        }
+        Ok(())
+    }
+
+    // Crop unit sizes in luma samples, ITU-T H.264 7.4.2.1.1
+    fn crop_units(&self) -> (u32, u32) {
+        let field_factor = 2 - self.frame_mbs_only_flag as u32;
+        match (self.chroma_format_idc, self.separate_colour_plane_flag) {
+            (0, _) | (_, true) => (1, field_factor),
+            (1, _) => (2, 2 * field_factor),
+            (2, _) => (2, field_factor),
+            _ => (1, field_factor),
+        }
+    }
+
+    pub fn coded_width(&self) -> u32 {
+        self.pic_width_in_mbs.saturating_mul(16)
+    }
+
+    pub fn coded_height(&self) -> u32 {
+        let field_factor = 2 - self.frame_mbs_only_flag as u32;
+        self.pic_height_in_map_units.saturating_mul(16).saturating_mul(field_factor)
+    }
+
+    // Width after frame cropping
+    pub fn width(&self) -> u32 {
+        let crop = self.frame_cropping.unwrap_or_default();
+        let (crop_unit_x, _) = self.crop_units();
+        self.coded_width()
+            .saturating_sub(crop.left.saturating_add(crop.right).saturating_mul(crop_unit_x))
+    }
+
+    // Height after frame cropping
+    pub fn height(&self) -> u32 {
+        let crop = self.frame_cropping.unwrap_or_default();
+        let (_, crop_unit_y) = self.crop_units();
+        self.coded_height()
+            .saturating_sub(crop.top.saturating_add(crop.bottom).saturating_mul(crop_unit_y))
+    }
+
+    // Frames per second from the VUI timing, two ticks per frame
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.vui.as_ref()?.timing_info?;
+        if timing.num_units_in_tick == 0 {
+            return None;
+        }
+        Some(timing.time_scale as f64 / (2.0 * timing.num_units_in_tick as f64))
+    }
+
+    // Whether frames are reordered (B-frames), None when the SPS does not tell
+    pub fn has_reordering(&self) -> Option<bool> {
+        let vui = self.vui.as_ref()?;
+        Some(vui.max_num_reorder_frames? > 0)
+    }
+}
+
+impl AvcVuiParameters {
+    fn parse(bits: &mut BitReader) -> Result<Self, Error> {
+        let mut vui = AvcVuiParameters {
+            video_format: 5, // Unspecified
+            ..Default::default()
+        };
+        if bits.read_bit()? {
+            let aspect_ratio_idc = bits.read_u8(8)?;
+            vui.sample_aspect_ratio = match aspect_ratio_idc {
+                EXTENDED_SAR => Some((bits.read_bits(16)? as u16, bits.read_bits(16)? as u16)),
+                _ => AVC_SAMPLE_ASPECT_RATIOS
+                    .get(aspect_ratio_idc as usize)
+                    .filter(|sar| sar.0 != 0)
+                    .copied(),
+            };
+        }
+        if bits.read_bit()? {
+            bits.skip_bits(1)?; // overscan_appropriate_flag
+        }
+        if bits.read_bit()? {
+            vui.video_format = bits.read_u8(3)?;
+            vui.video_full_range_flag = bits.read_bit()?;
+            if bits.read_bit()? {
+                vui.colour_description = Some(ColourDescription {
+                    colour_primaries: bits.read_u8(8)?,
+                    transfer_characteristics: bits.read_u8(8)?,
+                    matrix_coefficients: bits.read_u8(8)?,
+                });
+            }
+        }
+        if bits.read_bit()? {
+            bits.read_ue()?; // chroma_sample_loc_type_top_field
+            bits.read_ue()?; // chroma_sample_loc_type_bottom_field
+        }
+        if bits.read_bit()? {
+            vui.timing_info = Some(TimingInfo {
+                num_units_in_tick: bits.read_bits(32)?,
+                time_scale: bits.read_bits(32)?,
+                fixed_frame_rate_flag: bits.read_bit()?,
+            });
+        }
+        let nal_hrd_parameters_present = bits.read_bit()?;
+        if nal_hrd_parameters_present {
+            skip_hrd_parameters(bits)?;
+        }
+        let vcl_hrd_parameters_present = bits.read_bit()?;
+        if vcl_hrd_parameters_present {
+            skip_hrd_parameters(bits)?;
+        }
+        if nal_hrd_parameters_present || vcl_hrd_parameters_present {
+            bits.skip_bits(1)?; // low_delay_hrd_flag
+        }
+        bits.skip_bits(1)?; // pic_struct_present_flag
+        if bits.read_bit()? {
+            bits.skip_bits(1)?; // motion_vectors_over_pic_boundaries_flag
+            for _ in 0..4 {
+                // max_bytes_per_pic_denom, max_bits_per_mb_denom, log2_max_mv_length
+                bits.read_ue()?;
+            }
+            vui.max_num_reorder_frames = Some(bits.read_ue()?);
+            vui.max_dec_frame_buffering = Some(bits.read_ue()?);
+        }
+        Ok(vui)
+    }
+}
+
+// ITU-T H.264 7.3.2.2 Picture parameter set
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AvcPictureParameterSet {
+    pub pic_parameter_set_id: u32,
+    pub seq_parameter_set_id: u32,
+    pub entropy_coding_mode_flag: bool, // CABAC when set, CAVLC otherwise
+    pub bottom_field_pic_order_in_frame_present_flag: bool,
+    pub num_slice_groups: u32,
+    pub num_ref_idx_l0_default_active: u32,
+    pub num_ref_idx_l1_default_active: u32,
+    pub weighted_pred_flag: bool,
+    pub weighted_bipred_idc: u8,
+    pub pic_init_qp: i32,
+    pub pic_init_qs: i32,
+    pub chroma_qp_index_offset: i32,
+    pub deblocking_filter_control_present_flag: bool,
+    pub constrained_intra_pred_flag: bool,
+    pub redundant_pic_cnt_present_flag: bool,
+    pub transform_8x8_mode_flag: bool,
+    pub second_chroma_qp_index_offset: i32,
+}
+
+impl AvcPictureParameterSet {
+    // Parse a PPS NAL unit, header included. The SPS gives the chroma format
+    // needed to skip the optional scaling lists.
+    pub fn parse(nal: &[u8], sps: Option<&AvcSequenceParameterSet>) -> Result<Self, Error> {
+        let rbsp = avc_rbsp(nal, AVC_NAL_PPS)?;
+        let mut bits = BitReader::new(&rbsp);
+        let mut pps = AvcPictureParameterSet {
+            pic_parameter_set_id: bits.read_ue_max("H264: pic_parameter_set_id", 255)?,
+            seq_parameter_set_id: bits.read_ue_max("H264: seq_parameter_set_id", 31)?,
+            entropy_coding_mode_flag: bits.read_bit()?,
+            bottom_field_pic_order_in_frame_present_flag: bits.read_bit()?,
+            num_slice_groups: bits.read_ue_max("H264: num_slice_groups_minus1", 7)? + 1,
+            ..Default::default()
+        };
+        if pps.num_slice_groups > 1 {
+            match bits.read_ue_max("H264: slice_group_map_type", 6)? {
+                0 => {
+                    for _ in 0..pps.num_slice_groups {
+                        bits.read_ue()?; // run_length_minus1
+                    }
+                }
+                2 => {
+                    for _ in 0..2 * (pps.num_slice_groups - 1) {
+                        bits.read_ue()?; // top_left, bottom_right
+                    }
+                }
+                3..=5 => {
+                    bits.skip_bits(1)?; // slice_group_change_direction_flag
+                    bits.read_ue()?; // slice_group_change_rate_minus1
+                }
+                6 => {
+                    let pic_size_in_map_units = bits.read_ue()? + 1;
+                    let id_bits = (32 - (pps.num_slice_groups - 1).leading_zeros()) as usize;
+                    bits.skip_bits(pic_size_in_map_units as usize * id_bits)?;
+                }
+                _ => (),
+            }
+        }
+        pps.num_ref_idx_l0_default_active = bits.read_ue_max("H264: num_ref_idx_l0_default_active_minus1", 31)? + 1;
+        pps.num_ref_idx_l1_default_active = bits.read_ue_max("H264: num_ref_idx_l1_default_active_minus1", 31)? + 1;
+        pps.weighted_pred_flag = bits.read_bit()?;
+        pps.weighted_bipred_idc = bits.read_u8(2)?;
+        // Down to -(26 + QpBdOffsetY) with the largest bit depth
+        pps.pic_init_qp = bits.read_se_range("H264: pic_init_qp_minus26", -62, 25)? + 26;
+        pps.pic_init_qs = bits.read_se_range("H264: pic_init_qs_minus26", -26, 25)? + 26;
+        pps.chroma_qp_index_offset = bits.read_se_range("H264: chroma_qp_index_offset", -12, 12)?;
+        pps.deblocking_filter_control_present_flag = bits.read_bit()?;
+        pps.constrained_intra_pred_flag = bits.read_bit()?;
+        pps.redundant_pic_cnt_present_flag = bits.read_bit()?;
+        pps.second_chroma_qp_index_offset = pps.chroma_qp_index_offset;
+        if bits.more_rbsp_data() {
+            pps.transform_8x8_mode_flag = bits.read_bit()?;
+            if bits.read_bit()? {
+                let chroma_format_idc = sps.map_or(1, |sps| sps.chroma_format_idc);
+                let count_8x8 = if chroma_format_idc == 3 { 6 } else { 2 };
+                let count = 6 + count_8x8 * pps.transform_8x8_mode_flag as u32;
+                for i in 0..count {
+                    if bits.read_bit()? {
+                        skip_scaling_list(&mut bits, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+            pps.second_chroma_qp_index_offset = bits.read_se_range("H264: second_chroma_qp_index_offset", -12, 12)?;
+        }
+        Ok(pps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1280x720 High profile level 3.1 parameter sets from testdata/progressive.mp4
+    const SPS: &str = "6764001facd9405005bb016a02020280000003008000001e478c18cb";
+    const PPS: &str = "68ebe3cb22c0";
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn ue(value: u32) -> String {
+        let code = value as u64 + 1;
+        let length = 64 - code.leading_zeros() as usize;
+        format!("{:0width$b}", code, width = 2 * length - 1)
+    }
+
+    // Pack a bit string behind a NAL header, with the rbsp stop bit
+    fn nal(header: u8, bits: &str) -> Vec<u8> {
+        let mut bits = format!("{}1", bits);
+        while bits.len() % 8 != 0 {
+            bits.push('0');
+        }
+        let mut data = vec![header];
+        data.extend((0..bits.len()).step_by(8).map(|i| u8::from_str_radix(&bits[i..i + 8], 2).unwrap()));
+        data
+    }
+
+    // High profile 4:2:0 SPS without VUI
+    fn sps(bit_depth_luma_minus8: u32, pic_width_in_mbs_minus1: u32, crop: Option<[u32; 4]>) -> Vec<u8> {
+        let mut bits = String::from("011001000000000000011111");
+        for value in [0, 1, bit_depth_luma_minus8, 0] {
+            bits += &ue(value); // seq_parameter_set_id, chroma_format_idc, bit depths
+        }
+        bits += "00"; // qpprime_y_zero_transform_bypass_flag, seq_scaling_matrix_present_flag
+        for value in [0, 0, 0, 1] {
+            bits += &ue(value); // log2_max_frame_num_minus4, pic_order_cnt_type, lsb, max_num_ref_frames
+        }
+        bits += "0"; // gaps_in_frame_num_value_allowed_flag
+        bits += &ue(pic_width_in_mbs_minus1);
+        bits += &ue(22);
+        bits += "11"; // frame_mbs_only_flag, direct_8x8_inference_flag
+        match crop {
+            Some(offsets) => {
+                bits += "1";
+                offsets.iter().for_each(|offset| bits += &ue(*offset));
+            }
+            None => bits += "0",
+        }
+        bits += "0"; // vui_parameters_present_flag
+        nal(0x67, &bits)
+    }
+
+    #[test]
+    fn parse_sps() {
+        let sps = AvcSequenceParameterSet::parse(&hex(SPS)).unwrap();
+        assert_eq!((sps.profile_idc, sps.level_idc), (100, 31));
+        assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (8, 8));
+        assert_eq!((sps.coded_width(), sps.coded_height()), (1280, 720));
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert!(sps.vui.is_some());
+
+        let pps = AvcPictureParameterSet::parse(&hex(PPS), Some(&sps)).unwrap();
+        assert!(pps.entropy_coding_mode_flag);
+        assert!(pps.transform_8x8_mode_flag);
+    }
+
+    #[test]
+    fn parse_generated_sps() {
+        let sps = AvcSequenceParameterSet::parse(&sps(2, 39, Some([0, 0, 0, 4]))).unwrap();
+        assert_eq!(sps.bit_depth_luma, 10);
+        assert_eq!((sps.width(), sps.height()), (640, 360));
+    }
+
+    #[test]
+    fn truncated_sps_rejected() {
+        let data = hex(SPS);
+        for length in [1, 4, 8, 12] {
+            assert!(AvcSequenceParameterSet::parse(&data[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn out_of_range_sps_rejected() {
+        for data in [
+            sps(250, 39, None),
+            sps(0, 1 << 28, None),
+            sps(0, 39, Some([200, 200, 0, 0])),
+            sps(0, 39, Some([0, 0, u32::MAX - 1, u32::MAX - 1])),
+        ] {
+            assert!(matches!(AvcSequenceParameterSet::parse(&data), Err(Error::InvalidData(_))));
+        }
+    }
+}
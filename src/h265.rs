@@ -0,0 +1,537 @@
+use crate::{
+    hvcc::{HEVC_NAL_SPS, HEVC_NAL_VPS},
+    remove_emulation_prevention, BitReader, ColourDescription, Error, FrameCropping, TimingInfo,
+};
+
+// Sample aspect ratios indexed by aspect_ratio_idc, ITU-T H.265 Table E.1
+const HEVC_SAMPLE_ASPECT_RATIOS: [(u16, u16); 17] = [
+    (0, 0),
+    (1, 1),
+    (12, 11),
+    (10, 11),
+    (16, 11),
+    (40, 33),
+    (24, 11),
+    (20, 11),
+    (32, 11),
+    (80, 33),
+    (18, 11),
+    (15, 11),
+    (64, 33),
+    (160, 99),
+    (4, 3),
+    (3, 2),
+    (2, 1),
+];
+const EXTENDED_SAR: u8 = 255;
+
+// ITU-T H.265 7.3.3 Profile, tier and level, general part
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcProfileTierLevel {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    pub general_constraint_indicator_flags: u64, // 48 bits
+    pub general_level_idc: u8,
+}
+
+// ITU-T H.265 7.3.2.1 Video parameter set
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcVideoParameterSet {
+    pub vps_video_parameter_set_id: u8,
+    pub vps_max_layers: u8,
+    pub vps_max_sub_layers: u8,
+    pub vps_temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub vps_max_dec_pic_buffering: u32, // Values of the highest sub-layer
+    pub vps_max_num_reorder_pics: u32,
+    pub vps_max_latency_increase: u32,
+    pub timing_info: Option<TimingInfo>,
+}
+
+// ITU-T H.265 7.3.2.2 Sequence parameter set, up to the VUI
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcSequenceParameterSet {
+    pub sps_video_parameter_set_id: u8,
+    pub sps_max_sub_layers: u8,
+    pub sps_temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub sps_seq_parameter_set_id: u32,
+    pub chroma_format_idc: u32, // 0: monochrome, 1: 4:2:0, 2: 4:2:2, 3: 4:4:4
+    pub separate_colour_plane_flag: bool,
+    pub pic_width_in_luma_samples: u32,
+    pub pic_height_in_luma_samples: u32,
+    pub conformance_window: Option<FrameCropping>, // In chroma sample units
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub log2_max_pic_order_cnt_lsb: u8,
+    pub sps_max_dec_pic_buffering: u32, // Values of the highest sub-layer
+    pub sps_max_num_reorder_pics: u32,
+    pub sps_max_latency_increase: u32,
+    pub vui: Option<HevcVuiParameters>,
+}
+
+// ITU-T H.265 E.2.1 VUI parameters, up to the timing information
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct HevcVuiParameters {
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+    pub colour_description: Option<ColourDescription>,
+    pub field_seq_flag: bool,
+    pub default_display_window: Option<FrameCropping>,
+    pub timing_info: Option<TimingInfo>,
+}
+
+// Check the NAL unit type and return the RBSP after the two bytes header
+fn hevc_rbsp(nal: &[u8], nal_unit_type: u8) -> Result<Vec<u8>, Error> {
+    if nal.len() < 2 {
+        return Err(Error::EOF());
+    }
+    if (nal[0] >> 1) & 0x3F != nal_unit_type {
+        return Err(Error::InvalidData(format!(
+            "H265: expected NAL unit type {:?}, found {:?}",
+            nal_unit_type,
+            (nal[0] >> 1) & 0x3F
+        )));
+    }
+    Ok(remove_emulation_prevention(&nal[2..]))
+}
+
+impl HevcProfileTierLevel {
+    fn parse(bits: &mut BitReader, max_sub_layers_minus1: u8) -> Result<Self, Error> {
+        let general_profile_space = bits.read_u8(2)?;
+        let general_tier_flag = bits.read_bit()?;
+        let general_profile_idc = bits.read_u8(5)?;
+        let general_profile_compatibility_flags = bits.read_bits(32)?;
+        let general_constraint_indicator_flags = bits.read_bits_u64(48)?;
+        let general_level_idc = bits.read_u8(8)?;
+
+        let mut sub_layer_flags = Vec::with_capacity(max_sub_layers_minus1 as usize);
+        for _ in 0..max_sub_layers_minus1 {
+            // sub_layer_profile_present_flag, sub_layer_level_present_flag
+            sub_layer_flags.push((bits.read_bit()?, bits.read_bit()?));
+        }
+        if max_sub_layers_minus1 > 0 {
+            bits.skip_bits(2 * (8 - max_sub_layers_minus1 as usize))?; // Reserved
+        }
+        for (profile_present, level_present) in sub_layer_flags {
+            if profile_present {
+                bits.skip_bits(88)?;
+            }
+            if level_present {
+                bits.skip_bits(8)?;
+            }
+        }
+
+        Ok(Self {
+            general_profile_space,
+            general_tier_flag,
+            general_profile_idc,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+        })
+    }
+}
+
+// Sub-layer ordering info, returns the values of the highest sub-layer
+fn read_sub_layer_ordering_info(
+    bits: &mut BitReader,
+    max_sub_layers_minus1: u8,
+) -> Result<(u32, u32, u32), Error> {
+    let first = match bits.read_bit()? {
+        true => 0,
+        false => max_sub_layers_minus1,
+    };
+    let mut values = (0, 0, 0);
+    for _ in first..=max_sub_layers_minus1 {
+        values = (
+            bits.read_ue_max("H265: max_dec_pic_buffering_minus1", 15)? + 1,
+            bits.read_ue_max("H265: max_num_reorder_pics", 15)?,
+            bits.read_ue()?,
+        );
+    }
+    Ok(values)
+}
+
+fn read_timing_info(bits: &mut BitReader) -> Result<TimingInfo, Error> {
+    let timing_info = TimingInfo {
+        num_units_in_tick: bits.read_bits(32)?,
+        time_scale: bits.read_bits(32)?,
+        fixed_frame_rate_flag: false,
+    };
+    if bits.read_bit()? {
+        bits.read_ue()?; // num_ticks_poc_diff_one_minus1
+    }
+    Ok(timing_info)
+}
+
+// ITU-T H.265 7.3.4 Scaling list data
+fn skip_scaling_list_data(bits: &mut BitReader) -> Result<(), Error> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !bits.read_bit()? {
+                bits.read_ue()?; // scaling_list_pred_matrix_id_delta
+                continue;
+            }
+            let coef_num = 64.min(1 << (4 + (size_id << 1)));
+            if size_id > 1 {
+                bits.read_se()?; // scaling_list_dc_coef_minus8
+            }
+            for _ in 0..coef_num {
+                bits.read_se()?; // scaling_list_delta_coef
+            }
+        }
+    }
+    Ok(())
+}
+
+// ITU-T H.265 7.3.7 Short-term reference picture set, returns NumDeltaPocs
+fn read_st_ref_pic_set(
+    bits: &mut BitReader,
+    index: usize,
+    num_delta_pocs: &[u32],
+) -> Result<u32, Error> {
+    if index != 0 && bits.read_bit()? {
+        // Predicted from the previous set in the SPS, delta_idx_minus1 is only in slice headers
+        bits.skip_bits(1)?; // delta_rps_sign
+        bits.read_ue_max("H265: abs_delta_rps_minus1", (1 << 15) - 1)?;
+        let mut count = 0;
+        for _ in 0..=num_delta_pocs[index - 1] {
+            let used_by_curr_pic_flag = bits.read_bit()?;
+            if used_by_curr_pic_flag || bits.read_bit()? {
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+    let num_negative_pics = bits.read_ue_max("H265: num_negative_pics", 16)?;
+    let num_positive_pics = bits.read_ue_max("H265: num_positive_pics", 16)?;
+    for _ in 0..num_negative_pics + num_positive_pics {
+        bits.read_ue()?; // delta_poc_minus1
+        bits.skip_bits(1)?; // used_by_curr_pic_flag
+    }
+    Ok(num_negative_pics + num_positive_pics)
+}
+
+fn read_window(bits: &mut BitReader) -> Result<FrameCropping, Error> {
+    Ok(FrameCropping {
+        left: bits.read_ue()?,
+        right: bits.read_ue()?,
+        top: bits.read_ue()?,
+        bottom: bits.read_ue()?,
+    })
+}
+
+impl HevcVideoParameterSet {
+    // Parse a VPS NAL unit, header included
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let rbsp = hevc_rbsp(nal, HEVC_NAL_VPS)?;
+        let mut bits = BitReader::new(&rbsp);
+        let vps_video_parameter_set_id = bits.read_u8(4)?;
+        bits.skip_bits(2)?; // vps_base_layer_internal_flag, vps_base_layer_available_flag
+        let vps_max_layers = bits.read_u8(6)? + 1;
+        let max_sub_layers_minus1 = bits.read_u8(3)?;
+        let vps_temporal_id_nesting_flag = bits.read_bit()?;
+        bits.skip_bits(16)?; // vps_reserved_0xffff_16bits
+        let profile_tier_level = HevcProfileTierLevel::parse(&mut bits, max_sub_layers_minus1)?;
+        let (vps_max_dec_pic_buffering, vps_max_num_reorder_pics, vps_max_latency_increase) =
+            read_sub_layer_ordering_info(&mut bits, max_sub_layers_minus1)?;
+        let vps_max_layer_id = bits.read_u8(6)?;
+        let vps_num_layer_sets = bits.read_ue_max("H265: vps_num_layer_sets_minus1", 1023)? as usize + 1;
+        // layer_id_included_flag
+        bits.skip_bits((vps_num_layer_sets - 1) * (vps_max_layer_id as usize + 1))?;
+        let timing_info = match bits.read_bit()? {
+            true => Some(read_timing_info(&mut bits)?),
+            false => None,
+        };
+
+        Ok(Self {
+            vps_video_parameter_set_id,
+            vps_max_layers,
+            vps_max_sub_layers: max_sub_layers_minus1 + 1,
+            vps_temporal_id_nesting_flag,
+            profile_tier_level,
+            vps_max_dec_pic_buffering,
+            vps_max_num_reorder_pics,
+            vps_max_latency_increase,
+            timing_info,
+        })
+    }
+}
+
+impl HevcSequenceParameterSet {
+    // Parse a SPS NAL unit, header included
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let rbsp = hevc_rbsp(nal, HEVC_NAL_SPS)?;
+        let mut bits = BitReader::new(&rbsp);
+        let sps_video_parameter_set_id = bits.read_u8(4)?;
+        let max_sub_layers_minus1 = bits.read_u8(3)?;
+        if max_sub_layers_minus1 > 6 {
+            return Err(Error::InvalidData(format!(
+                "H265: invalid sps_max_sub_layers_minus1 {:?}",
+                max_sub_layers_minus1
+            )));
+        }
+        let mut sps = HevcSequenceParameterSet {
+            sps_video_parameter_set_id,
+            sps_max_sub_layers: max_sub_layers_minus1 + 1,
+            sps_temporal_id_nesting_flag: bits.read_bit()?,
+            profile_tier_level: HevcProfileTierLevel::parse(&mut bits, max_sub_layers_minus1)?,
+            sps_seq_parameter_set_id: bits.read_ue_max("H265: sps_seq_parameter_set_id", 15)?,
+            chroma_format_idc: bits.read_ue_max("H265: chroma_format_idc", 3)?,
+            ..Default::default()
+        };
+        if sps.chroma_format_idc == 3 {
+            sps.separate_colour_plane_flag = bits.read_bit()?;
+        }
+        sps.pic_width_in_luma_samples = bits.read_ue()?;
+        sps.pic_height_in_luma_samples = bits.read_ue()?;
+        if bits.read_bit()? {
+            sps.conformance_window = Some(read_window(&mut bits)?);
+        }
+        sps.check_dimensions()?;
+        sps.bit_depth_luma = bits.read_ue_max("H265: bit_depth_luma_minus8", 8)? as u8 + 8;
+        sps.bit_depth_chroma = bits.read_ue_max("H265: bit_depth_chroma_minus8", 8)? as u8 + 8;
+        sps.log2_max_pic_order_cnt_lsb = bits.read_ue_max("H265: log2_max_pic_order_cnt_lsb_minus4", 12)? as u8 + 4;
+        let ordering_info = read_sub_layer_ordering_info(&mut bits, max_sub_layers_minus1)?;
+        sps.sps_max_dec_pic_buffering = ordering_info.0;
+        sps.sps_max_num_reorder_pics = ordering_info.1;
+        sps.sps_max_latency_increase = ordering_info.2;
+
+        // Coding block and transform sizes, transform hierarchy depths
+        for _ in 0..6 {
+            bits.read_ue()?;
+        }
+        if bits.read_bit()? && bits.read_bit()? {
+            skip_scaling_list_data(&mut bits)?;
+        }
+        bits.skip_bits(2)?; // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+        if bits.read_bit()? {
+            // PCM sample bit depths and coding block sizes, pcm_loop_filter_disabled_flag
+            bits.skip_bits(8)?;
+            bits.read_ue()?;
+            bits.read_ue()?;
+            bits.skip_bits(1)?;
+        }
+        let num_short_term_ref_pic_sets = bits.read_ue()? as usize;
+        if num_short_term_ref_pic_sets > 64 {
+            return Err(Error::InvalidData(format!(
+                "H265: invalid num_short_term_ref_pic_sets {:?}",
+                num_short_term_ref_pic_sets
+            )));
+        }
+        let mut num_delta_pocs: Vec<u32> = Vec::with_capacity(num_short_term_ref_pic_sets);
+        for i in 0..num_short_term_ref_pic_sets {
+            let count = read_st_ref_pic_set(&mut bits, i, &num_delta_pocs)?;
+            num_delta_pocs.push(count);
+        }
+        if bits.read_bit()? {
+            let num_long_term_ref_pics = bits.read_ue_max("H265: num_long_term_ref_pics_sps", 32)? as usize;
+            // lt_ref_pic_poc_lsb_sps, used_by_curr_pic_lt_sps_flag
+            bits.skip_bits(num_long_term_ref_pics * (sps.log2_max_pic_order_cnt_lsb as usize + 1))?;
+        }
+        bits.skip_bits(2)?; // sps_temporal_mvp_enabled_flag, strong_intra_smoothing_enabled_flag
+        if bits.read_bit()? {
+            sps.vui = Some(HevcVuiParameters::parse(&mut bits)?);
+        }
+        Ok(sps)
+    }
+
+    // The picture must not be empty and the conformance window must leave at
+    // least one sample, ITU-T H.265 7.4.3.2.1
+    fn check_dimensions(&self) -> Result<(), Error> {
+        if self.pic_width_in_luma_samples == 0 || self.pic_height_in_luma_samples == 0 {
+            return Err(Error::InvalidData(format!(
+                "H265: invalid picture size {:?}x{:?}",
+                self.pic_width_in_luma_samples, self.pic_height_in_luma_samples
+            )));
+        }
+        let window = self.conformance_window.unwrap_or_default();
+        let (unit_x, unit_y) = self.window_units();
+        let crop_x = window.left.checked_add(window.right).and_then(|x| x.checked_mul(unit_x));
+        let crop_y = window.top.checked_add(window.bottom).and_then(|y| y.checked_mul(unit_y));
+        if !matches!(crop_x, Some(x) if x < self.pic_width_in_luma_samples)
+            || !matches!(crop_y, Some(y) if y < self.pic_height_in_luma_samples)
+        {
+            return Err(Error::InvalidData(format!(
+                "H265: conformance window larger than the picture {:?}",
+                window
+            )));
+        }
+        Ok(())
+    }
+
+    // Chroma sample units of the conformance window
+    fn window_units(&self) -> (u32, u32) {
+        match (self.chroma_format_idc, self.separate_colour_plane_flag) {
+            (1, false) => (2, 2),
+            (2, false) => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    // Width after the conformance window
+    pub fn width(&self) -> u32 {
+        let window = self.conformance_window.unwrap_or_default();
+        let (unit_x, _) = self.window_units();
+        self.pic_width_in_luma_samples
+            .saturating_sub(window.left.saturating_add(window.right).saturating_mul(unit_x))
+    }
+
+    // Height after the conformance window
+    pub fn height(&self) -> u32 {
+        let window = self.conformance_window.unwrap_or_default();
+        let (_, unit_y) = self.window_units();
+        self.pic_height_in_luma_samples
+            .saturating_sub(window.top.saturating_add(window.bottom).saturating_mul(unit_y))
+    }
+
+    // Frames per second from the VUI timing
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.vui.as_ref()?.timing_info?;
+        if timing.num_units_in_tick == 0 {
+            return None;
+        }
+        Some(timing.time_scale as f64 / timing.num_units_in_tick as f64)
+    }
+
+    // Whether pictures are reordered (B-frames)
+    pub fn has_reordering(&self) -> bool {
+        self.sps_max_num_reorder_pics > 0
+    }
+
+    // PQ or HLG transfer characteristics
+    pub fn is_hdr(&self) -> bool {
+        let colour = self.vui.as_ref().and_then(|vui| vui.colour_description);
+        colour.is_some_and(|colour| colour.is_hdr())
+    }
+}
+
+impl HevcVuiParameters {
+    fn parse(bits: &mut BitReader) -> Result<Self, Error> {
+        let mut vui = HevcVuiParameters {
+            video_format: 5, // Unspecified
+            ..Default::default()
+        };
+        if bits.read_bit()? {
+            let aspect_ratio_idc = bits.read_u8(8)?;
+            vui.sample_aspect_ratio = match aspect_ratio_idc {
+                EXTENDED_SAR => Some((bits.read_bits(16)? as u16, bits.read_bits(16)? as u16)),
+                _ => HEVC_SAMPLE_ASPECT_RATIOS
+                    .get(aspect_ratio_idc as usize)
+                    .filter(|sar| sar.0 != 0)
+                    .copied(),
+            };
+        }
+        if bits.read_bit()? {
+            bits.skip_bits(1)?; // overscan_appropriate_flag
+        }
+        if bits.read_bit()? {
+            vui.video_format = bits.read_u8(3)?;
+            vui.video_full_range_flag = bits.read_bit()?;
+            if bits.read_bit()? {
+                vui.colour_description = Some(ColourDescription {
+                    colour_primaries: bits.read_u8(8)?,
+                    transfer_characteristics: bits.read_u8(8)?,
+                    matrix_coefficients: bits.read_u8(8)?,
+                });
+            }
+        }
+        if bits.read_bit()? {
+            bits.read_ue()?; // chroma_sample_loc_type_top_field
+            bits.read_ue()?; // chroma_sample_loc_type_bottom_field
+        }
+        bits.skip_bits(1)?; // neutral_chroma_indication_flag
+        vui.field_seq_flag = bits.read_bit()?;
+        bits.skip_bits(1)?; // frame_field_info_present_flag
+        if bits.read_bit()? {
+            vui.default_display_window = Some(read_window(bits)?);
+        }
+        if bits.read_bit()? {
+            vui.timing_info = Some(read_timing_info(bits)?);
+        }
+        Ok(vui)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 640x360 Main profile parameter sets written by testdata/gen.py
+    const VPS: &str = "40010c01ffff016000000300900000030000030078959809";
+    const SPS: &str = "420101016000000300900000030000030078a00502016965959a4932bc05a80808082000000300200000030321";
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn ue(value: u32) -> String {
+        let code = value as u64 + 1;
+        let length = 64 - code.leading_zeros() as usize;
+        format!("{:0width$b}", code, width = 2 * length - 1)
+    }
+
+    // SPS up to the bit depths, enough to reach the range checks
+    fn sps_prefix(width: u32, window: Option<[u32; 4]>, bit_depth_luma_minus8: u32) -> Vec<u8> {
+        let mut bits = String::from("00000001"); // sps_video_parameter_set_id, max_sub_layers_minus1, nesting
+        bits += &format!("{:096b}", 1u128 << 88 | 120); // Main profile, level 4
+        for value in [0, 1, width, 360] {
+            bits += &ue(value); // sps_seq_parameter_set_id, chroma_format_idc, picture size
+        }
+        match window {
+            Some(offsets) => {
+                bits += "1";
+                offsets.iter().for_each(|offset| bits += &ue(*offset));
+            }
+            None => bits += "0",
+        }
+        bits += &ue(bit_depth_luma_minus8);
+        bits += &ue(0);
+        while bits.len() % 8 != 0 {
+            bits.push('0');
+        }
+        let mut data = vec![0x42, 0x01];
+        data.extend((0..bits.len()).step_by(8).map(|i| u8::from_str_radix(&bits[i..i + 8], 2).unwrap()));
+        data
+    }
+
+    #[test]
+    fn parse_parameter_sets() {
+        let vps = HevcVideoParameterSet::parse(&hex(VPS)).unwrap();
+        assert_eq!((vps.vps_max_layers, vps.vps_max_sub_layers), (1, 1));
+        assert_eq!(vps.profile_tier_level.general_profile_idc, 1);
+
+        let sps = HevcSequenceParameterSet::parse(&hex(SPS)).unwrap();
+        assert_eq!(sps.profile_tier_level.general_level_idc, 120);
+        assert_eq!((sps.width(), sps.height()), (640, 360));
+        assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (8, 8));
+        assert_eq!(sps.sps_max_num_reorder_pics, 2);
+        assert_eq!(sps.frame_rate(), Some(25.0));
+    }
+
+    #[test]
+    fn truncated_sps_rejected() {
+        let data = hex(SPS);
+        for length in [2, 8, 16, 20, 30] {
+            assert!(HevcSequenceParameterSet::parse(&data[..length]).is_err());
+        }
+        assert!(matches!(HevcSequenceParameterSet::parse(&sps_prefix(640, None, 0)), Err(Error::EOF())));
+    }
+
+    #[test]
+    fn out_of_range_sps_rejected() {
+        for data in [
+            sps_prefix(640, None, 250),
+            sps_prefix(0, None, 0),
+            sps_prefix(640, Some([160, 160, 0, 0]), 0),
+            sps_prefix(640, Some([0, 0, u32::MAX - 1, u32::MAX - 1]), 0),
+        ] {
+            assert!(matches!(HevcSequenceParameterSet::parse(&data), Err(Error::InvalidData(_))));
+        }
+    }
+}
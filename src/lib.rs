@@ -3,14 +3,42 @@ mod boxes;
 mod fourcc;
 mod error;
 mod parser;
+mod writer;
 mod macros;
 mod mp4;
 mod common;
+mod media_time;
+mod bitreader;
+mod h264;
+mod h265;
+mod sample;
+mod fragment;
+mod timeline;
+mod metadata;
+mod chapter;
+mod seek;
+mod muxer;
+mod segmenter;
+mod editor;
 
 pub use error::Error;
 pub use fourcc::FourCC as FourCC;
 pub use boxes::*;
 pub use parser::*;
+pub use writer::*;
 pub use mp4::*;
 pub use common::*;
+pub use media_time::*;
+pub use bitreader::*;
+pub use h264::*;
+pub use h265::*;
+pub use sample::*;
+pub use fragment::*;
+pub use timeline::*;
+pub use metadata::*;
+pub use chapter::*;
+pub use seek::*;
+pub use muxer::*;
+pub use segmenter::*;
+pub use editor::*;
 
@@ -0,0 +1,214 @@
+use std::{cmp::Ordering, fmt, time::Duration};
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+// A time or duration counted in timescale units per second, as stored by mvhd, mdhd, tkhd,
+// elst and the sample tables. Conversions go through 128 bit integers so they never overflow
+// and results outside the i64 range saturate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MediaTime {
+    pub value: i64,
+    pub timescale: u32, // units per second, 0 is treated as 1
+}
+
+fn saturate(value: i128) -> i64 {
+    value.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+impl MediaTime {
+    pub const fn new(value: i64, timescale: u32) -> Self {
+        Self { value, timescale }
+    }
+
+    pub fn from_duration(duration: Duration, timescale: u32) -> Self {
+        Self::from(duration).rescale_floor(timescale)
+    }
+
+    fn scale(&self) -> i128 {
+        self.timescale.max(1) as i128
+    }
+
+    // Convert to another timescale rounding to the nearest unit, halves away from zero
+    pub fn rescale(&self, timescale: u32) -> Self {
+        if timescale == self.timescale {
+            return *self;
+        }
+        let numerator = self.value as i128 * timescale as i128;
+        let half = self.scale() / 2;
+        let value = if numerator < 0 {
+            (numerator - half) / self.scale()
+        } else {
+            (numerator + half) / self.scale()
+        };
+        Self::new(saturate(value), timescale)
+    }
+
+    // Convert to another timescale rounding down, the last unit at or before this time
+    pub fn rescale_floor(&self, timescale: u32) -> Self {
+        let value = (self.value as i128 * timescale as i128).div_euclid(self.scale());
+        Self::new(saturate(value), timescale)
+    }
+
+    // Convert to another timescale rounding up, the first unit at or after this time
+    pub fn rescale_ceil(&self, timescale: u32) -> Self {
+        let value = -(-(self.value as i128) * timescale as i128).div_euclid(self.scale());
+        Self::new(saturate(value), timescale)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.value < 0
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.value as f64 / self.timescale.max(1) as f64
+    }
+
+    // None for negative times, which Duration cannot represent
+    pub fn to_duration(&self) -> Option<Duration> {
+        if self.is_negative() {
+            return None;
+        }
+        Some(Duration::from_nanos(self.rescale_floor(NANOS_PER_SECOND).value as u64))
+    }
+
+    // SMPTE timecode HH:MM:SS:FF at an integer, non drop frame, frame rate
+    pub fn smpte(&self, frame_rate: u32) -> String {
+        let frame_rate = frame_rate.max(1) as u64;
+        let frames = self.rescale_floor(frame_rate as u32).value;
+        let sign = if frames < 0 { "-" } else { "" };
+        let frames = frames.unsigned_abs();
+        let seconds = frames / frame_rate;
+        format!(
+            "{}{:02}:{:02}:{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            frames % frame_rate
+        )
+    }
+}
+
+impl From<Duration> for MediaTime {
+    fn from(duration: Duration) -> Self {
+        Self::new(saturate(duration.as_nanos() as i128), NANOS_PER_SECOND)
+    }
+}
+
+// Times compare by the instant they represent, whatever their timescales
+impl PartialEq for MediaTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MediaTime {}
+
+impl PartialOrd for MediaTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MediaTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.value as i128 * other.scale()).cmp(&(other.value as i128 * self.scale()))
+    }
+}
+
+// HH:MM:SS.mmm
+impl fmt::Display for MediaTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.rescale_floor(1000).value;
+        let sign = if millis < 0 { "-" } else { "" };
+        let millis = millis.unsigned_abs();
+        let seconds = millis / 1000;
+        write!(
+            f,
+            "{}{:02}:{:02}:{:02}.{:03}",
+            sign,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            millis % 1000
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/gen.py: mvhd at 1000 Hz, video mdhd at 12800 Hz, audio mdhd at 44100 Hz
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    #[test]
+    fn durations_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let movie = mp4.duration();
+        let video = mp4.track(1).unwrap().duration();
+        let audio = mp4.track(2).unwrap().duration();
+        assert_eq!((movie.value, movie.timescale), (200, 1000));
+        assert_eq!(video.rescale(1000), MediaTime::new(240, 1000));
+        assert_eq!(audio.rescale(1000), MediaTime::new(93, 1000));
+        assert!(audio < movie && movie < video);
+        assert_eq!(video.to_duration(), Some(Duration::from_millis(240)));
+        assert_eq!(audio.to_string(), "00:00:00.092");
+
+        let index = mp4.sample_index(2).unwrap();
+        let last = index.get(3).unwrap().pts(44100);
+        assert_eq!(last.to_duration(), Some(Duration::from_nanos(69_659_863)));
+        // The nanoseconds are truncated, only rounding to the nearest unit gets the sample back
+        let duration = last.to_duration().unwrap();
+        assert_eq!(MediaTime::from(duration).rescale(44100).value, 3072);
+        assert_eq!(MediaTime::from_duration(duration, 44100).value, 3071);
+    }
+
+    #[test]
+    fn rescale_rounding() {
+        // 1.5 and -1.5 units
+        assert_eq!(MediaTime::new(3, 2).rescale(1).value, 2);
+        assert_eq!(MediaTime::new(-3, 2).rescale(1).value, -2);
+        assert_eq!(MediaTime::new(3, 2).rescale_floor(1).value, 1);
+        assert_eq!(MediaTime::new(-3, 2).rescale_floor(1).value, -2);
+        assert_eq!(MediaTime::new(3, 2).rescale_ceil(1).value, 2);
+        assert_eq!(MediaTime::new(-3, 2).rescale_ceil(1).value, -1);
+        assert_eq!(MediaTime::new(1001, 30000).rescale(90000).value, 3003);
+
+        // 128 bit intermediates, saturated results
+        assert_eq!(MediaTime::new(i64::MAX, 1).rescale(90000).value, i64::MAX);
+        assert_eq!(MediaTime::new(i64::MIN, 1).rescale_floor(90000).value, i64::MIN);
+        assert_eq!(MediaTime::new(i64::MAX, 90000).rescale(90000).value, i64::MAX);
+        assert_eq!(MediaTime::new(i64::MAX / 2, 1000).rescale_floor(1000000).value, i64::MAX);
+        assert_eq!(MediaTime::new(5, 0).rescale(1000).value, 5000);
+    }
+
+    #[test]
+    fn compare_across_timescales() {
+        assert_eq!(MediaTime::new(1, 2), MediaTime::new(22050, 44100));
+        assert!(MediaTime::new(1, 3) > MediaTime::new(333333, 1000000));
+        assert!(MediaTime::new(-1, 1000) < MediaTime::new(0, 1));
+        assert_eq!(MediaTime::new(i64::MAX, 1).cmp(&MediaTime::new(i64::MAX, 2)), Ordering::Greater);
+        let mut times = vec![MediaTime::new(3, 4), MediaTime::new(1, 2), MediaTime::new(2, 3)];
+        times.sort();
+        assert_eq!(times, vec![MediaTime::new(1, 2), MediaTime::new(2, 3), MediaTime::new(3, 4)]);
+    }
+
+    #[test]
+    fn conversions_and_formatting() {
+        assert_eq!(MediaTime::new(-1, 1000).to_duration(), None);
+        assert_eq!(MediaTime::from(Duration::from_millis(1500)), MediaTime::new(3, 2));
+        assert_eq!(MediaTime::from_duration(Duration::from_millis(999), 1).value, 0);
+        assert_eq!(MediaTime::new(1, 4).as_secs_f64(), 0.25);
+
+        let time = MediaTime::new(3723 * 25 + 7, 25);
+        assert_eq!(time.smpte(25), "01:02:03:07");
+        assert_eq!(time.to_string(), "01:02:03.280");
+        assert_eq!(MediaTime::new(-25, 1000).to_string(), "-00:00:00.025");
+        assert_eq!(MediaTime::new(-1, 1).smpte(30), "-00:00:01:00");
+        assert_eq!(MediaTime::new(100 * 3600, 1).smpte(24), "100:00:00:00");
+    }
+}
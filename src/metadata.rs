@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use crate::{CoverArt, ItemListBox, MetadataKey, MetadataValue};
+
+// Tags of an iTunes style udta/meta/ilst, every value of an item under its key
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub tags: BTreeMap<MetadataKey, Vec<MetadataValue>>,
+}
+
+impl Metadata {
+    pub fn new(ilst: &ItemListBox) -> Self {
+        let mut tags: BTreeMap<MetadataKey, Vec<MetadataValue>> = BTreeMap::new();
+        for item in ilst.items.iter() {
+            tags.entry(item.key.clone()).or_default().extend(item.values());
+        }
+        Self { tags }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MetadataKey, &Vec<MetadataValue>)> {
+        self.tags.iter()
+    }
+
+    // First value of a tag
+    pub fn get(&self, key: &MetadataKey) -> Option<&MetadataValue> {
+        self.tags.get(key)?.first()
+    }
+
+    pub fn text(&self, key: &MetadataKey) -> Option<&str> {
+        match self.get(key)? {
+            MetadataValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn index(&self, key: &MetadataKey) -> Option<(u16, u16)> {
+        match self.get(key)? {
+            MetadataValue::Index { number, total } => Some((*number, *total)),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.text(&MetadataKey::TITLE)
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.text(&MetadataKey::ARTIST)
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.text(&MetadataKey::ALBUM)
+    }
+
+    pub fn year(&self) -> Option<&str> {
+        self.text(&MetadataKey::YEAR)
+    }
+
+    // Track number and total, 0 when unknown
+    pub fn track_number(&self) -> Option<(u16, u16)> {
+        self.index(&MetadataKey::TRACK_NUMBER)
+    }
+
+    pub fn disc_number(&self) -> Option<(u16, u16)> {
+        self.index(&MetadataKey::DISC_NUMBER)
+    }
+
+    pub fn cover_art(&self) -> impl Iterator<Item = &CoverArt> {
+        self.tags
+            .get(&MetadataKey::COVER_ART)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| match value {
+                MetadataValue::Image(image) => Some(image),
+                _ => None,
+            })
+    }
+
+    pub fn freeform(&self, mean: &str, name: &str) -> Option<&MetadataValue> {
+        self.get(&MetadataKey::freeform(mean, name))
+    }
+}
@@ -1,51 +1,282 @@
-use std::io::{Read, Seek};
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+};
 
-use crate::{BoxContent, BoxElement, BoxHeader, BoxReader, Error, ListBox };
+use crate::{
+    chapter::decode_text_sample, BoxContent, BoxHeader, BoxReader, BoxType, Chapter, Error,
+    Fragment, FragmentCursor, FtypBox, KeyId, ListBox, MediaDataBox, MediaTime, Metadata, MoovBox,
+    MovieFragmentBox, PresentationTimeline, ProtectionSystemHeaderBox, SampleIndex, SampleReader,
+    SeekResult, Seeker, Track, CHPL_TIMESCALE,
+};
 
 #[derive(Clone, Debug)]
 pub struct Mp4 {
-    pub ftyp: BoxElement,
-    pub moov: BoxElement,
-    pub mdat: BoxElement,
+    pub ftyp: FtypBox,
+    pub moov: MoovBox,
+    pub mdat: Vec<MediaDataBox>,
+    pub fragments: Vec<Fragment>,
 }
 
 impl Mp4 {
-    pub fn parse<'a, T: Read + Seek>(src: &'a mut T) -> Result<Self, Error> {
-        let mut ftyp: Option<BoxElement> = None;
-        let mut moov: Option<BoxElement> = None;
-        let mut mdat: Option<BoxElement> = None;
+    pub fn parse<T: Read + Seek>(src: &mut T) -> Result<Self, Error> {
+        let mut ftyp: Option<FtypBox> = None;
+        let mut moov: Option<MoovBox> = None;
+        let mut mdat: Vec<MediaDataBox> = Vec::new();
+        let mut moofs: Vec<MovieFragmentBox> = Vec::new();
         let header = BoxHeader::root("Mp4 ");
         let mut iter = ListBox::iter(header);
-        let mut is_wide = false;
         while let Some(child) = iter.next(&mut BoxReader::new(src))? {
-            println!("{:?}: {:?}", header.name, child);
             match child.content {
-                BoxContent::Ftyp(_) => ftyp = Some(child),
-                BoxContent::Moov(_) => moov = Some(child),
-                BoxContent::Wide(_) => {
-                    is_wide = true;
-                }
-                BoxContent::Mdat(_) => mdat = Some(child),
+                BoxContent::Ftyp(b) => ftyp = Some(b),
+                BoxContent::Moov(b) => moov = Some(b),
+                BoxContent::Mdat(b) => mdat.push(b),
+                BoxContent::Moof(b) => moofs.push(b),
                 _ => (),
             }
         }
 
-        println!("Mp4: TODO handle wide {:}", is_wide);
-    
         if ftyp.is_none() {
             return Err(Error::BoxNotFound("Mp4: Ftyp box is mandatory".to_owned()));
         }
         if moov.is_none() {
             return Err(Error::BoxNotFound("Mp4: Moov box is mandatory".to_owned()));
         }
-        if mdat.is_none() {
+        let moov = moov.unwrap();
+        // A fragmented init segment carries no media data
+        if mdat.is_empty() && moov.mvex.is_none() {
             return Err(Error::BoxNotFound("Mp4: Mdat box is mandatory".to_owned()));
         }
 
+        let fragments = Self::resolve_fragments(&moov, moofs)?;
+
         Ok(Self {
             ftyp: ftyp.unwrap(),
-            moov: moov.unwrap(),
-            mdat: mdat.unwrap(),
+            moov,
+            mdat,
+            fragments,
         })
     }
+
+    fn resolve_fragments(
+        moov: &MoovBox,
+        moofs: Vec<MovieFragmentBox>,
+    ) -> Result<Vec<Fragment>, Error> {
+        if moofs.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Fragment samples follow the ones described in the moov sample tables
+        let mut cursors: HashMap<u32, FragmentCursor> = HashMap::new();
+        for track in moov.tracks.iter() {
+            let stbl = &track.media.info.sample_table;
+            let decode_time = stbl
+                .stts
+                .table
+                .iter()
+                .map(|&(count, duration)| count as u64 * duration as u64)
+                .sum();
+            cursors.insert(
+                track.track_id(),
+                FragmentCursor {
+                    index: stbl.stsz.sample_count,
+                    decode_time,
+                },
+            );
+        }
+        let mut fragments = Vec::with_capacity(moofs.len());
+        for moof in moofs {
+            fragments.push(Fragment::new(moof, moov.mvex.as_ref(), &mut cursors)?);
+        }
+        Ok(fragments)
+    }
+
+    pub fn is_fragmented(&self) -> bool {
+        self.moov.mvex.is_some()
+    }
+
+    // Movie duration from mvhd
+    pub fn duration(&self) -> MediaTime {
+        MediaTime::new(self.moov.mvhd.duration as i64, self.moov.mvhd.timescale)
+    }
+
+    // iTunes style tags of moov/udta/meta/ilst, empty without ilst
+    pub fn metadata(&self) -> Metadata {
+        let udta = self.moov.udta.as_ref();
+        match udta.and_then(|udta| udta.meta.as_ref()).and_then(|meta| meta.ilst.as_ref()) {
+            Some(ilst) => Metadata::new(ilst),
+            None => Metadata::default(),
+        }
+    }
+
+    // Chapters of the text track referenced with tref/chap, whose samples are read from src,
+    // else of the Nero udta/chpl list. Empty without either.
+    pub fn chapters<T: Read + Seek>(&self, src: &mut T) -> Result<Vec<Chapter>, Error> {
+        let chapter_track = self
+            .tracks()
+            .iter()
+            .flat_map(|track| track.references(BoxType::Chapter))
+            .find_map(|&track_id| self.track(track_id));
+        if let Some(track) = chapter_track {
+            let timescale = track.timescale();
+            let mut chapters = Vec::new();
+            for sample in self.samples(track.track_id(), src)? {
+                let (sample, data) = sample?;
+                chapters.push(Chapter {
+                    start: sample.pts(timescale),
+                    title: decode_text_sample(&data),
+                });
+            }
+            return Ok(chapters);
+        }
+
+        let chpl = self.moov.udta.as_ref().and_then(|udta| udta.chpl.as_ref());
+        let chapters = chpl.map_or(Vec::new(), |chpl| {
+            chpl.chapters
+                .iter()
+                .map(|chapter| Chapter {
+                    start: MediaTime::new(chapter.start as i64, CHPL_TIMESCALE),
+                    title: chapter.title.clone(),
+                })
+                .collect()
+        });
+        Ok(chapters)
+    }
+
+    // pssh boxes of the moov then of every moof, in file order
+    pub fn pssh(&self) -> Vec<&ProtectionSystemHeaderBox> {
+        let moofs = self.fragments.iter().map(|fragment| &fragment.moof);
+        self.moov.pssh.iter().chain(moofs.flat_map(|moof| moof.pssh.iter())).collect()
+    }
+
+    // Key ids of the file: tenc default KIDs of every sample description, then the KIDs
+    // listed by version 1 pssh boxes, without duplicates
+    pub fn key_ids(&self) -> Vec<KeyId> {
+        let default_kids = self
+            .tracks()
+            .iter()
+            .flat_map(|track| track.sample_entries())
+            .filter_map(|entry| entry.protection()?.tenc())
+            .filter(|tenc| tenc.is_protected())
+            .map(|tenc| tenc.default_kid);
+        let pssh_kids = self.pssh().into_iter().flat_map(|pssh| pssh.key_ids.iter().copied());
+        let mut key_ids: Vec<KeyId> = Vec::new();
+        for key_id in default_kids.chain(pssh_kids) {
+            if !key_ids.contains(&key_id) {
+                key_ids.push(key_id);
+            }
+        }
+        key_ids
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.moov.tracks
+    }
+
+    pub fn track(&self, track_id: u32) -> Option<&Track> {
+        self.moov.tracks.iter().find(|track| track.track_id() == track_id)
+    }
+
+    // Tracks a track references with a type, such as the Timecode track of a video track.
+    // Ids without a matching track are left out.
+    pub fn referenced_tracks(&self, track_id: u32, kind: BoxType) -> Vec<&Track> {
+        let references = self.track(track_id).map_or(&[][..], |track| track.references(kind));
+        references.iter().filter_map(|&id| self.track(id)).collect()
+    }
+
+    // Tracks referencing a track, with each reference type they use
+    pub fn tracks_referencing(&self, track_id: u32) -> Vec<(&Track, BoxType)> {
+        let mut tracks = Vec::new();
+        for track in self.tracks() {
+            if let Some(tref) = track.tref.as_ref() {
+                tracks.extend(tref.kinds_of(track_id).map(|kind| (track, kind)));
+            }
+        }
+        tracks
+    }
+
+    // Samples of a track from its sample table followed by those of every fragment
+    pub fn sample_index(&self, track_id: u32) -> Result<SampleIndex, Error> {
+        let track = self
+            .track(track_id)
+            .ok_or(Error::BoxNotFound(format!("Mp4: no track with id {:?}", track_id)))?;
+        let mut index = track.sample_index()?;
+        for fragment in self.fragments.iter() {
+            if let Some(track) = fragment.track(track_id) {
+                index.samples.extend_from_slice(&track.samples);
+            }
+        }
+        Ok(index)
+    }
+
+    // Presentation timeline of a track, fragment samples included
+    pub fn presentation_timeline(&self, track_id: u32) -> Result<PresentationTimeline, Error> {
+        let track = self
+            .track(track_id)
+            .ok_or(Error::BoxNotFound(format!("Mp4: no track with id {:?}", track_id)))?;
+        Ok(track.timeline(self.moov.mvhd.timescale, &self.sample_index(track_id)?))
+    }
+
+    pub fn seeker(&self) -> Result<Seeker, Error> {
+        Seeker::new(self)
+    }
+
+    // Seek all tracks to a common point at or before time, see Seeker::seek. This builds
+    // the sample indexes, keep a Seeker around to seek repeatedly.
+    pub fn seek(&self, time: MediaTime) -> Result<Vec<(u32, SeekResult)>, Error> {
+        Ok(self.seeker()?.seek(time))
+    }
+
+    pub fn samples<'a, T: Read + Seek>(
+        &self,
+        track_id: u32,
+        src: &'a mut T,
+    ) -> Result<SampleReader<'a, T>, Error> {
+        Ok(SampleReader::new(src, self.sample_index(track_id)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // testdata/gen.py: avc1 + mp4a tracks, ftyp then mdat then moov
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    #[test]
+    fn parse_typed_tracks() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        assert!(!mp4.is_fragmented());
+        assert_eq!(mp4.duration(), MediaTime::new(200, 1000));
+        assert_eq!(mp4.tracks().len(), 2);
+
+        let video = mp4.track(1).unwrap();
+        assert_eq!(video.timescale(), 12800);
+        assert_eq!(video.duration(), MediaTime::new(3072, 12800));
+        assert_eq!(video.media.handler.as_ref().unwrap().handler, "vide");
+        assert_eq!(video.codec_string().unwrap(), "avc1.64001F");
+        let stbl = &video.media.info.sample_table;
+        assert_eq!(stbl.stsz.sample_count, 6);
+        assert_eq!(stbl.stss.as_ref().unwrap().samples, vec![1, 4]);
+        assert_eq!(stbl.ctts.as_ref().unwrap().table.len(), 6);
+        assert_eq!(stbl.stco.as_ref().unwrap().table.len(), 2);
+        assert!(video.edts.is_some());
+
+        let audio = mp4.track(2).unwrap();
+        assert_eq!(audio.timescale(), 44100);
+        assert_eq!(audio.media.handler.as_ref().unwrap().handler, "soun");
+        assert_eq!(audio.codec_string().unwrap(), "mp4a.40.2");
+        assert!(audio.media.info.sample_table.stss.is_none());
+        assert!(mp4.track(3).is_none());
+    }
+
+    #[test]
+    fn parse_requires_moov() {
+        let ftyp = &PROGRESSIVE[..32];
+        assert!(matches!(
+            Mp4::parse(&mut Cursor::new(ftyp)),
+            Err(Error::BoxNotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,723 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::{
+    chapter::{chapter_list, encode_text_sample, text_sample_entry},
+    dref::{Reference, UrlBox},
+    elst::EditEntry,
+    mdhd::language_code,
+    BoxType, BoxWriter, Chapter, ChunkOffset64Box, ChunkOffsetBox, CompositionOffsetBox,
+    DataInfoBox, DataReferenceBox, EditBox, EditListBox, Error, FtypBox, HandlerBox, Matrix,
+    MediaBox, MediaHeaderBox, MediaInfoBox, MediaTime, MoovBox, MvhdBox, NullMediaHeaderBox,
+    SampleDescriptionBox, SampleEntry, SampleSizeBox, SampleTableBox, SampleToChunkBox,
+    SoundInfoBox, SyncSampleBox, TimeToSampleBox, TrackBox, TrackHeaderBox, TrackReferenceBox,
+    UserDataBox, VideoInfoBox, Writer,
+};
+
+pub const MOVIE_TIMESCALE: u32 = 1000;
+
+// Maximum duration of a chunk in seconds before a new chunk is started
+const CHUNK_DURATION: u64 = 1;
+
+// Description of a track added to a muxer
+#[derive(Clone, Debug)]
+pub struct TrackConfig {
+    pub handler: String, // FourCC: "vide", "soun", ...
+    pub timescale: u32,
+    pub language: String, // ISO 639-2/T code, "und" if unknown
+    pub width: u32,       // Display size in pixels, 0 for audio
+    pub height: u32,
+    pub codec: SampleEntry, // Sample entry written in stsd
+}
+
+impl TrackConfig {
+    pub fn is_video(&self) -> bool {
+        self.handler == "vide"
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.handler == "soun"
+    }
+
+    pub(crate) fn track_header(&self, track_id: u32, duration: u64) -> TrackHeaderBox {
+        TrackHeaderBox {
+            version: if duration > u32::MAX as u64 { 1 } else { 0 },
+            flags: 0x3, // Enabled | In movie
+            creation_time: 0,
+            modification_time: 0,
+            track_id,
+            duration,
+            layer: 0,
+            alternate_group: 0,
+            volume: if self.is_audio() { 0x0100 } else { 0 },
+            matrix: Matrix::default(),
+            width: self.width << 16,
+            height: self.height << 16,
+        }
+    }
+
+    pub(crate) fn media(&self, duration: u64, sample_table: SampleTableBox) -> MediaBox {
+        let name = match self.handler.as_str() {
+            "vide" => "VideoHandler",
+            "soun" => "SoundHandler",
+            "text" => "TextHandler",
+            _ => "DataHandler",
+        };
+        let language_code = language_code(&self.language);
+        MediaBox {
+            media_header: MediaHeaderBox {
+                version: if duration > u32::MAX as u64 { 1 } else { 0 },
+                flags: 0,
+                creation_time: 0,
+                modification_time: 0,
+                timescale: self.timescale,
+                duration,
+                language_code,
+                language: self.language.clone(),
+                quality: 0,
+            },
+            handler: Some(HandlerBox {
+                version: 0,
+                flags: 0,
+                component_type: String::new(),
+                handler: self.handler.clone(),
+                name: name.to_owned(),
+                null_terminated: true,
+            }),
+            info: MediaInfoBox {
+                vmhd: match self.is_video() {
+                    true => Some(VideoInfoBox {
+                        version: 0,
+                        flags: 1,
+                        graphics_mode: 0,
+                        op_color: (0, 0, 0),
+                    }),
+                    false => None,
+                },
+                smhd: match self.is_audio() {
+                    true => Some(SoundInfoBox {
+                        version: 0,
+                        flags: 0,
+                        balance: 0,
+                    }),
+                    false => None,
+                },
+                nmhd: match self.is_video() || self.is_audio() {
+                    true => None,
+                    false => Some(NullMediaHeaderBox {
+                        version: 0,
+                        flags: 0,
+                    }),
+                },
+                dinf: Some(DataInfoBox {
+                    data_reference: DataReferenceBox {
+                        version: 0,
+                        flags: 0,
+                        references: vec![Reference::Url(UrlBox {
+                            version: 0,
+                            flags: 1, // Media data is in the same file
+                            location: String::new(),
+                        })],
+                    },
+                }),
+                sample_table,
+                order: Vec::new(),
+                extensions: Vec::new(),
+            },
+            order: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn sample_description(&self) -> SampleDescriptionBox {
+        SampleDescriptionBox {
+            version: 0,
+            flags: 0,
+            entries: vec![self.codec.clone()],
+        }
+    }
+}
+
+pub(crate) fn movie_header(next_track_id: u32, duration: u64) -> MvhdBox {
+    MvhdBox {
+        version: if duration > u32::MAX as u64 { 1 } else { 0 },
+        flags: 0,
+        creation_time: 0,
+        modification_time: 0,
+        timescale: MOVIE_TIMESCALE,
+        duration,
+        rate: 0x00010000,
+        volume: 0x0100,
+        matrix: Matrix::default(),
+        preview_time: 0,
+        preview_duration: 0,
+        poster_time: 0,
+        selection_time: 0,
+        selection_duration: 0,
+        current_time: 0,
+        next_track_id,
+    }
+}
+
+pub(crate) fn default_ftyp() -> FtypBox {
+    FtypBox {
+        major_brand: "isom".to_owned(),
+        minor_brand: 512,
+        compatible_brands: vec![
+            "isom".to_owned(),
+            "iso2".to_owned(),
+            "avc1".to_owned(),
+            "mp41".to_owned(),
+        ],
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MuxerTrack {
+    config: TrackConfig,
+    sizes: Vec<u32>,
+    decode_times: Vec<u64>,
+    composition_times: Vec<i64>,
+    sync_samples: Vec<u32>, // 1 based
+    chunk_offsets: Vec<u64>,
+    chunk_samples: Vec<u32>,
+    chunk_start: u64, // Decode time of the first sample of the current chunk
+    end: Option<u64>, // Decode time the last sample ends at
+}
+
+impl MuxerTrack {
+    fn new(config: TrackConfig) -> Self {
+        Self {
+            config,
+            sizes: Vec::new(),
+            decode_times: Vec::new(),
+            composition_times: Vec::new(),
+            sync_samples: Vec::new(),
+            chunk_offsets: Vec::new(),
+            chunk_samples: Vec::new(),
+            chunk_start: 0,
+            end: None,
+        }
+    }
+
+    fn duration(&self, index: usize) -> Result<u32, Error> {
+        let duration = match (self.decode_times.get(index), self.decode_times.get(index + 1)) {
+            (Some(dts), Some(next)) => next - dts,
+            (Some(dts), None) if self.end.is_some() => self.end.map_or(0, |end| end.saturating_sub(*dts)),
+            // The last sample lasts as long as the previous one
+            (Some(_), None) if index > 0 => return self.duration(index - 1),
+            _ => 0,
+        };
+        u32::try_from(duration)
+            .map_err(|_| Error::InvalidData(format!("Muxer: sample duration {:?} out of range", duration)))
+    }
+
+    fn media_duration(&self) -> Result<u64, Error> {
+        (0..self.decode_times.len()).map(|i| self.duration(i).map(u64::from)).sum()
+    }
+
+    fn sample_table(&self) -> Result<SampleTableBox, Error> {
+        let mut stts: Vec<(u32, u32)> = Vec::new();
+        for i in 0..self.decode_times.len() {
+            let duration = self.duration(i)?;
+            match stts.last_mut() {
+                Some((count, last)) if *last == duration => *count += 1,
+                _ => stts.push((1, duration)),
+            }
+        }
+
+        let offsets: Vec<i64> = self
+            .decode_times
+            .iter()
+            .zip(self.composition_times.iter())
+            .map(|(&dts, &pts)| pts.saturating_sub(dts as i64))
+            .collect();
+        let ctts = match offsets.iter().any(|&offset| offset != 0) {
+            true => {
+                let mut table: Vec<(u32, i32)> = Vec::new();
+                for &offset in offsets.iter() {
+                    let offset = i32::try_from(offset).map_err(|_| {
+                        Error::InvalidData(format!("Muxer: composition offset {:?} out of range", offset))
+                    })?;
+                    match table.last_mut() {
+                        Some((count, last)) if *last == offset => *count += 1,
+                        _ => table.push((1, offset)),
+                    }
+                }
+                Some(CompositionOffsetBox {
+                    // Negative offsets need the signed version
+                    version: if offsets.iter().any(|&offset| offset < 0) { 1 } else { 0 },
+                    flags: 0,
+                    table,
+                })
+            }
+            false => None,
+        };
+
+        let mut stsc: Vec<(u32, u32, u32)> = Vec::new();
+        for (i, &count) in self.chunk_samples.iter().enumerate() {
+            match stsc.last() {
+                Some(&(_, last, _)) if last == count => (),
+                _ => stsc.push((i as u32 + 1, count, 1)),
+            }
+        }
+
+        // Fall back to co64 as soon as one offset does not fit in stco
+        let offsets: Result<Vec<u32>, _> = self.chunk_offsets.iter().map(|&offset| u32::try_from(offset)).collect();
+        let (stco, co64) = match offsets {
+            Ok(table) => (
+                Some(ChunkOffsetBox {
+                    version: 0,
+                    flags: 0,
+                    table,
+                }),
+                None,
+            ),
+            Err(_) => (
+                None,
+                Some(ChunkOffset64Box {
+                    version: 0,
+                    flags: 0,
+                    table: self.chunk_offsets.clone(),
+                }),
+            ),
+        };
+
+        // Without stss every sample is a sync sample
+        let stss = match self.sync_samples.len() == self.sizes.len() {
+            true => None,
+            false => Some(SyncSampleBox {
+                version: 0,
+                flags: 0,
+                samples: self.sync_samples.clone(),
+            }),
+        };
+
+        let (sample_size, sample_sizes) = match self.sizes.first() {
+            Some(&first) if self.sizes.iter().all(|&size| size == first) => (first, Vec::new()),
+            _ => (0, self.sizes.clone()),
+        };
+
+        Ok(SampleTableBox {
+            stsd: self.config.sample_description(),
+            stts: TimeToSampleBox {
+                version: 0,
+                flags: 0,
+                table: stts,
+            },
+            stsc: SampleToChunkBox {
+                version: 0,
+                flags: 0,
+                table: stsc,
+            },
+            stsz: SampleSizeBox {
+                version: 0,
+                flags: 0,
+                sample_size,
+                sample_count: self.sizes.len() as u32,
+                sample_sizes,
+            },
+            stco,
+            co64,
+            ctts,
+            stss,
+            order: Vec::new(),
+            extensions: Vec::new(),
+        })
+    }
+
+    fn track(&self, track_id: u32) -> Result<TrackBox, Error> {
+        let media_duration = self.media_duration()?;
+        // Shift the presentation so that the first composed sample starts at 0. An edit
+        // cannot start before media time 0, so samples composed before it are cut.
+        let first_pts = self.composition_times.iter().copied().min().unwrap_or(0);
+        let media_time = first_pts.max(0);
+        let presentation_duration = (media_duration as i64 + first_pts - media_time).max(0);
+        let duration = MediaTime::new(presentation_duration, self.config.timescale)
+            .rescale_floor(MOVIE_TIMESCALE)
+            .value as u64;
+        let edts = match first_pts {
+            0 => None,
+            _ => Some(EditBox {
+                list: Some(EditListBox {
+                    version: match duration > u32::MAX as u64 || media_time > i32::MAX as i64 {
+                        true => 1,
+                        false => 0,
+                    },
+                    flags: 0,
+                    entries: vec![EditEntry {
+                        segment_duration: duration,
+                        media_time,
+                        media_rate_integer: 1,
+                        media_rate_fraction: 0,
+                    }],
+                }),
+            }),
+        };
+        Ok(TrackBox {
+            tkhd: self.config.track_header(track_id, duration),
+            tref: None,
+            edts,
+            media: self.config.media(media_duration, self.sample_table()?),
+            order: Vec::new(),
+            extensions: Vec::new(),
+        })
+    }
+}
+
+// Progressive MP4 muxer writing ftyp, mdat then moov
+#[derive(Debug)]
+pub struct Mp4Muxer<'a, W: 'a> {
+    dst: &'a mut W,
+    pub ftyp: FtypBox,
+    pub chapters: Vec<Chapter>, // Written by finish as a chapter track and a Nero chpl
+    tracks: Vec<MuxerTrack>,
+    chapter_track: Option<u32>,
+    mdat_start: Option<u64>,
+    base: u64,     // Position of dst at the first write, the MP4 may follow other data
+    position: u64, // Position of dst, chunk offsets are positions in dst
+    last_track: Option<usize>,
+}
+
+impl<'a, W: Write + Seek> Mp4Muxer<'a, W> {
+    pub fn new(dst: &'a mut W) -> Self {
+        Self {
+            dst,
+            ftyp: default_ftyp(),
+            chapters: Vec::new(),
+            tracks: Vec::new(),
+            chapter_track: None,
+            mdat_start: None,
+            base: 0,
+            position: 0,
+            last_track: None,
+        }
+    }
+
+    // Returns the track id
+    pub fn add_track(&mut self, config: TrackConfig) -> Result<u32, Error> {
+        if self.mdat_start.is_some() {
+            return Err(Error::InvalidData("Muxer: tracks must be added before samples".to_owned()));
+        }
+        if config.timescale == 0 {
+            return Err(Error::InvalidData("Muxer: timescale must not be 0".to_owned()));
+        }
+        self.tracks.push(MuxerTrack::new(config));
+        Ok(self.tracks.len() as u32)
+    }
+
+    fn start(&mut self) -> Result<u64, Error> {
+        if let Some(mdat_start) = self.mdat_start {
+            return Ok(mdat_start);
+        }
+        self.base = self
+            .dst
+            .stream_position()
+            .map_err(|error| Error::InvalidData(error.to_string()))?;
+        let mut writer = BoxWriter::new(&mut *self.dst);
+        let ftyp_size = self.ftyp.write(&mut writer)?;
+        // Always use a largesize header, patched by finish
+        writer.write_u32(1)?;
+        writer.write_bytes(b"mdat")?;
+        writer.write_u64(0)?;
+        let mdat_start = self.base + ftyp_size;
+        self.mdat_start = Some(mdat_start);
+        self.position = mdat_start + 16;
+        Ok(mdat_start)
+    }
+
+    pub fn push(
+        &mut self,
+        track_id: u32,
+        data: &[u8],
+        decode_time: u64,
+        composition_time: i64,
+        is_sync: bool,
+    ) -> Result<(), Error> {
+        let index = (track_id as usize).wrapping_sub(1);
+        if index >= self.tracks.len() {
+            return Err(Error::InvalidData(format!("Muxer: unknown track {:?}", track_id)));
+        }
+        self.start()?;
+        let position = self.position;
+        let new_chunk = self.last_track != Some(index);
+        let track = &mut self.tracks[index];
+        let size = u32::try_from(data.len())
+            .map_err(|_| Error::InvalidData(format!("Muxer: sample of {:?} bytes too large", data.len())))?;
+        if let Some(&last) = track.decode_times.last() {
+            if decode_time < last {
+                return Err(Error::InvalidData(format!(
+                    "Muxer: decode time {:?} before {:?} on track {:?}",
+                    decode_time, last, track_id
+                )));
+            }
+            // The stts delta of the previous sample
+            if u32::try_from(decode_time - last).is_err() {
+                return Err(Error::InvalidData(format!(
+                    "Muxer: decode time {:?} too far after {:?} on track {:?}",
+                    decode_time, last, track_id
+                )));
+            }
+        }
+        // The ctts offset of this sample
+        if i32::try_from(composition_time.saturating_sub(decode_time as i64)).is_err() {
+            return Err(Error::InvalidData(format!(
+                "Muxer: composition time {:?} too far from decode time {:?} on track {:?}",
+                composition_time, decode_time, track_id
+            )));
+        }
+        let chunk_limit = CHUNK_DURATION * track.config.timescale as u64;
+        if new_chunk || decode_time - track.chunk_start >= chunk_limit {
+            track.chunk_offsets.push(position);
+            track.chunk_samples.push(0);
+            track.chunk_start = decode_time;
+        }
+        if let Some(count) = track.chunk_samples.last_mut() {
+            *count += 1;
+        }
+        track.sizes.push(size);
+        track.decode_times.push(decode_time);
+        track.composition_times.push(composition_time);
+        if is_sync {
+            track.sync_samples.push(track.sizes.len() as u32);
+        }
+
+        BoxWriter::new(&mut *self.dst).write_bytes(data)?;
+        self.position += data.len() as u64;
+        self.last_track = Some(index);
+        Ok(())
+    }
+
+    // Chapter titles become the samples of a disabled text track, the first chapter is
+    // stretched back to the start and the last one runs to the end of the movie
+    fn write_chapters(&mut self) -> Result<(), Error> {
+        if self.chapters.is_empty() {
+            return Ok(());
+        }
+        if self.chapters.len() > u8::MAX as usize {
+            return Err(Error::InvalidData("Muxer: at most 255 chapters fit in chpl".to_owned()));
+        }
+        let mut durations = Vec::new();
+        for track in self.tracks.iter() {
+            durations.push(MediaTime::new(track.media_duration()? as i64, track.config.timescale));
+        }
+        let end = durations
+            .into_iter()
+            .max()
+            .unwrap_or_default()
+            .rescale(MOVIE_TIMESCALE)
+            .value as u64;
+        self.tracks.push(MuxerTrack::new(TrackConfig {
+            handler: "text".to_owned(),
+            timescale: MOVIE_TIMESCALE,
+            language: "und".to_owned(),
+            width: 0,
+            height: 0,
+            codec: text_sample_entry()?,
+        }));
+        let track_id = self.tracks.len() as u32;
+
+        let mut chapters = self.chapters.clone();
+        chapters.sort_by_key(|chapter| chapter.start);
+        for (i, chapter) in chapters.iter().enumerate() {
+            let start = match i {
+                0 => 0,
+                _ => chapter.start.rescale(MOVIE_TIMESCALE).value.max(0) as u64,
+            };
+            self.push(track_id, &encode_text_sample(&chapter.title), start, start as i64, true)?;
+        }
+        let track = &mut self.tracks[track_id as usize - 1];
+        let last = track.decode_times.last().copied().unwrap_or(0);
+        track.end = Some(end.max(last + 1));
+        self.chapter_track = Some(track_id);
+        Ok(())
+    }
+
+    pub fn moov(&self) -> Result<MoovBox, Error> {
+        let mut tracks: Vec<TrackBox> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| track.track(i as u32 + 1))
+            .collect::<Result<_, _>>()?;
+        if let Some(chapter_track) = self.chapter_track {
+            for track in tracks.iter_mut() {
+                if track.track_id() == chapter_track {
+                    track.tkhd.flags &= !0x1; // Not enabled, players show it as chapters
+                } else {
+                    track.tref =
+                        Some(TrackReferenceBox::new(BoxType::Chapter, vec![chapter_track]));
+                }
+            }
+        }
+        let duration = tracks.iter().map(|track| track.tkhd.duration).max().unwrap_or(0);
+        let udta = match self.chapters.is_empty() {
+            true => None,
+            false => {
+                let mut chapters = self.chapters.clone();
+                chapters.sort_by_key(|chapter| chapter.start);
+                Some(UserDataBox {
+                    chpl: Some(chapter_list(&chapters)),
+                    ..Default::default()
+                })
+            }
+        };
+        Ok(MoovBox {
+            mvhd: movie_header(tracks.len() as u32 + 1, duration),
+            pssh: Vec::new(),
+            tracks,
+            mvex: None,
+            udta,
+            order: Vec::new(),
+            extensions: Vec::new(),
+        })
+    }
+
+    // Patch the mdat size and write the moov box, returns the size of the MP4
+    pub fn finish(mut self) -> Result<u64, Error> {
+        if self.tracks.is_empty() {
+            return Err(Error::InvalidData("Muxer: no track".to_owned()));
+        }
+        self.write_chapters()?;
+        let mdat_start = self.start()?;
+        let mdat_size = self.position - mdat_start;
+        self.dst
+            .seek(SeekFrom::Start(mdat_start + 8))
+            .map_err(|error| Error::InvalidData(error.to_string()))?;
+        BoxWriter::new(&mut *self.dst).write_u64(mdat_size)?;
+        self.dst
+            .seek(SeekFrom::Start(self.position))
+            .map_err(|error| Error::InvalidData(error.to_string()))?;
+
+        let moov = self.moov()?;
+        let moov_size = moov.write(&mut BoxWriter::new(&mut *self.dst))?;
+        Ok(self.position + moov_size - self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/gen.py, its avc1 sample entry is reused by the muxed tracks
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    fn video_config() -> TrackConfig {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        TrackConfig {
+            handler: "vide".to_owned(),
+            timescale: 12800,
+            language: "und".to_owned(),
+            width: 640,
+            height: 360,
+            codec: mp4.track(1).unwrap().sample_entries()[0].clone(),
+        }
+    }
+
+    // Four frames of 512 ticks shifted by offset in composition order I P B B
+    fn mux(dst: &mut Cursor<Vec<u8>>, offset: i64) -> u64 {
+        let mut muxer = Mp4Muxer::new(dst);
+        let track_id = muxer.add_track(video_config()).unwrap();
+        for (i, pts) in [0i64, 1536, 512, 1024].iter().enumerate() {
+            let data = vec![i as u8; 10 + i];
+            muxer.push(track_id, &data, i as u64 * 512, pts + offset, i == 0).unwrap();
+        }
+        muxer.finish().unwrap()
+    }
+
+    fn edit(mp4: &Mp4) -> Option<EditEntry> {
+        let edts = mp4.track(1).unwrap().edts.as_ref()?;
+        Some(edts.list.as_ref().unwrap().entries[0].clone())
+    }
+
+    #[test]
+    fn positive_first_pts_shifted_by_an_edit() {
+        let mut dst = Cursor::new(Vec::new());
+        let size = mux(&mut dst, 512);
+        assert_eq!(size, dst.get_ref().len() as u64);
+        let mp4 = Mp4::parse(&mut Cursor::new(dst.get_ref())).unwrap();
+        let edit = edit(&mp4).unwrap();
+        assert_eq!(edit.media_time, 512);
+        assert_eq!(edit.segment_duration, 160);
+
+        let index = mp4.sample_index(1).unwrap();
+        let pts: Vec<i64> = index.iter().map(|sample| sample.composition_time).collect();
+        assert_eq!(pts, vec![512, 2048, 1024, 1536]);
+    }
+
+    #[test]
+    fn negative_first_pts_clamped() {
+        let mut dst = Cursor::new(Vec::new());
+        mux(&mut dst, -512);
+        let mp4 = Mp4::parse(&mut Cursor::new(dst.get_ref())).unwrap();
+        let edit = edit(&mp4).unwrap();
+        assert_eq!(edit.media_time, 0);
+        // The first 512 ticks are composed before media time 0 and cut
+        assert_eq!(edit.segment_duration, 120);
+        assert_eq!(mp4.track(1).unwrap().tkhd.duration, 120);
+        let ctts = mp4.track(1).unwrap().media.info.sample_table.ctts.as_ref().unwrap();
+        assert_eq!(ctts.version, 1);
+    }
+
+    #[test]
+    fn offsets_past_4gb_use_co64() {
+        let mut track = MuxerTrack::new(video_config());
+        track.sizes = vec![10, 11];
+        track.decode_times = vec![0, 512];
+        track.composition_times = vec![0, 512];
+        track.sync_samples = vec![1, 2];
+        track.chunk_samples = vec![1, 1];
+        track.chunk_offsets = vec![48, u32::MAX as u64 + 1];
+        let stbl = track.sample_table().unwrap();
+        assert!(stbl.stco.is_none());
+        assert_eq!(stbl.co64.unwrap().table, vec![48, u32::MAX as u64 + 1]);
+
+        track.chunk_offsets = vec![48, u32::MAX as u64];
+        let stbl = track.sample_table().unwrap();
+        assert!(stbl.co64.is_none());
+        assert_eq!(stbl.stco.unwrap().table, vec![48, u32::MAX]);
+    }
+
+    #[test]
+    fn out_of_range_samples_rejected() {
+        let mut dst = Cursor::new(Vec::new());
+        let mut muxer = Mp4Muxer::new(&mut dst);
+        let track_id = muxer.add_track(video_config()).unwrap();
+        muxer.push(track_id, &[0; 10], 0, 0, true).unwrap();
+        // The stts delta and the ctts offset do not fit
+        let far = u32::MAX as u64 + 1;
+        let result = muxer.push(track_id, &[0; 10], far, far as i64, false);
+        assert!(matches!(result, Err(Error::InvalidData(_))));
+        let result = muxer.push(track_id, &[0; 10], 512, i32::MAX as i64 + 513, false);
+        assert!(matches!(result, Err(Error::InvalidData(_))));
+        // Rejected samples are not muxed
+        muxer.push(track_id, &[0; 10], 512, 512, false).unwrap();
+        assert_eq!(muxer.moov().unwrap().tracks[0].media.info.sample_table.stsz.sample_count, 2);
+    }
+
+    #[test]
+    fn chunk_offsets_from_the_start_position() {
+        // A 100 bytes free box before the MP4
+        let mut prefix = vec![0, 0, 0, 100];
+        prefix.extend_from_slice(b"free");
+        prefix.resize(100, 0);
+        let mut dst = Cursor::new(prefix);
+        dst.set_position(100);
+        let size = mux(&mut dst, 0);
+        assert_eq!(size + 100, dst.get_ref().len() as u64);
+
+        let mut src = Cursor::new(dst.into_inner());
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        assert!(edit(&mp4).is_none());
+        let mut samples = mp4.samples(1, &mut src).unwrap();
+        let mut data = Vec::new();
+        let mut count = 0;
+        while let Some(sample) = samples.next_into(&mut data).unwrap() {
+            assert_eq!(data, vec![sample.index as u8; 10 + sample.index as usize]);
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+}
@@ -2,10 +2,9 @@ use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
 
 use crate::{
     boxes::{BoxHeader, BoxType},
-    error, fourcc,
+    error,
 };
 pub use error::Error;
-pub use fourcc::FourCC;
 
 #[derive(Debug)]
 pub struct BoxReader<'a, T: 'a> {
@@ -14,7 +13,7 @@ pub struct BoxReader<'a, T: 'a> {
 }
 
 impl<'a, T: Read + Seek> BoxReader<'a, T> {
-    pub fn new(src: &'a mut T) -> BoxReader<T> {
+    pub fn new(src: &'a mut T) -> BoxReader<'a, T> {
         Self {
             src,
             error: None,
@@ -27,7 +26,7 @@ impl<'a, T: Read + Seek> BoxReader<'a, T> {
         }
         let error = Error::InvalidData(error.to_string());
         self.error = Some(error.clone());
-        return error;
+        error
     }
 
     pub fn stream_position(&mut self) -> Result<u64, Error> {
@@ -41,6 +40,12 @@ impl<'a, T: Read + Seek> BoxReader<'a, T> {
         Ok(())
     }
 
+    pub fn skip_to_end(&mut self) -> Result<u64, Error> {
+        self.src
+            .seek(SeekFrom::End(0))
+            .map_err(|error| self.set_error(error))
+    }
+
     pub fn read_u8(&mut self) -> Result<u8, Error> {
         let mut buf: [u8; 1] = [0; 1];
         if let Err(error) = self.src.read_exact(&mut buf) {
@@ -113,6 +118,23 @@ impl<'a, T: Read + Seek> BoxReader<'a, T> {
         Ok(value)
     }
 
+    pub fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; len];
+        if let Err(error) = self.src.read_exact(&mut buf) {
+            return Err(self.set_error(error));
+        }
+        Ok(buf)
+    }
+
+    // Read a u32 and seek back before it
+    pub fn peek_u32(&mut self) -> Result<u32, Error> {
+        let value = self.read_u32()?;
+        self.src
+            .seek(SeekFrom::Current(-4))
+            .map_err(|error| self.set_error(error))?;
+        Ok(value)
+    }
+
     pub fn read_header_extra(&mut self) -> Result<(u8, u32), Error> {
         let version = self.read_u8()?;
         let mut buf: [u8; 3] = [0; 3];
@@ -127,14 +149,15 @@ impl<'a, T: Read + Seek> BoxReader<'a, T> {
 
     pub fn show_error(&self) -> String {
         match &self.error {
-            Some(error) => return error.to_string(),
-            None => return "Ok".to_string(),
+            Some(error) => error.to_string(),
+            None => "Ok".to_string(),
         }
     }
 }
 
 
 pub trait Reader {
+    #[allow(clippy::extra_unused_lifetimes)]
     fn read<'a, T: Read + Seek>(reader: &mut BoxReader<T>, header: BoxHeader) -> Result<Self, Error>
     where
         Self: Sized;
@@ -153,7 +176,7 @@ impl<'a, T: Read + Seek> BoxParser<'a, T> {
     }
 
     pub fn get_reader(&mut self) -> &mut BoxReader<'a, T> {
-        return &mut self.reader;
+        &mut self.reader
     }
 
     pub fn next_header(&mut self) -> Result<BoxHeader, Error> {
@@ -171,12 +194,13 @@ impl<'a, T: Read + Seek> BoxParser<'a, T> {
     }
 }
 
-pub fn parse<'a, T: Read + Seek>(src: &mut T) -> BoxParser<T> {
+pub fn parse<T: Read + Seek>(src: &mut T) -> BoxParser<'_, T> {
     let parser: BoxParser<T> = BoxParser::new(src);
     parser
 }
 
 pub trait Parser {
+    #[allow(clippy::extra_unused_lifetimes)]
     fn parse<'a, T: Read + Seek>(parser: &mut BoxParser<T>) -> Result<Self, Error>
     where
         Self: Sized;
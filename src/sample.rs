@@ -0,0 +1,405 @@
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+use crate::{Error, MediaTime, SampleTableBox};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub index: u32, // 0 based
+    pub file_offset: u64,
+    pub size: u32,
+    pub decode_time: u64,      // DTS in media timescale
+    pub composition_time: i64, // PTS in media timescale
+    pub duration: u32,
+    pub is_sync: bool,
+    pub description_index: u32, // 1 based index into stsd
+}
+
+impl Sample {
+    pub fn dts(&self, timescale: u32) -> MediaTime {
+        MediaTime::new(self.decode_time as i64, timescale)
+    }
+
+    pub fn pts(&self, timescale: u32) -> MediaTime {
+        MediaTime::new(self.composition_time, timescale)
+    }
+}
+
+// Where to start decoding to present a given time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeekResult {
+    pub sample: u32,      // sample presented at the requested time
+    pub sync_sample: u32, // sync sample at or before it, decoding starts here
+    pub file_offset: u64, // of the sync sample
+    pub time: MediaTime,  // composition time of the sync sample
+}
+
+// Resolves stts, stsc, stsz, stco/co64, ctts and stss into a flat list of samples
+#[derive(Clone, Debug, Default)]
+pub struct SampleIndex {
+    pub timescale: u32, // media timescale of the sample times
+    pub samples: Vec<Sample>,
+}
+
+impl SampleIndex {
+    pub fn new(stbl: &SampleTableBox, timescale: u32) -> Result<Self, Error> {
+        let sample_count = stbl.stsz.sample_count;
+        let chunk_offsets: Vec<u64> = match (&stbl.stco, &stbl.co64) {
+            (Some(stco), _) => stco.table.iter().map(|offset| *offset as u64).collect(),
+            (None, Some(co64)) => co64.table.clone(),
+            (None, None) => Vec::new(),
+        };
+        if stbl.stsz.sample_size == 0 && stbl.stsz.sample_sizes.len() != sample_count as usize {
+            return Err(Error::InvalidData("SampleIndex: stsz table is truncated".to_owned()));
+        }
+
+        let timed_count = stbl.stts.table.iter().fold(0u64, |count, &(run, _)| count + run as u64);
+        if timed_count < sample_count as u64 {
+            return Err(Error::InvalidData(format!(
+                "SampleIndex: stts times {:?} samples, stsz {:?}",
+                timed_count, sample_count
+            )));
+        }
+
+        // The counts come from the file, only reserve what the chunks can hold
+        let stsc = &stbl.stsc.table;
+        let mut described: u64 = 0;
+        for (i, &(first_chunk, samples_per_chunk, _)) in stsc.iter().enumerate() {
+            let end = stsc.get(i + 1).map_or(u64::MAX, |next| next.0 as u64);
+            let chunks = end.min(chunk_offsets.len() as u64 + 1).saturating_sub(first_chunk as u64);
+            described = described.saturating_add(chunks.saturating_mul(samples_per_chunk as u64));
+        }
+        let mut samples: Vec<Sample> = Vec::new();
+        samples.try_reserve_exact(described.min(sample_count as u64) as usize).map_err(|_| {
+            Error::InvalidData(format!("SampleIndex: no memory for {:?} samples", sample_count))
+        })?;
+
+        // Sample to chunk: walk every chunk and assign offsets and description index
+        let mut index: u32 = 0;
+        for (i, &(first_chunk, samples_per_chunk, description_index)) in stsc.iter().enumerate() {
+            if first_chunk == 0 {
+                return Err(Error::InvalidData("SampleIndex: stsc first_chunk is 0".to_owned()));
+            }
+            if stbl.stsd.entry(description_index).is_none() {
+                return Err(Error::InvalidData(format!(
+                    "SampleIndex: stsc references missing sample description {:?}",
+                    description_index
+                )));
+            }
+            let last_chunk = match stsc.get(i + 1) {
+                Some(next) => next.0.saturating_sub(1),
+                None => chunk_offsets.len() as u32,
+            };
+            for chunk in first_chunk..=last_chunk {
+                let Some(&chunk_offset) = chunk_offsets.get(chunk as usize - 1) else {
+                    return Err(Error::InvalidData(format!(
+                        "SampleIndex: chunk {:?} has no offset",
+                        chunk
+                    )));
+                };
+                let mut file_offset = chunk_offset;
+                for _ in 0..samples_per_chunk {
+                    if index >= sample_count {
+                        break;
+                    }
+                    let size = match stbl.stsz.sample_size {
+                        0 => stbl.stsz.sample_sizes[index as usize],
+                        size => size,
+                    };
+                    samples.push(Sample {
+                        index,
+                        file_offset,
+                        size,
+                        decode_time: 0,
+                        composition_time: 0,
+                        duration: 0,
+                        is_sync: stbl.stss.is_none(),
+                        description_index,
+                    });
+                    file_offset = file_offset.checked_add(size as u64).ok_or(Error::InvalidData(
+                        "SampleIndex: sample offset overflows".to_owned(),
+                    ))?;
+                    index += 1;
+                }
+            }
+        }
+        if index != sample_count {
+            return Err(Error::InvalidData(format!(
+                "SampleIndex: stsc describes {:?} samples, stsz {:?}",
+                index, sample_count
+            )));
+        }
+
+        // Time to sample: decode times and durations
+        let mut decode_time: u64 = 0;
+        let mut timed = samples.iter_mut();
+        for &(count, duration) in stbl.stts.table.iter() {
+            for _ in 0..count {
+                let Some(sample) = timed.next() else {
+                    break;
+                };
+                sample.decode_time = decode_time;
+                sample.composition_time = decode_time as i64;
+                sample.duration = duration;
+                decode_time += duration as u64;
+            }
+        }
+
+        // Composition offsets: PTS = DTS + offset
+        if let Some(ctts) = &stbl.ctts {
+            let mut timed = samples.iter_mut();
+            for &(count, offset) in ctts.table.iter() {
+                for _ in 0..count {
+                    let Some(sample) = timed.next() else {
+                        break;
+                    };
+                    sample.composition_time = sample.decode_time as i64 + offset as i64;
+                }
+            }
+        }
+
+        // Sync samples: stss numbers are 1 based
+        if let Some(stss) = &stbl.stss {
+            for &number in stss.samples.iter() {
+                if let Some(sample) = samples.get_mut((number as usize).wrapping_sub(1)) {
+                    sample.is_sync = true;
+                }
+            }
+        }
+
+        Ok(Self { timescale, samples })
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn get(&self, index: u32) -> Option<&Sample> {
+        self.samples.get(index as usize)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Sample> {
+        self.samples.iter()
+    }
+
+    // Decode end of the last sample
+    pub fn duration(&self) -> MediaTime {
+        let end = self
+            .samples
+            .last()
+            .map_or(0, |sample| sample.decode_time + sample.duration as u64);
+        MediaTime::new(end as i64, self.timescale)
+    }
+
+    // Find the sync sample at or before a composition time. Samples are in decode order so
+    // the decode times are binary searched, then the walk back to the sync sample only
+    // covers the current group of pictures.
+    pub fn seek(&self, time: MediaTime) -> Option<SeekResult> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let time = time.rescale_floor(self.timescale).value;
+        let end = self
+            .samples
+            .partition_point(|sample| sample.decode_time as i64 <= time)
+            .max(1);
+        // Before the first sync sample seeking clamps to the start
+        let sync = self.samples[..end]
+            .iter()
+            .rposition(|sample| sample.is_sync && sample.composition_time <= time)
+            .or_else(|| self.samples.iter().position(|sample| sample.is_sync))
+            .unwrap_or(0);
+        // Latest sample presented at or before time, decoded between the sync sample and time
+        let sample = self.samples[sync..end.max(sync + 1)]
+            .iter()
+            .filter(|sample| sample.composition_time <= time)
+            .max_by_key(|sample| sample.composition_time)
+            .unwrap_or(&self.samples[sync]);
+        let sync = &self.samples[sync];
+        Some(SeekResult {
+            sample: sample.index,
+            sync_sample: sync.index,
+            file_offset: sync.file_offset,
+            time: sync.pts(self.timescale),
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a SampleIndex {
+    type Item = &'a Sample;
+    type IntoIter = std::slice::Iter<'a, Sample>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.samples.iter()
+    }
+}
+
+// Reads sample payloads from the media data of a Read + Seek source
+#[derive(Debug)]
+pub struct SampleReader<'a, T: 'a> {
+    src: &'a mut T,
+    samples: Vec<Sample>,
+    next: usize,
+    position: Option<u64>,
+}
+
+impl<'a, T: Read + Seek> SampleReader<'a, T> {
+    pub fn new(src: &'a mut T, index: SampleIndex) -> Self {
+        Self {
+            src,
+            samples: index.samples,
+            next: 0,
+            position: None,
+        }
+    }
+
+    // Reorder the remaining samples by file offset so reads only move forward
+    pub fn in_file_order(mut self) -> Self {
+        self.samples[self.next..].sort_by_key(|sample| sample.file_offset);
+        self
+    }
+
+    pub fn read_sample(&mut self, sample: &Sample, buf: &mut Vec<u8>) -> Result<(), Error> {
+        if self.position != Some(sample.file_offset) {
+            self.src
+                .seek(SeekFrom::Start(sample.file_offset))
+                .map_err(|error| Error::InvalidData(error.to_string()))?;
+        }
+        buf.clear();
+        buf.resize(sample.size as usize, 0);
+        if let Err(error) = self.src.read_exact(buf) {
+            self.position = None;
+            if error.kind() == ErrorKind::UnexpectedEof {
+                return Err(Error::EOF());
+            }
+            return Err(Error::InvalidData(error.to_string()));
+        }
+        self.position = Some(sample.file_offset + sample.size as u64);
+        Ok(())
+    }
+
+    // Read the next sample into a caller provided buffer
+    pub fn next_into(&mut self, buf: &mut Vec<u8>) -> Result<Option<Sample>, Error> {
+        let Some(&sample) = self.samples.get(self.next) else {
+            return Ok(None);
+        };
+        self.read_sample(&sample, buf)?;
+        self.next += 1;
+        Ok(Some(sample))
+    }
+}
+
+impl<'a, T: Read + Seek> Iterator for SampleReader<'a, T> {
+    type Item = Result<(Sample, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        match self.next_into(&mut buf) {
+            Ok(Some(sample)) => Some(Ok((sample, buf))),
+            Ok(None) => None,
+            Err(error) => {
+                // Stop on the failing sample instead of retrying it forever
+                self.next = self.samples.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/gen.py: 6 video samples in two chunks of 3 with ctts and stss, 4 audio samples
+    // of 7 bytes in two chunks of 2, the chunks interleaved in mdat after a 32 byte ftyp
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    #[test]
+    fn resolve_sample_tables() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let video = mp4.sample_index(1).unwrap();
+        assert_eq!(video.len(), 6);
+        let offsets: Vec<u64> = video.iter().map(|sample| sample.file_offset).collect();
+        assert_eq!(offsets, vec![40, 50, 61, 87, 100, 114]);
+        let sizes: Vec<u32> = video.iter().map(|sample| sample.size).collect();
+        assert_eq!(sizes, vec![10, 11, 12, 13, 14, 15]);
+        let dts: Vec<u64> = video.iter().map(|sample| sample.decode_time).collect();
+        assert_eq!(dts, vec![0, 512, 1024, 1536, 2048, 2560]);
+        let pts: Vec<i64> = video.iter().map(|sample| sample.composition_time).collect();
+        assert_eq!(pts, vec![1024, 3072, 1024, 2048, 3072, 2560]);
+        let sync: Vec<bool> = video.iter().map(|sample| sample.is_sync).collect();
+        assert_eq!(sync, vec![true, false, false, true, false, false]);
+        assert_eq!(video.duration(), MediaTime::new(3072, 12800));
+
+        let audio = mp4.sample_index(2).unwrap();
+        let offsets: Vec<u64> = audio.iter().map(|sample| sample.file_offset).collect();
+        assert_eq!(offsets, vec![73, 80, 129, 136]);
+        assert!(audio.iter().all(|sample| sample.size == 7 && sample.is_sync && sample.duration == 1024));
+        assert_eq!(audio.get(3).unwrap().pts(44100), MediaTime::new(3072, 44100));
+    }
+
+    #[test]
+    fn inconsistent_tables_rejected() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let stbl = &mp4.track(1).unwrap().media.info.sample_table;
+
+        let mut truncated = stbl.clone();
+        truncated.stsz.sample_sizes.pop();
+        let mut missing_chunk = stbl.clone();
+        missing_chunk.stco.as_mut().unwrap().table.pop();
+        let mut extra_samples = stbl.clone();
+        extra_samples.stsz.sample_count = 7;
+        extra_samples.stsz.sample_sizes.push(16);
+        let mut untimed = stbl.clone();
+        untimed.stts.table[0].0 = 5;
+        // A constant sample size with a count no chunk can hold
+        let mut huge_count = stbl.clone();
+        huge_count.stsz.sample_size = 10;
+        huge_count.stsz.sample_sizes.clear();
+        huge_count.stsz.sample_count = 0xFFFFFFF0;
+        huge_count.stts.table = vec![(0xFFFFFFF0, 512)];
+        for stbl in [truncated, missing_chunk, extra_samples, untimed, huge_count] {
+            assert!(matches!(SampleIndex::new(&stbl, 12800), Err(Error::InvalidData(_))));
+        }
+    }
+
+    #[test]
+    fn read_payloads() {
+        let mut src = Cursor::new(PROGRESSIVE);
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        let payloads: Vec<Vec<u8>> =
+            mp4.samples(1, &mut src).unwrap().map(|sample| sample.unwrap().1).collect();
+        let expected: Vec<Vec<u8>> = (0..6).map(|i| vec![0x40 + i as u8; 10 + i]).collect();
+        assert_eq!(payloads, expected);
+
+        // In file order the reads only move forward
+        let mut reader = SampleReader::new(&mut src, mp4.sample_index(2).unwrap()).in_file_order();
+        let mut buf = Vec::new();
+        let mut offsets = Vec::new();
+        while let Some(sample) = reader.next_into(&mut buf).unwrap() {
+            assert_eq!(buf, vec![0x60 + sample.index as u8; 7]);
+            offsets.push(sample.file_offset);
+        }
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn truncated_media_data_stops_the_reader() {
+        let mut src = Cursor::new(PROGRESSIVE);
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        let index = mp4.sample_index(1).unwrap();
+        // Cut the file in the middle of the third video sample
+        let mut src = Cursor::new(&PROGRESSIVE[..65]);
+        let mut reader = SampleReader::new(&mut src, index);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::EOF()))));
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{Error, MediaTime, Mp4, PresentationTimeline, SampleIndex, SeekResult, Track};
+
+// Seeks the tracks of a Mp4 by time. The sample indexes are built once so every seek is a
+// binary search instead of a walk over the sample tables.
+#[derive(Clone, Debug)]
+pub struct Seeker {
+    tracks: Vec<SeekerTrack>,
+}
+
+#[derive(Clone, Debug)]
+struct SeekerTrack {
+    track_id: u32,
+    index: SampleIndex,
+    timeline: PresentationTimeline,
+    reference: bool, // Enabled audio or video track, these pick the common seek point
+}
+
+impl SeekerTrack {
+    fn new(mp4: &Mp4, track: &Track) -> Result<Self, Error> {
+        let index = mp4.sample_index(track.track_id())?;
+        let handler = track.media.handler.as_ref().map(|hdlr| hdlr.handler.as_str());
+        Ok(Self {
+            track_id: track.track_id(),
+            timeline: track.timeline(mp4.moov.mvhd.timescale, &index),
+            index,
+            reference: track.is_enabled() && matches!(handler, Some("vide" | "soun")),
+        })
+    }
+
+    // Seek at a presentation time: the edit that presents it gives the media time to seek,
+    // then the sync sample is mapped back into that edit. Times in empty edits go to the
+    // next presented media and times past the end to the last one. Returns the presentation
+    // time of the sync sample along with the result.
+    fn seek(&self, time: MediaTime) -> Option<(SeekResult, MediaTime)> {
+        let timescale = self.timeline.timescale;
+        let time = time.rescale_floor(timescale).value.max(0) as u64;
+        let mut media_segments = self.timeline.segments.iter().filter(|segment| !segment.is_empty_edit());
+        let segment = media_segments
+            .clone()
+            .find(|segment| time < segment.start + segment.duration)
+            .or_else(|| media_segments.next_back())?;
+        let media_time = segment.media_time?;
+        let offset = match segment.is_dwell() {
+            true => 0,
+            false => time.saturating_sub(segment.start).min(segment.duration.saturating_sub(1)),
+        };
+        let result = self.index.seek(MediaTime::new(media_time + offset as i64, timescale))?;
+        let sync_offset = match segment.is_dwell() {
+            true => 0,
+            false => (result.time.rescale_floor(timescale).value - media_time).max(0) as u64,
+        };
+        Some((result, MediaTime::new((segment.start + sync_offset) as i64, timescale)))
+    }
+}
+
+impl Seeker {
+    pub fn new(mp4: &Mp4) -> Result<Self, Error> {
+        let mut tracks = Vec::with_capacity(mp4.tracks().len());
+        for track in mp4.tracks() {
+            tracks.push(SeekerTrack::new(mp4, track)?);
+        }
+        Ok(Self { tracks })
+    }
+
+    // Time is on the media timeline of the track, edit lists are not applied
+    pub fn seek_track(&self, track_id: u32, time: MediaTime) -> Result<Option<SeekResult>, Error> {
+        let track = self
+            .tracks
+            .iter()
+            .find(|track| track.track_id == track_id)
+            .ok_or(Error::BoxNotFound(format!("Seeker: no track with id {:?}", track_id)))?;
+        Ok(track.index.seek(time))
+    }
+
+    // Seek every track to a common point on the presentation timeline, edit lists applied:
+    // the earliest sync sample the enabled audio and video tracks need to present time.
+    // Other tracks, such as chapter text, follow that point. Tracks without samples are
+    // left out.
+    pub fn seek(&self, time: MediaTime) -> Vec<(u32, SeekResult)> {
+        let start = self
+            .tracks
+            .iter()
+            .filter(|track| track.reference)
+            .filter_map(|track| Some(track.seek(time)?.1))
+            .min()
+            .unwrap_or(time);
+        self.tracks
+            .iter()
+            .filter_map(|track| Some((track.track_id, track.seek(start)?.0)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Chapter, Mp4Muxer, TrackConfig};
+
+    // testdata/gen.py, its avc1 and mp4a sample entries are reused by the muxed tracks
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    fn config(handler: &str, timescale: u32, track_id: u32) -> TrackConfig {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        TrackConfig {
+            handler: handler.to_owned(),
+            timescale,
+            language: "und".to_owned(),
+            width: if handler == "vide" { 640 } else { 0 },
+            height: if handler == "vide" { 360 } else { 0 },
+            codec: mp4.track(track_id).unwrap().sample_entries()[0].clone(),
+        }
+    }
+
+    // Two seconds of 25 fps video with a sync sample every 10 frames, composed one frame
+    // late so the muxer writes an edit, 50 fps 48 kHz audio and chapters at 0 and 1 second
+    fn muxed() -> Mp4 {
+        let mut dst = Cursor::new(Vec::new());
+        let mut muxer = Mp4Muxer::new(&mut dst);
+        let video = muxer.add_track(config("vide", 12800, 1)).unwrap();
+        let audio = muxer.add_track(config("soun", 48000, 2)).unwrap();
+        for i in 0..50u64 {
+            muxer.push(video, &[i as u8; 16], i * 512, (i as i64 + 1) * 512, i % 10 == 0).unwrap();
+        }
+        for i in 0..100u64 {
+            muxer.push(audio, &[i as u8; 8], i * 960, i as i64 * 960, true).unwrap();
+        }
+        muxer.chapters = vec![
+            Chapter { start: MediaTime::new(0, 1), title: "One".to_owned() },
+            Chapter { start: MediaTime::new(1, 1), title: "Two".to_owned() },
+        ];
+        muxer.finish().unwrap();
+        Mp4::parse(&mut Cursor::new(dst.into_inner())).unwrap()
+    }
+
+    fn sync_samples(results: &[(u32, SeekResult)]) -> Vec<(u32, u32)> {
+        results.iter().map(|(track_id, result)| (*track_id, result.sync_sample)).collect()
+    }
+
+    #[test]
+    fn seek_on_the_presentation_timeline() {
+        let mp4 = muxed();
+        let seeker = mp4.seeker().unwrap();
+        // Frame 20 is presented at 0.8 s, on media time 0.84 s behind the edit
+        let results = seeker.seek(MediaTime::new(800, 1000));
+        assert_eq!(sync_samples(&results), vec![(1, 20), (2, 40), (3, 0)]);
+        // The media timeline is not edited
+        let result = seeker.seek_track(1, MediaTime::new(800, 1000)).unwrap().unwrap();
+        assert_eq!(result.sync_sample, 10);
+        // Past the end the last sync samples are used
+        let results = seeker.seek(MediaTime::new(10, 1));
+        assert_eq!(sync_samples(&results), vec![(1, 40), (2, 80), (3, 1)]);
+    }
+
+    #[test]
+    fn chapters_do_not_move_the_seek_point() {
+        let mp4 = muxed();
+        let chapters = mp4.track(3).unwrap();
+        assert!(!chapters.is_enabled());
+        // The first chapter starts at 0, before the video sync sample at 0.8 s
+        let results = mp4.seek(MediaTime::new(900, 1000)).unwrap();
+        assert_eq!(sync_samples(&results), vec![(1, 20), (2, 40), (3, 0)]);
+    }
+
+    #[test]
+    fn disabled_tracks_do_not_move_the_seek_point() {
+        let mut mp4 = muxed();
+        mp4.moov.tracks[0].tkhd.flags &= !0x1;
+        // Audio alone picks the point, the video seeks back to its previous sync sample
+        let results = mp4.seek(MediaTime::new(1300, 1000)).unwrap();
+        assert_eq!(sync_samples(&results), vec![(1, 30), (2, 65), (3, 1)]);
+        let seeker = Seeker::new(&mp4).unwrap();
+        assert!(matches!(seeker.seek_track(4, MediaTime::new(0, 1)), Err(Error::BoxNotFound(_))));
+    }
+}
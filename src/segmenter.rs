@@ -0,0 +1,457 @@
+use std::{io::Write, time::Duration};
+
+use crate::{
+    muxer::movie_header,
+    tfhd::TFHD_DEFAULT_BASE_IS_MOOF,
+    trun::{
+        TRUN_DATA_OFFSET, TRUN_SAMPLE_COMPOSITION_TIME_OFFSET, TRUN_SAMPLE_DURATION,
+        TRUN_SAMPLE_FLAGS, TRUN_SAMPLE_SIZE,
+    },
+    BoxType, BoxWriter, ChunkOffsetBox, Error, FtypBox, MediaTime, MoovBox, MovieExtendsBox,
+    MovieFragmentBox, MovieFragmentHeaderBox, SampleSizeBox, SampleTableBox, SampleToChunkBox,
+    TimeToSampleBox, TrackBox, TrackConfig, TrackExtendsBox, TrackFragmentBox,
+    TrackFragmentDecodeTimeBox, TrackFragmentHeaderBox, TrackRunBox, TrackRunEntry, Writer, HEADER_LENGTH,
+};
+
+// sample_depends_on = 2, the sample does not depend on others
+pub const SYNC_SAMPLE_FLAGS: u32 = 0x02000000;
+// sample_depends_on = 1 and sample_is_non_sync_sample
+pub const NON_SYNC_SAMPLE_FLAGS: u32 = 0x01010000;
+
+#[derive(Clone, Debug)]
+struct PendingSample {
+    data: Vec<u8>,
+    decode_time: u64,
+    composition_time: i64,
+    duration: u32,
+    is_sync: bool,
+}
+
+#[derive(Clone, Debug)]
+struct SegmenterTrack {
+    config: TrackConfig,
+    samples: Vec<PendingSample>, // Samples with a known duration, ready for the next fragment
+    last: Option<PendingSample>, // Waiting for the next decode time to know its duration
+    fragment_start: Option<u64>, // Decode time of the first sample of the next fragment
+    last_duration: u32,
+}
+
+impl SegmenterTrack {
+    fn traf(&self, track_id: u32, base_media_decode_time: u64, data_offset: i32) -> TrackFragmentBox {
+        let has_offsets = self
+            .samples
+            .iter()
+            .any(|sample| sample.composition_time != sample.decode_time as i64);
+        let has_negative_offsets = self
+            .samples
+            .iter()
+            .any(|sample| sample.composition_time < sample.decode_time as i64);
+        let mut flags = TRUN_DATA_OFFSET | TRUN_SAMPLE_DURATION | TRUN_SAMPLE_SIZE | TRUN_SAMPLE_FLAGS;
+        if has_offsets {
+            flags |= TRUN_SAMPLE_COMPOSITION_TIME_OFFSET;
+        }
+        let entries = self
+            .samples
+            .iter()
+            .map(|sample| TrackRunEntry {
+                sample_duration: Some(sample.duration),
+                sample_size: Some(sample.data.len() as u32),
+                sample_flags: Some(match sample.is_sync {
+                    true => SYNC_SAMPLE_FLAGS,
+                    false => NON_SYNC_SAMPLE_FLAGS,
+                }),
+                sample_composition_time_offset: match has_offsets {
+                    true => Some(sample.composition_time - sample.decode_time as i64),
+                    false => None,
+                },
+            })
+            .collect();
+
+        TrackFragmentBox {
+            tfhd: TrackFragmentHeaderBox {
+                version: 0,
+                flags: TFHD_DEFAULT_BASE_IS_MOOF,
+                track_id,
+                ..Default::default()
+            },
+            tfdt: Some(TrackFragmentDecodeTimeBox {
+                version: 1,
+                flags: 0,
+                base_media_decode_time,
+            }),
+            truns: vec![TrackRunBox {
+                // Negative offsets need the signed version
+                version: if has_negative_offsets { 1 } else { 0 },
+                flags,
+                data_offset: Some(data_offset),
+                first_sample_flags: None,
+                entries,
+            }],
+            order: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+}
+
+// Fragmented MP4 (CMAF) muxer producing an init segment and moof + mdat media segments
+#[derive(Clone, Debug)]
+pub struct Segmenter {
+    pub ftyp: FtypBox,
+    pub fragment_duration: Duration,
+    pub cut_at_sync: bool, // Only start fragments on sync samples of the reference track
+    tracks: Vec<SegmenterTrack>,
+    sequence_number: u32,
+}
+
+impl Segmenter {
+    pub fn new(fragment_duration: Duration) -> Self {
+        Self {
+            ftyp: FtypBox {
+                major_brand: "iso6".to_owned(),
+                minor_brand: 0,
+                compatible_brands: vec!["iso6".to_owned(), "cmfc".to_owned()],
+            },
+            fragment_duration,
+            cut_at_sync: true,
+            tracks: Vec::new(),
+            sequence_number: 1,
+        }
+    }
+
+    // Returns the track id
+    pub fn add_track(&mut self, config: TrackConfig) -> Result<u32, Error> {
+        let has_samples = |track: &SegmenterTrack| track.last.is_some() || !track.samples.is_empty();
+        if self.sequence_number != 1 || self.tracks.iter().any(has_samples) {
+            return Err(Error::InvalidData("Segmenter: tracks must be added before samples".to_owned()));
+        }
+        if config.timescale == 0 {
+            return Err(Error::InvalidData("Segmenter: timescale must not be 0".to_owned()));
+        }
+        self.tracks.push(SegmenterTrack {
+            config,
+            samples: Vec::new(),
+            last: None,
+            fragment_start: None,
+            last_duration: 0,
+        });
+        Ok(self.tracks.len() as u32)
+    }
+
+    // Fragments are cut on the first video track, or the first track
+    fn reference_track(&self) -> usize {
+        self.tracks
+            .iter()
+            .position(|track| track.config.is_video())
+            .unwrap_or(0)
+    }
+
+    pub fn moov(&self) -> MoovBox {
+        let tracks: Vec<TrackBox> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                let sample_table = SampleTableBox {
+                    stsd: track.config.sample_description(),
+                    stts: TimeToSampleBox {
+                        version: 0,
+                        flags: 0,
+                        table: Vec::new(),
+                    },
+                    stsc: SampleToChunkBox {
+                        version: 0,
+                        flags: 0,
+                        table: Vec::new(),
+                    },
+                    stsz: SampleSizeBox {
+                        version: 0,
+                        flags: 0,
+                        sample_size: 0,
+                        sample_count: 0,
+                        sample_sizes: Vec::new(),
+                    },
+                    stco: Some(ChunkOffsetBox {
+                        version: 0,
+                        flags: 0,
+                        table: Vec::new(),
+                    }),
+                    co64: None,
+                    ctts: None,
+                    stss: None,
+                    order: Vec::new(),
+                    extensions: Vec::new(),
+                };
+                TrackBox {
+                    tkhd: track.config.track_header(i as u32 + 1, 0),
+                    tref: None,
+                    edts: None,
+                    media: track.config.media(0, sample_table),
+                    order: Vec::new(),
+                    extensions: Vec::new(),
+                }
+            })
+            .collect();
+        let trex = (1..=tracks.len() as u32)
+            .map(|track_id| TrackExtendsBox {
+                version: 0,
+                flags: 0,
+                track_id,
+                default_sample_description_index: 1,
+                default_sample_duration: 0,
+                default_sample_size: 0,
+                default_sample_flags: 0,
+            })
+            .collect();
+        MoovBox {
+            mvhd: movie_header(tracks.len() as u32 + 1, 0),
+            pssh: Vec::new(),
+            tracks,
+            mvex: Some(MovieExtendsBox {
+                mehd: None,
+                trex,
+                order: Vec::new(),
+                extensions: Vec::new(),
+            }),
+            udta: None,
+            order: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    // Write the init segment: ftyp and a moov without samples
+    pub fn write_init<W: Write>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        if self.tracks.is_empty() {
+            return Err(Error::InvalidData("Segmenter: no track".to_owned()));
+        }
+        let ftyp_size = self.ftyp.write(writer)?;
+        let moov_size = self.moov().write(writer)?;
+        Ok(ftyp_size + moov_size)
+    }
+
+    // Add a sample, returns a media segment when the sample starts a new fragment
+    pub fn push(
+        &mut self,
+        track_id: u32,
+        data: &[u8],
+        decode_time: u64,
+        composition_time: i64,
+        is_sync: bool,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let index = (track_id as usize).wrapping_sub(1);
+        if index >= self.tracks.len() {
+            return Err(Error::InvalidData(format!("Segmenter: unknown track {:?}", track_id)));
+        }
+
+        let mut segment = None;
+        if index == self.reference_track() && (is_sync || !self.cut_at_sync) {
+            let track = &self.tracks[index];
+            let limit = MediaTime::from_duration(self.fragment_duration, track.config.timescale);
+            if let Some(start) = track.fragment_start {
+                let has_samples = !track.samples.is_empty() || track.last.is_some();
+                if has_samples && decode_time.saturating_sub(start) >= limit.value as u64 {
+                    // Complete the last sample duration before cutting
+                    self.complete(index, decode_time)?;
+                    segment = self.flush()?;
+                }
+            }
+        }
+
+        self.complete(index, decode_time)?;
+        let track = &mut self.tracks[index];
+        if track.fragment_start.is_none() {
+            track.fragment_start = Some(decode_time);
+        }
+        track.last = Some(PendingSample {
+            data: data.to_vec(),
+            decode_time,
+            composition_time,
+            duration: 0,
+            is_sync,
+        });
+        Ok(segment)
+    }
+
+    // The duration of the waiting sample of a track is known from the next decode time
+    fn complete(&mut self, index: usize, decode_time: u64) -> Result<(), Error> {
+        let track = &mut self.tracks[index];
+        if let Some(last) = track.last.as_ref().filter(|last| decode_time < last.decode_time) {
+            return Err(Error::InvalidData(format!(
+                "Segmenter: decode time {:?} before {:?} on track {:?}",
+                decode_time,
+                last.decode_time,
+                index + 1
+            )));
+        }
+        if let Some(mut last) = track.last.take() {
+            last.duration = (decode_time - last.decode_time) as u32;
+            track.last_duration = last.duration;
+            track.samples.push(last);
+        }
+        Ok(())
+    }
+
+    // Build a media segment from the samples with a known duration. Each track gets its own
+    // moof + mdat as CMAF fragments carry a single track.
+    pub fn flush(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.tracks.iter().all(|track| track.samples.is_empty()) {
+            return Ok(None);
+        }
+        let build = |track: &SegmenterTrack, track_id: u32, sequence_number: u32, data_offset: u64| {
+            let first = track.samples.first().map_or(0, |sample| sample.decode_time);
+            MovieFragmentBox {
+                start: 0,
+                mfhd: MovieFragmentHeaderBox {
+                    version: 0,
+                    flags: 0,
+                    sequence_number,
+                },
+                pssh: Vec::new(),
+                trafs: vec![track.traf(track_id, first, data_offset as i32)],
+                order: Vec::new(),
+                extensions: Vec::new(),
+            }
+        };
+
+        let mut segment: Vec<u8> = Vec::new();
+        let mut writer = BoxWriter::new(&mut segment);
+        for (i, track) in self.tracks.iter_mut().enumerate() {
+            if track.samples.is_empty() {
+                continue;
+            }
+            // The data offset depends on the moof size, which does not depend on its value
+            let moof = build(track, i as u32 + 1, self.sequence_number, 0);
+            let moof_size = moof.write(&mut BoxWriter::new(&mut std::io::sink()))?;
+            let moof = build(track, i as u32 + 1, self.sequence_number, moof_size + HEADER_LENGTH);
+            moof.write(&mut writer)?;
+            let data_size: u64 = track.samples.iter().map(|sample| sample.data.len() as u64).sum();
+            writer.write_box_header(BoxType::MediaData, data_size)?;
+            for sample in track.samples.drain(..) {
+                writer.write_bytes(&sample.data)?;
+            }
+            self.sequence_number += 1;
+        }
+        for track in self.tracks.iter_mut() {
+            track.fragment_start = track.last.as_ref().map(|sample| sample.decode_time);
+        }
+        Ok(Some(segment))
+    }
+
+    // Flush the remaining samples, the last sample of each track lasts as long as the previous one
+    pub fn finish(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        for track in self.tracks.iter_mut() {
+            if let Some(mut last) = track.last.take() {
+                last.duration = track.last_duration;
+                track.samples.push(last);
+            }
+        }
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Mp4;
+
+    // testdata/gen.py, its avc1 and mp4a sample entries are reused by the segmented tracks
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    fn config(handler: &str, timescale: u32, track_id: u32) -> TrackConfig {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        TrackConfig {
+            handler: handler.to_owned(),
+            timescale,
+            language: "und".to_owned(),
+            width: if handler == "vide" { 640 } else { 0 },
+            height: if handler == "vide" { 360 } else { 0 },
+            codec: mp4.track(track_id).unwrap().sample_entries()[0].clone(),
+        }
+    }
+
+    // Two seconds of 25 fps video with a sync sample every 10 frames and B-frame like
+    // composition offsets, 50 fps audio, cut in one second fragments. Returns the init
+    // segment followed by the media segments and the number of media segments.
+    fn segment() -> (Vec<u8>, usize) {
+        let mut segmenter = Segmenter::new(Duration::from_secs(1));
+        let video = segmenter.add_track(config("vide", 12800, 1)).unwrap();
+        let audio = segmenter.add_track(config("soun", 48000, 2)).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        segmenter.write_init(&mut BoxWriter::new(&mut data)).unwrap();
+        let mut segments = Vec::new();
+        for i in 0..50u64 {
+            let pts = (i as i64 + 1 - (i % 2) as i64 * 2) * 512 + 512;
+            segments.extend(segmenter.push(video, &[i as u8; 20], i * 512, pts, i % 10 == 0).unwrap());
+            for j in 2 * i..2 * i + 2 {
+                let segment = segmenter.push(audio, &[j as u8; 6], j * 960, j as i64 * 960, true);
+                segments.extend(segment.unwrap());
+            }
+        }
+        segments.extend(segmenter.finish().unwrap());
+        let count = segments.len();
+        segments.iter().for_each(|segment| data.extend_from_slice(segment));
+        (data, count)
+    }
+
+    #[test]
+    fn segments_read_back() {
+        let (data, count) = segment();
+        assert_eq!(count, 2);
+        let mut src = Cursor::new(data);
+        let mp4 = Mp4::parse(&mut src).unwrap();
+        assert!(mp4.is_fragmented());
+        // One video and one audio fragment per segment
+        assert_eq!(mp4.fragments.len(), 4);
+
+        let video = mp4.sample_index(1).unwrap();
+        assert_eq!(video.len(), 50);
+        for sample in video.iter() {
+            let i = sample.index as i64;
+            assert_eq!(sample.decode_time, sample.index as u64 * 512);
+            assert_eq!(sample.composition_time, (i + 1 - (i % 2) * 2) * 512 + 512);
+            assert_eq!(sample.duration, 512);
+            assert_eq!(sample.is_sync, i % 10 == 0);
+        }
+        // The second fragment starts on the first sync sample after one second
+        let second = mp4.fragments[2].track(1).unwrap();
+        assert_eq!(second.samples[0].decode_time, 15360);
+        assert_eq!(mp4.fragments[2].moof.mfhd.sequence_number, 3);
+
+        let audio = mp4.sample_index(2).unwrap();
+        assert_eq!(audio.len(), 100);
+        assert_eq!(audio.get(99).unwrap().duration, 960);
+        let payloads: Vec<Vec<u8>> =
+            mp4.samples(2, &mut src).unwrap().map(|sample| sample.unwrap().1).collect();
+        assert!(payloads.iter().enumerate().all(|(j, payload)| *payload == vec![j as u8; 6]));
+    }
+
+    #[test]
+    fn one_track_per_fragment() {
+        let (data, _) = segment();
+        let mp4 = Mp4::parse(&mut Cursor::new(data)).unwrap();
+        let tracks: Vec<(u32, u32)> = mp4
+            .fragments
+            .iter()
+            .map(|fragment| {
+                assert_eq!(fragment.moof.trafs.len(), 1);
+                (fragment.moof.mfhd.sequence_number, fragment.moof.trafs[0].tfhd.track_id)
+            })
+            .collect();
+        assert_eq!(tracks, vec![(1, 1), (2, 2), (3, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn invalid_pushes_rejected() {
+        let mut segmenter = Segmenter::new(Duration::from_secs(1));
+        let mut data: Vec<u8> = Vec::new();
+        assert!(segmenter.write_init(&mut BoxWriter::new(&mut data)).is_err());
+        let video = segmenter.add_track(config("vide", 12800, 1)).unwrap();
+        assert!(matches!(segmenter.push(2, &[0], 0, 0, true), Err(Error::InvalidData(_))));
+        segmenter.push(video, &[0], 512, 512, true).unwrap();
+        assert!(matches!(segmenter.push(video, &[0], 0, 0, true), Err(Error::InvalidData(_))));
+        assert!(matches!(segmenter.add_track(config("soun", 48000, 2)), Err(Error::InvalidData(_))));
+        // The rejected push keeps the waiting sample
+        assert!(segmenter.finish().unwrap().is_some());
+    }
+}
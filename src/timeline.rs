@@ -0,0 +1,299 @@
+use crate::{EditListBox, MediaTime, Sample, SampleIndex};
+
+// Movie time to media time mapping of a track, built from its edit list
+#[derive(Clone, Debug, Default)]
+pub struct PresentationTimeline {
+    pub timescale: u32, // Media timescale of every value below
+    pub duration: u64,
+    pub segments: Vec<TimelineSegment>,
+}
+
+// One edit of the edit list
+#[derive(Clone, Debug, Default)]
+pub struct TimelineSegment {
+    pub start: u64, // Presentation time of the segment start
+    pub duration: u64,
+    pub media_time: Option<i64>, // None for an empty edit
+    pub rate: i32,               // 16.16 fix point, 0 for a dwell edit
+    pub samples: Vec<PresentedSample>,
+}
+
+// A sample presented in a segment, in presentation order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct PresentedSample {
+    pub index: u32,             // Index in the sample index
+    pub presentation_time: i64, // PTS on the presentation timeline, before trimming
+    pub duration: u64,          // Presented duration, after trimming
+    pub trim_start: u64,        // Media time cut at the start of the sample, such as AAC priming
+    pub trim_end: u64,
+}
+
+impl TimelineSegment {
+    pub fn is_empty_edit(&self) -> bool {
+        self.media_time.is_none()
+    }
+
+    pub fn is_dwell(&self) -> bool {
+        self.media_time.is_some() && self.rate == 0
+    }
+}
+
+// Times past i64::MAX saturate, only hostile edit lists get there
+fn to_i64(value: u64) -> i64 {
+    value.min(i64::MAX as u64) as i64
+}
+
+// End of the last composed sample, the media duration seen by the edit list
+fn media_end(samples: &[Sample]) -> i64 {
+    samples
+        .iter()
+        .map(|sample| sample.composition_time + sample.duration as i64)
+        .max()
+        .unwrap_or(0)
+}
+
+impl PresentationTimeline {
+    // Apply an edit list, in movie timescale, to the samples of a track in media timescale.
+    // Without edit list the media is presented as is.
+    pub fn new(
+        edit_list: Option<&EditListBox>,
+        movie_timescale: u32,
+        media_timescale: u32,
+        index: &SampleIndex,
+    ) -> Self {
+        let mut timeline = PresentationTimeline {
+            timescale: media_timescale,
+            ..Default::default()
+        };
+        let end = media_end(&index.samples);
+        let entries = edit_list.map(|edit_list| &edit_list.entries[..]).unwrap_or_default();
+        if entries.is_empty() {
+            timeline.push_media(0, end.max(0) as u64, 0x00010000, index);
+            return timeline;
+        }
+
+        for entry in entries {
+            let rate = (entry.media_rate_integer as i16 as i32) << 16
+                | entry.media_rate_fraction as i32;
+            let duration = match entry.segment_duration {
+                // A zero duration edit runs to the end of the media, as written by fragmented files
+                0 if entry.media_time >= 0 => (end - entry.media_time).max(0) as u64,
+                duration => {
+                    let duration = MediaTime::new(to_i64(duration), movie_timescale);
+                    duration.rescale_floor(media_timescale).value as u64
+                }
+            };
+            if entry.media_time < 0 {
+                timeline.segments.push(TimelineSegment {
+                    start: timeline.duration,
+                    duration,
+                    media_time: None,
+                    rate,
+                    samples: Vec::new(),
+                });
+                timeline.duration = timeline.duration.saturating_add(duration);
+            } else if rate == 0 {
+                timeline.push_dwell(entry.media_time, duration, index);
+            } else {
+                timeline.push_media(entry.media_time, duration, rate, index);
+            }
+        }
+        timeline
+    }
+
+    // Samples overlapping [media_time, media_time + duration) shifted to the segment start
+    fn push_media(&mut self, media_time: i64, duration: u64, rate: i32, index: &SampleIndex) {
+        let start = self.duration;
+        let media_end = media_time.saturating_add(to_i64(duration));
+        let mut samples: Vec<PresentedSample> = index
+            .samples
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sample)| {
+                let sample_end = sample.composition_time + sample.duration as i64;
+                if sample_end <= media_time || sample.composition_time >= media_end {
+                    return None;
+                }
+                let trim_start = (media_time - sample.composition_time).max(0) as u64;
+                let trim_end = (sample_end - media_end).max(0) as u64;
+                Some(PresentedSample {
+                    index: i as u32,
+                    presentation_time: to_i64(start).saturating_add(sample.composition_time - media_time),
+                    duration: (sample.duration as u64).saturating_sub(trim_start + trim_end),
+                    trim_start,
+                    trim_end,
+                })
+            })
+            .collect();
+        samples.sort_by_key(|sample| sample.presentation_time);
+
+        self.segments.push(TimelineSegment {
+            start,
+            duration,
+            media_time: Some(media_time),
+            rate,
+            samples,
+        });
+        self.duration = self.duration.saturating_add(duration);
+    }
+
+    // The sample composed at media_time is held for the whole segment
+    fn push_dwell(&mut self, media_time: i64, duration: u64, index: &SampleIndex) {
+        let start = self.duration;
+        let held = index.samples.iter().enumerate().find(|(_, sample)| {
+            sample.composition_time <= media_time
+                && media_time < sample.composition_time + sample.duration as i64
+        });
+        let samples = match held {
+            Some((i, _)) => vec![PresentedSample {
+                index: i as u32,
+                presentation_time: to_i64(start),
+                duration,
+                trim_start: 0,
+                trim_end: 0,
+            }],
+            None => Vec::new(),
+        };
+
+        self.segments.push(TimelineSegment {
+            start,
+            duration,
+            media_time: Some(media_time),
+            rate: 0,
+            samples,
+        });
+        self.duration = self.duration.saturating_add(duration);
+    }
+
+    // Every presented sample in presentation order
+    pub fn samples(&self) -> impl Iterator<Item = &PresentedSample> {
+        self.segments.iter().flat_map(|segment| segment.samples.iter())
+    }
+
+    // Presentation time of a media time, None when it is not presented
+    pub fn media_to_presentation(&self, media_time: i64) -> Option<u64> {
+        self.segments.iter().find_map(|segment| {
+            let segment_media_time = segment.media_time?;
+            if segment.rate == 0 || media_time < segment_media_time {
+                return None;
+            }
+            let offset = (media_time - segment_media_time) as u64;
+            (offset < segment.duration).then_some(segment.start.saturating_add(offset))
+        })
+    }
+
+    // Media time presented at a presentation time, None in empty edits
+    pub fn presentation_to_media(&self, presentation_time: u64) -> Option<i64> {
+        let segment = self.segments.iter().find(|segment| {
+            segment.start <= presentation_time && presentation_time - segment.start < segment.duration
+        })?;
+        let media_time = segment.media_time?;
+        match segment.rate {
+            0 => Some(media_time),
+            _ => Some(media_time.saturating_add(to_i64(presentation_time - segment.start))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{elst::EditEntry, Mp4};
+
+    // testdata/gen.py: the video track has a 200 ms edit from media time 1024 at 12800 Hz,
+    // the audio track has no edit list
+    const PROGRESSIVE: &[u8] = include_bytes!("../testdata/progressive.mp4");
+
+    fn edit(segment_duration: u64, media_time: i64, media_rate_integer: u16) -> EditEntry {
+        EditEntry {
+            segment_duration,
+            media_time,
+            media_rate_integer,
+            media_rate_fraction: 0,
+        }
+    }
+
+    fn edit_list(entries: Vec<EditEntry>) -> EditListBox {
+        EditListBox {
+            version: 1,
+            flags: 0,
+            entries,
+        }
+    }
+
+    fn presented(timeline: &PresentationTimeline) -> Vec<(u32, i64, u64)> {
+        timeline.samples().map(|sample| (sample.index, sample.presentation_time, sample.duration)).collect()
+    }
+
+    #[test]
+    fn edit_list_from_fixture() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let video = mp4.presentation_timeline(1).unwrap();
+        assert_eq!((video.timescale, video.duration, video.segments.len()), (12800, 2560, 1));
+        assert_eq!(video.segments[0].media_time, Some(1024));
+        assert_eq!(
+            presented(&video),
+            vec![(0, 0, 512), (2, 0, 512), (3, 1024, 512), (5, 1536, 512), (1, 2048, 512), (4, 2048, 512)]
+        );
+        assert_eq!(video.media_to_presentation(1024), Some(0));
+        assert_eq!(video.media_to_presentation(1023), None);
+        assert_eq!(video.media_to_presentation(3584), None);
+        assert_eq!(video.presentation_to_media(2559), Some(3583));
+        assert_eq!(video.presentation_to_media(2560), None);
+
+        // Without edit list the whole media is presented from 0
+        let audio = mp4.presentation_timeline(2).unwrap();
+        assert_eq!((audio.timescale, audio.duration), (44100, 4096));
+        assert_eq!(presented(&audio), vec![(0, 0, 1024), (1, 1024, 1024), (2, 2048, 1024), (3, 3072, 1024)]);
+        assert_eq!(mp4.track(2).unwrap().presentation_timeline(1000).unwrap().duration, 4096);
+    }
+
+    #[test]
+    fn empty_dwell_and_media_segments() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let index = mp4.sample_index(1).unwrap();
+        // 100 ms of nothing, 40 ms held on the picture at media time 2048, then 80 ms from 1536
+        let edits = edit_list(vec![edit(100, -1, 1), edit(40, 2048, 0), edit(80, 1536, 1)]);
+        let timeline = PresentationTimeline::new(Some(&edits), 1000, 12800, &index);
+        let starts: Vec<(u64, u64)> =
+            timeline.segments.iter().map(|segment| (segment.start, segment.duration)).collect();
+        assert_eq!(starts, vec![(0, 1280), (1280, 512), (1792, 1024)]);
+        assert!(timeline.segments[0].is_empty_edit() && timeline.segments[0].samples.is_empty());
+        assert!(timeline.segments[1].is_dwell());
+        assert_eq!(timeline.duration, 2816);
+        assert_eq!(presented(&timeline), vec![(3, 1280, 512), (3, 2304, 512)]);
+
+        assert_eq!(timeline.presentation_to_media(500), None);
+        assert_eq!(timeline.presentation_to_media(1500), Some(2048));
+        assert_eq!(timeline.presentation_to_media(1800), Some(1544));
+        assert_eq!(timeline.media_to_presentation(2048), Some(2304));
+    }
+
+    #[test]
+    fn priming_is_trimmed() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let index = mp4.sample_index(2).unwrap();
+        // Skip 1600 priming samples, a zero duration runs to the end of the media
+        let edits = edit_list(vec![edit(0, 1600, 1)]);
+        let timeline = PresentationTimeline::new(Some(&edits), 1000, 44100, &index);
+        assert_eq!(timeline.duration, 4096 - 1600);
+        let first = timeline.samples().next().unwrap();
+        assert_eq!((first.index, first.presentation_time, first.duration), (1, -576, 448));
+        assert_eq!((first.trim_start, first.trim_end), (576, 0));
+        assert_eq!(presented(&timeline)[1..], [(2, 448, 1024), (3, 1472, 1024)]);
+    }
+
+    #[test]
+    fn huge_edits_saturate() {
+        let mp4 = Mp4::parse(&mut Cursor::new(PROGRESSIVE)).unwrap();
+        let index = mp4.sample_index(1).unwrap();
+        let edits = edit_list(vec![edit(u64::MAX, 1024, 1), edit(u64::MAX, -1, 1), edit(u64::MAX, i64::MAX, 0)]);
+        let timeline = PresentationTimeline::new(Some(&edits), 1, 12800, &index);
+        assert_eq!(timeline.samples().count(), 6);
+        assert_eq!(timeline.segments[0].duration, i64::MAX as u64);
+        assert_eq!(timeline.duration, u64::MAX);
+        assert_eq!(timeline.presentation_to_media(u64::MAX - 1), Some(i64::MAX));
+    }
+}
@@ -0,0 +1,246 @@
+use std::io::Write;
+
+use crate::{BoxType, Error, FourCC, HEADER_LENGTH};
+
+#[derive(Debug)]
+pub struct BoxWriter<'a, W: ?Sized + 'a> {
+    dst: &'a mut W,
+    pub error: Option<Error>,
+    written: u64,
+    // Only count the written bytes, used to compute box sizes before writing them
+    sizing: bool,
+}
+
+// Sink counting the bytes of a box content
+struct SizeCounter;
+
+impl Write for SizeCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Unsized destination seen as a trait object by box contents
+struct Destination<'a, W: ?Sized>(&'a mut W);
+
+impl<W: Write + ?Sized> Write for Destination<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<'a, W: Write + ?Sized> BoxWriter<'a, W> {
+    pub fn new(dst: &'a mut W) -> BoxWriter<'a, W> {
+        Self {
+            dst,
+            error: None,
+            written: 0,
+            sizing: false,
+        }
+    }
+
+    fn set_error(&mut self, error: std::io::Error) -> Error {
+        let error = Error::InvalidData(error.to_string());
+        self.error = Some(error.clone());
+        error
+    }
+
+    pub fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Error> {
+        if !self.sizing {
+            if let Err(error) = self.dst.write_all(buf) {
+                return Err(self.set_error(error));
+            }
+        }
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    // Number of bytes written so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn write_zeros(&mut self, len: usize) -> Result<(), Error> {
+        self.write_bytes(&vec![0; len])
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> Result<(), Error> {
+        self.write_bytes(&[value])
+    }
+
+    pub fn write_u16(&mut self, value: u16) -> Result<(), Error> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> Result<(), Error> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_i32(&mut self, value: i32) -> Result<(), Error> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> Result<(), Error> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_i64(&mut self, value: i64) -> Result<(), Error> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    // Write a string on exactly len bytes, zero padded
+    pub fn write_string(&mut self, value: &str, len: usize) -> Result<(), Error> {
+        let bytes = value.as_bytes();
+        if bytes.len() > len {
+            return Err(Error::InvalidData(format!(
+                "Writer: string {:?} longer than {:?} bytes",
+                value, len
+            )));
+        }
+        self.write_bytes(bytes)?;
+        self.write_zeros(len - bytes.len())
+    }
+
+    // Write a null terminated string
+    pub fn write_cstring(&mut self, value: &str) -> Result<(), Error> {
+        self.write_bytes(value.as_bytes())?;
+        self.write_u8(0)
+    }
+
+    pub fn write_fourcc(&mut self, value: &str) -> Result<(), Error> {
+        self.write_string(value, 4)
+    }
+
+    pub fn write_header_extra(&mut self, version: u8, flags: u32) -> Result<(), Error> {
+        self.write_u32((version as u32) << 24 | (flags & 0x00FF_FFFF))
+    }
+
+    // Write a box header for a content of content_size bytes,
+    // switching to a 64 bit largesize when the box does not fit in 32 bits.
+    // Returns the header length.
+    pub fn write_box_header(&mut self, name: BoxType, content_size: u64) -> Result<u64, Error> {
+        let size = content_size + HEADER_LENGTH;
+        let fourcc: FourCC = From::from(name);
+        if size > u32::MAX as u64 {
+            self.write_u32(1)?;
+            self.write_bytes(&fourcc.value)?;
+            self.write_u64(size + 8)?;
+            return Ok(HEADER_LENGTH + 8);
+        }
+        self.write_u32(size as u32)?;
+        self.write_bytes(&fourcc.value)?;
+        Ok(HEADER_LENGTH)
+    }
+
+    // Write a box whose content is produced by content. The content is run once to
+    // compute the box size then streamed after the header, so that nothing is buffered.
+    // Returns the total box size.
+    pub fn write_box<F>(&mut self, name: BoxType, content: F) -> Result<u64, Error>
+    where
+        F: Fn(&mut BoxWriter<dyn Write>) -> Result<(), Error>,
+    {
+        let mut counter = SizeCounter;
+        let mut sizer: BoxWriter<dyn Write> = BoxWriter::new(&mut counter);
+        sizer.sizing = true;
+        content(&mut sizer)?;
+        let content_size = sizer.written;
+
+        let header_size = self.write_box_header(name, content_size)?;
+        if self.sizing {
+            self.written += content_size;
+            return Ok(header_size + content_size);
+        }
+        let mut dst = Destination(&mut *self.dst);
+        let mut writer: BoxWriter<dyn Write> = BoxWriter::new(&mut dst);
+        let result = content(&mut writer);
+        self.written += writer.written;
+        if let Some(error) = writer.error {
+            self.error = Some(error);
+        }
+        result?;
+        if writer.written != content_size {
+            return Err(Error::InternalError());
+        }
+        Ok(header_size + content_size)
+    }
+
+    pub fn show_error(&self) -> String {
+        match &self.error {
+            Some(error) => error.to_string(),
+            None => "Ok".to_string(),
+        }
+    }
+}
+
+pub trait Writer {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error>;
+}
+
+impl<B: Writer> Writer for Option<B> {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        match self {
+            Some(b) => b.write(writer),
+            None => Ok(0),
+        }
+    }
+}
+
+impl<B: Writer> Writer for Vec<B> {
+    fn write<W: Write + ?Sized>(&self, writer: &mut BoxWriter<W>) -> Result<u64, Error> {
+        let mut size = 0;
+        for b in self.iter() {
+            size += b.write(writer)?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_boxes_sized_before_streaming() {
+        let mut data: Vec<u8> = Vec::new();
+        let mut writer = BoxWriter::new(&mut data);
+        let size = writer
+            .write_box(BoxType::Movie, |writer| {
+                writer.write_box(BoxType::Free, |writer| writer.write_u32(7))?;
+                writer.write_box(BoxType::Skip, |_| Ok(()))?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(size, 28);
+        assert_eq!(writer.written(), 28);
+        assert_eq!(
+            data,
+            [
+                &[0, 0, 0, 28][..],
+                b"moov",
+                &[0, 0, 0, 12],
+                b"free",
+                &[0, 0, 0, 7],
+                &[0, 0, 0, 8],
+                b"skip",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn large_box_header() {
+        let mut data: Vec<u8> = Vec::new();
+        let mut writer = BoxWriter::new(&mut data);
+        assert_eq!(writer.write_box_header(BoxType::MediaData, 1 << 32).unwrap(), 16);
+        assert_eq!(data[..8], [0, 0, 0, 1, b'm', b'd', b'a', b't']);
+        assert_eq!(u64::from_be_bytes(data[8..].try_into().unwrap()), (1 << 32) + 16);
+    }
+}
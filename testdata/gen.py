@@ -0,0 +1,71 @@
+import struct, sys
+def box(t, payload): return struct.pack('>I',8+len(payload))+t+payload
+def full(t,v,f,payload): return box(t, struct.pack('>I',(v<<24)|f)+payload)
+def u32(x): return struct.pack('>I',x)
+def u16(x): return struct.pack('>H',x)
+MAT = struct.pack('>9i',0x10000,0,0,0,0x10000,0,0,0,0x40000000)
+def mvhd(ts,dur): return full(b'mvhd',0,0,u32(0)+u32(0)+u32(ts)+u32(dur)+u32(0x10000)+u16(0x100)+b'\0'*10+MAT+b'\0'*24+u32(3))
+def tkhd(tid,dur,w,h): return full(b'tkhd',0,3,u32(0)+u32(0)+u32(tid)+u32(0)+u32(dur)+b'\0'*8+u16(0)+u16(0)+u16(0)+b'\0'*2+MAT+u32(w<<16)+u32(h<<16))
+def mdhd(ts,dur): return full(b'mdhd',0,0,u32(0)+u32(0)+u32(ts)+u32(dur)+u16(0x55c4)+u16(0))
+def hdlr(h,name): return full(b'hdlr',0,0,u32(0)+h+b'\0'*12+name+b'\0')
+def dinf(): return box(b'dinf', full(b'dref',0,0,u32(1)+full(b'url ',0,1,b'')))
+SPS=bytes.fromhex('6764001facd9405005bb016a02020280000003008000001e478c18cb')
+PPS=bytes.fromhex('68ebe3cb22c0')
+def avc1(w,h):
+    avcc=box(b'avcC', bytes([1,0x64,0,0x1f,0xff,0xe1])+u16(len(SPS))+SPS+bytes([1])+u16(len(PPS))+PPS+bytes([0xfd,0xf8,0xf8,0]))
+    return box(b'avc1', b'\0'*6+u16(1)+b'\0'*16+u16(w)+u16(h)+u32(0x480000)+u32(0x480000)+u32(0)+u16(1)+bytes([4])+b'test'+b'\0'*27+u16(0x18)+u16(0xffff)+avcc)
+def hvcc(arrays):
+    b=bytes([1,0x01])+u32(0x60000000)+bytes([0x90,0,0,0,0,0])+bytes([93])+u16(0xf000)+bytes([0xfc,0xfd,0xf8,0xf8])+u16(0)+bytes([0x0f])+bytes([len(arrays)])
+    for t,nalus in arrays:
+        b+=bytes([0x80|t])+u16(len(nalus))+b''.join(u16(len(n))+n for n in nalus)
+    return box(b'hvcC', b)
+VPS=bytes.fromhex('40010c01ffff016000000300900000030000030078959809')
+HSPS=bytes.fromhex('420101016000000300900000030000030078a00502016965959a4932bc05a80808082000000300200000030321')
+HPPS=bytes.fromhex('4401c172b46240')
+def hvc1(w,h):
+    return box(b'hvc1', b'\0'*6+u16(1)+b'\0'*16+u16(w)+u16(h)+u32(0x480000)+u32(0x480000)+u32(0)+u16(1)+b'\0'*32+u16(0x18)+u16(0xffff)+hvcc([(32,[VPS]),(33,[HSPS]),(34,[HPPS])])+box(b'pasp',u32(1)+u32(1)))
+def esds():
+    asc=bytes([0x12,0x10])
+    dsi=bytes([5,len(asc)])+asc
+    dcd=bytes([4,13+len(dsi)])+bytes([0x40,0x15])+b'\0\0\0'+u32(128000)+u32(128000)+dsi
+    sl=bytes([6,1,2])
+    es=bytes([3,3+len(dcd)+len(sl)])+u16(2)+bytes([0])+dcd+sl
+    return full(b'esds',0,0,es)
+def mp4a():
+    return box(b'mp4a', b'\0'*6+u16(1)+b'\0'*8+u16(2)+u16(16)+u16(0)+u16(0)+u32(44100<<16)+esds())
+def stsd(entry): return full(b'stsd',0,0,u32(1)+entry)
+def stts(t): return full(b'stts',0,0,u32(len(t))+b''.join(u32(a)+u32(b) for a,b in t))
+def ctts(t): return full(b'ctts',0,0,u32(len(t))+b''.join(u32(a)+struct.pack('>i',b) for a,b in t))
+def stsc(t): return full(b'stsc',0,0,u32(len(t))+b''.join(u32(a)+u32(b)+u32(c) for a,b,c in t))
+def stsz(sz,t): return full(b'stsz',0,0,u32(sz)+u32(len(t) if sz==0 else t)+(b''.join(u32(x) for x in t) if sz==0 else b''))
+def stco(t): return full(b'stco',0,0,u32(len(t))+b''.join(u32(x) for x in t))
+def stss(t): return full(b'stss',0,0,u32(len(t))+b''.join(u32(x) for x in t))
+def elst(entries): return box(b'edts', full(b'elst',0,0,u32(len(entries))+b''.join(u32(d)+struct.pack('>i',m)+u16(1)+u16(0) for d,m in entries)))
+
+# layout: ftyp, mdat, moov
+ftyp=box(b'ftyp', b'isom'+u32(512)+b'isomiso2avc1mp41')
+# video: 6 samples sizes 10..15 ; audio: 4 samples size 7
+vsizes=[10,11,12,13,14,15]
+vdata=[bytes([0x40+i])*s for i,s in enumerate(vsizes)]
+adata=[bytes([0x60+i])*7 for i in range(4)]
+# chunks: v chunk0 (3 samples), a chunk0 (2), v chunk1 (3), a chunk1 (2)
+chunks=[b''.join(vdata[:3]), b''.join(adata[:2]), b''.join(vdata[3:]), b''.join(adata[2:])]
+mdat_off=len(ftyp)
+base=mdat_off+8
+offs=[]; o=base
+for c in chunks: offs.append(o); o+=len(c)
+mdat=box(b'mdat', b''.join(chunks))
+MODE=sys.argv[2] if len(sys.argv)>2 else ''
+VENTRY=hvc1(640,360) if MODE=='hevc' else avc1(640,360)
+VSTSD=stsd(VENTRY)
+VSTSC=stsc([(1,3,1)])
+if MODE=='multi':
+    # spliced clip: the second video chunk uses a second sample description
+    VSTSD=full(b'stsd',0,0,u32(2)+VENTRY+avc1(1280,720))
+    VSTSC=stsc([(1,3,1),(2,3,2)])
+vstbl=box(b'stbl', VSTSD+stts([(6,512)])+stss([1,4])+ctts([(1,1024),(1,2560),(1,0),(1,512),(1,1024),(1,0)])+VSTSC+stsz(0,vsizes)+stco([offs[0],offs[2]]))
+astbl=box(b'stbl', stsd(mp4a())+stts([(4,1024)])+stsc([(1,2,1)])+stsz(7,4)+stco([offs[1],offs[3]]))
+vtrak=box(b'trak', tkhd(1,200,640,360)+elst([(200,1024)])+box(b'mdia', mdhd(12800,3072)+hdlr(b'vide',b'VideoHandler')+box(b'minf', full(b'vmhd',0,1,u16(0)+u16(0)*3)+dinf()+vstbl)))
+atrak=box(b'trak', tkhd(2,93,0,0)+box(b'mdia', mdhd(44100,4096)+hdlr(b'soun',b'SoundHandler')+box(b'minf', full(b'smhd',0,0,u16(0)+u16(0))+dinf()+astbl)))
+moov=box(b'moov', mvhd(1000,200)+vtrak+atrak)
+open(sys.argv[1],'wb').write(ftyp+mdat+moov)
@@ -0,0 +1,93 @@
+# One single sample track per codec configuration, sample entries built field by field.
+# av1.mp4: av01 8 bit BT.709 1920x1080 and av01 10 bit PQ 3840x2160 with its sequence header OBU
+# vp.mp4: vp09 8 bit BT.709, vp09 profile 2 10 bit PQ full range, vp08 with a version 0 vpcC
+# audio.mp4: 5.1 Opus, 24 bit 96 kHz FLAC, 5.1 AC-3, 7.1 E-AC-3 with JOC, 24 bit 96 kHz ALAC
+import struct, sys
+def box(t, payload): return struct.pack('>I',8+len(payload))+t+payload
+def full(t,v,f,payload): return box(t, struct.pack('>I',(v<<24)|f)+payload)
+def u32(x): return struct.pack('>I',x)
+def u16(x): return struct.pack('>H',x)
+MAT = struct.pack('>9i',0x10000,0,0,0,0x10000,0,0,0,0x40000000)
+class Bits:
+    def __init__(self): self.bits = []
+    def put(self, n, value): self.bits += [(value >> (n-1-i)) & 1 for i in range(n)]
+    def bytes(self):
+        bits = self.bits + [1] + [0]*(-(len(self.bits)+1) % 8) # trailing_bits
+        return bytes(int(''.join(map(str, bits[i:i+8])), 2) for i in range(0, len(bits), 8))
+def visual(name, w, h, config):
+    return box(name, b'\0'*6+u16(1)+b'\0'*16+u16(w)+u16(h)+u32(0x480000)+u32(0x480000)+u32(0)+u16(1)+b'\0'*32+u16(0x18)+u16(0xffff)+config)
+
+def av1_sequence_header(level, w, h, high_bitdepth, color):
+    b = Bits()
+    b.put(3, 0); b.put(1, 0); b.put(1, 0) # seq_profile, still_picture, reduced_still_picture_header
+    b.put(1, 0); b.put(1, 0) # timing_info_present_flag, initial_display_delay_present_flag
+    b.put(5, 0); b.put(12, 0); b.put(5, level); b.put(1, 0) # one operating point, seq_tier
+    b.put(4, 15); b.put(4, 15); b.put(16, w-1); b.put(16, h-1)
+    b.put(1, 0) # frame_id_numbers_present_flag
+    b.put(1, 1); b.put(2, 3) # use_128x128_superblock, filter intra and intra edge
+    b.put(4, 15); b.put(1, 1); b.put(2, 3) # compound and warped tools, enable_order_hint, jnt_comp, ref_frame_mvs
+    b.put(1, 1); b.put(1, 1) # seq_choose_screen_content_tools, seq_choose_integer_mv
+    b.put(3, 6) # order_hint_bits_minus_1
+    b.put(1, 0); b.put(1, 1); b.put(1, 1) # superres, cdef, restoration
+    b.put(1, high_bitdepth); b.put(1, 0) # high_bitdepth, mono_chrome
+    if color:
+        b.put(1, 1); b.put(8, color[0]); b.put(8, color[1]); b.put(8, color[2])
+    else:
+        b.put(1, 0)
+    b.put(1, 0); b.put(2, 0); b.put(1, 0) # color_range, chroma_sample_position, separate_uv_delta_q
+    b.put(1, 0) # film_grain_params_present
+    return b.bytes()
+def av01(level, w, h, high_bitdepth, color):
+    obu = av1_sequence_header(level, w, h, high_bitdepth, color)
+    obu = bytes([1 << 3 | 2, len(obu)]) + obu
+    av1c = box(b'av1C', bytes([0x81, level, high_bitdepth << 6 | 0x0C, 0]) + obu)
+    return visual(b'av01', w, h, av1c)
+
+def audio(name, channels, sample_size, rate, config):
+    return box(name, b'\0'*6+u16(1)+b'\0'*8+u16(channels)+u16(sample_size)+u16(0)+u16(0)+u32(rate<<16)+config)
+def dops():
+    return box(b'dOps', bytes([0, 6])+u16(312)+u32(48000)+u16(0)+bytes([1, 4, 2, 0, 4, 1, 2, 3, 5]))
+def dfla():
+    info = u16(4096)+u16(4096)+struct.pack('>I', 14)[1:]+struct.pack('>I', 8000)[1:]
+    info += struct.pack('>Q', 96000 << 44 | 1 << 41 | 23 << 36 | 960000)+bytes(range(16))
+    comment = struct.pack('<I', 6)+b'mp4kit'+struct.pack('<I', 0)
+    return full(b'dfLa', 0, 0, u32(34)+info+u32(0x84 << 24 | len(comment))+comment)
+def dac3(): return box(b'dac3', struct.pack('>I', 0 << 22 | 8 << 17 | 0 << 14 | 7 << 11 | 1 << 10 | 14 << 5)[1:])
+def dec3(): return box(b'dec3', u16(640 << 3)+bytes([16 << 1, 7 << 1 | 1, 1 << 1, 0x80, 0x01, 0x10]))
+def alac():
+    cookie = u32(4096)+bytes([0, 24, 40, 10, 14, 2])+u16(255)+u32(0)+u32(0)+u32(96000)
+    return full(b'alac', 0, 0, cookie)
+
+def vpcc(version, profile, level, depth, chroma, full_range, primaries, transfer, matrix):
+    if version == 0:
+        fields = bytes([profile, level, depth << 4 | matrix, chroma << 4 | transfer, full_range << 7])
+    else:
+        fields = bytes([profile, level, depth << 4 | chroma << 1 | full_range, primaries, transfer, matrix])
+    return full(b'vpcC', version, 0, fields + u16(0))
+
+KINDS = {
+    'av1': [(b'vide', av01(8, 1920, 1080, 0, (1, 1, 1))), (b'vide', av01(13, 3840, 2160, 1, (9, 16, 9)))],
+    'vp': [(b'vide', visual(b'vp09', 1280, 720, vpcc(1, 0, 31, 8, 1, 0, 1, 1, 1))),
+           (b'vide', visual(b'vp09', 3840, 2160, vpcc(1, 2, 51, 10, 1, 1, 9, 16, 9)+box(b'pasp', u32(1)+u32(1)))),
+           (b'vide', visual(b'vp08', 640, 360, vpcc(0, 0, 10, 8, 1, 0, 0, 1, 1)))],
+    'audio': [(b'soun', audio(b'Opus', 2, 16, 48000, dops())), (b'soun', audio(b'fLaC', 2, 24, 0, dfla())),
+              (b'soun', audio(b'ac-3', 2, 16, 48000, dac3())), (b'soun', audio(b'ec-3', 2, 16, 48000, dec3())),
+              (b'soun', audio(b'alac', 2, 24, 0, alac()))],
+}
+
+def trak(tid, handler, entry, offset):
+    media = full(b'vmhd',0,1,u16(0)+u16(0)*3) if handler == b'vide' else full(b'smhd',0,0,u32(0))
+    stbl = box(b'stbl', full(b'stsd',0,0,u32(1)+entry)+full(b'stts',0,0,u32(1)+u32(1)+u32(1000))
+        +full(b'stsc',0,0,u32(1)+u32(1)+u32(1)+u32(1))+full(b'stsz',0,0,u32(16)+u32(1))+full(b'stco',0,0,u32(1)+u32(offset)))
+    dinf = box(b'dinf', full(b'dref',0,0,u32(1)+full(b'url ',0,1,b'')))
+    return box(b'trak', full(b'tkhd',0,3,u32(0)*2+u32(tid)+u32(0)+u32(1000)+b'\0'*16+MAT+u32(0)*2)
+        +box(b'mdia', full(b'mdhd',0,0,u32(0)*2+u32(1000)+u32(1000)+u16(0x55c4)+u16(0))
+        +full(b'hdlr',0,0,u32(0)+handler+b'\0'*12+b'\0')+box(b'minf', media+dinf+stbl)))
+
+tracks = KINDS[sys.argv[2]]
+ftyp = box(b'ftyp', b'isom'+u32(512)+b'isomiso2mp41')
+mdat = box(b'mdat', b''.join(bytes([i])*16 for i in range(len(tracks))))
+base = len(ftyp) + 8
+traks = b''.join(trak(i+1, h, e, base+16*i) for i, (h, e) in enumerate(tracks))
+moov = box(b'moov', full(b'mvhd',0,0,u32(0)*2+u32(1000)+u32(1000)+u32(0x10000)+u16(0x100)+b'\0'*10+MAT+b'\0'*24+u32(len(tracks)+1))+traks)
+open(sys.argv[1],'wb').write(ftyp+mdat+moov)
@@ -0,0 +1,40 @@
+# Common Encryption signalling on top of progressive.mp4 and fragmented.mp4: encv with its
+# sinf between avcC and pasp, enca with its sinf last, pssh boxes in moov and moof.
+# Writes encrypted.mp4 and encrypted_fragmented.mp4, the samples are left in the clear.
+import struct
+def box(name, payload): return struct.pack('>I', len(payload)+8) + name + payload
+def full(name, v, f, payload): return box(name, struct.pack('>I', (v<<24)|f) + payload)
+KID1 = bytes(range(16)); KID2 = bytes(range(16, 32))
+WV = bytes.fromhex('edef8ba979d64acea3c827dcd51d21ed')
+PR = bytes.fromhex('9a04f07998404286ab92e65be0885f95')
+def sinf(fmt, scheme, tenc):
+    return box(b'sinf', box(b'frma', fmt) + full(b'schm', 0, 0, scheme + struct.pack('>I', 0x10000)) + box(b'schi', tenc))
+tenc_v0 = full(b'tenc', 0, 0, bytes([0, 0, 1, 8]) + KID1)
+tenc_v1 = full(b'tenc', 1, 0, bytes([0, 0x19, 1, 0]) + KID2 + bytes([16]) + bytes(range(100, 116)))
+pssh_v1 = full(b'pssh', 1, 0, WV + struct.pack('>I', 2) + KID1 + KID2 + struct.pack('>I', 4) + b'wvdt')
+pssh_v0 = full(b'pssh', 0, 0, PR + struct.pack('>I', 3) + b'prh')
+pasp = box(b'pasp', struct.pack('>II', 1, 1))
+CONTAINERS = {b'moov', b'trak', b'mdia', b'minf', b'stbl', b'moof'}
+def after(body, name, extra):
+    i = body.index(name) - 4; n = int.from_bytes(body[i:i+4], 'big')
+    return body[:i+n] + extra + body[i+n:]
+def rewrite(d):
+    out = b''; pos = 0
+    while pos + 8 <= len(d):
+        size = int.from_bytes(d[pos:pos+4], 'big'); name = d[pos+4:pos+8]; body = d[pos+8:pos+size]
+        if name in CONTAINERS:
+            body = rewrite(body)
+            if name == b'moov': body = after(body, b'mvhd', pssh_v1 + pssh_v0)
+            if name == b'moof': body = after(body, b'mfhd', pssh_v0)
+        elif name == b'stsd':
+            hdr = body[:8]; entry = body[8:]
+            ename = entry[4:8]
+            if ename == b'avc1':
+                fields, children = entry[8:86], entry[86:]
+                entry = box(b'encv', fields + children + sinf(b'avc1', b'cenc', tenc_v0) + pasp)
+            elif ename == b'mp4a': entry = box(b'enca', entry[8:] + sinf(b'mp4a', b'cbcs', tenc_v1))
+            body = hdr + entry
+        out += box(name, body); pos += size
+    return out
+for src, dst in [('progressive.mp4', 'encrypted.mp4'), ('fragmented.mp4', 'encrypted_fragmented.mp4')]:
+    open(dst, 'wb').write(rewrite(open(src, 'rb').read()))
@@ -0,0 +1,25 @@
+# Progressive file with children the typed model does not know at every container level,
+# a hdlr name without NUL and a non default pre_defined in the visual sample entry
+import struct, sys
+sys.argv = [sys.argv[0], '/dev/null']
+exec(open('gen.py').read().split('# layout')[0])
+ftyp = box(b'ftyp', b'isom'+u32(512)+b'isomiso2avc1mp41')
+vsizes = [10, 11, 12]
+mdat = box(b'mdat', b''.join(bytes([0x40+i])*s for i, s in enumerate(vsizes)))
+entry = avc1(640, 360)
+entry = entry[:8+76] + u16(0) + entry[8+78:]
+sgpd = full(b'sgpd', 1, 0, b'roll'+u32(2)+u32(1)+u16(0xffff))
+sbgp = full(b'sbgp', 0, 0, b'roll'+u32(1)+u32(3)+u32(1))
+sdtp = full(b'sdtp', 0, 0, bytes([0x20, 0x10, 0x10]))
+vstbl = box(b'stbl', stsd(entry)+stts([(3, 512)])+sgpd+stss([1])+sbgp+sdtp+stsc([(1, 3, 1)])+stsz(0, vsizes)+stco([len(ftyp)+8]))
+hdlr_qt = full(b'hdlr', 0, 0, u32(0)+b'vide'+b'\0'*12+b'VideoHandler')
+gmhd = box(b'gmhd', full(b'gmin', 0, 0, u16(0x40)+u16(0x8000)*3+u16(0)+u16(0)))
+minf = box(b'minf', full(b'vmhd', 0, 1, u16(0)*4)+gmhd+dinf()+vstbl)
+elng = full(b'elng', 0, 0, b'en-US\0')
+mdia = box(b'mdia', mdhd(12800, 1536)+elng+hdlr_qt+minf)
+tudta = box(b'udta', box(b'name', b'Camera'))
+tmeta = full(b'meta', 0, 0, hdlr(b'mdta', b''))
+trak = box(b'trak', tkhd(1, 120, 640, 360)+tudta+mdia+tmeta)
+iods = full(b'iods', 0, 0, bytes([0x10, 7, 0, 0x4f, 0xff, 0xff, 0x29, 0xff, 0xff]))
+moov = box(b'moov', mvhd(1000, 120)+iods+trak+box(b'free', b'\0'*8))
+open('extra_children.mp4', 'wb').write(ftyp+mdat+moov)
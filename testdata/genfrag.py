@@ -0,0 +1,28 @@
+import struct, sys
+sys.argv=[sys.argv[0],'/dev/null']
+exec(open('gen.py').read().split('# layout')[0])
+def trex(tid,dur,size,flags): return full(b'trex',0,0,u32(tid)+u32(1)+u32(dur)+u32(size)+u32(flags))
+def empty_stbl(entry): return box(b'stbl', stsd(entry)+stts([])+stsc([])+stsz(0,[])+stco([]))
+vtrak=box(b'trak', tkhd(1,0,640,360)+box(b'mdia', mdhd(12800,0)+hdlr(b'vide',b'VideoHandler')+box(b'minf', full(b'vmhd',0,1,u16(0)*4)+dinf()+empty_stbl(avc1(640,360)))))
+atrak=box(b'trak', tkhd(2,0,0,0)+box(b'mdia', mdhd(44100,0)+hdlr(b'soun',b'SoundHandler')+box(b'minf', full(b'smhd',0,0,u16(0)*2)+dinf()+empty_stbl(mp4a()))))
+mvex=box(b'mvex', trex(1,512,0,0x10000)+trex(2,1024,7,0))
+ftyp=box(b'ftyp', b'iso6'+u32(0)+b'iso6cmfc')
+moov=box(b'moov', mvhd(1000,0)+vtrak+atrak+mvex)
+out=ftyp+moov
+seq=1
+vdts=0
+for frag in range(2):
+    vs=[20+frag,21,22]
+    vdata=b''.join(bytes([0x80+frag*3+i])*s for i,s in enumerate(vs))
+    adata=bytes([0xa0+frag])*14
+    def build(voff,aoff):
+        vtrun=full(b'trun',0,0x001|0x004|0x200|0x800, u32(3)+struct.pack('>i',voff)+u32(0x02000000)+b''.join(u32(s)+u32(c) for s,c in zip(vs,[512,0,0])))
+        vtraf=box(b'traf', full(b'tfhd',0,0x020000,u32(1))+full(b'tfdt',1,0,struct.pack('>Q',frag*1536))+vtrun)
+        atrun=full(b'trun',0,0x001,u32(2)+struct.pack('>i',aoff))
+        atraf=box(b'traf', full(b'tfhd',0,0x020000,u32(2))+full(b'tfdt',0,0,u32(frag*2048))+atrun)
+        return box(b'moof', full(b'mfhd',0,0,u32(seq))+vtraf+atraf)
+    m=build(0,0); L=len(m)
+    m=build(L+8, L+8+len(vdata))
+    out+=m+box(b'mdat', vdata+adata)
+    seq+=1
+open('fragmented.mp4','wb').write(out)
@@ -0,0 +1,53 @@
+# iTunes metadata in moov/udta/meta: metadata.mp4 with an ISO meta full box,
+# metadata_qt.mp4 with a QuickTime meta without version and flags,
+# metadata_faststart.mp4 as metadata.mp4 with moov and 32 bytes of free moved in front of mdat
+import struct
+def box(t, data): return struct.pack('>I', 8+len(data)) + t + data
+def full(t, v, f, data): return box(t, struct.pack('>I', (v<<24)|f) + data)
+def dat(tp, val): return box(b'data', struct.pack('>II', tp, 0) + val)
+hdlr = full(b'hdlr',0,0, b'\0\0\0\0' + b'mdir' + b'\0'*12 + b'\0')
+items = [
+  box(b'\xa9nam', dat(1, 'Title é'.encode())),
+  box(b'\xa9ART', dat(1, b'Artist')),
+  box(b'\xa9alb', dat(2, 'Alb'.encode('utf-16-be'))),
+  box(b'\xa9day', dat(1, b'2024')),
+  box(b'trkn', dat(0, struct.pack('>HHHH',0,3,12,0))),
+  box(b'disk', dat(0, struct.pack('>HHH',0,1,2))),
+  box(b'tmpo', dat(21, struct.pack('>h',-120))),
+  box(b'covr', dat(13, b'\xff\xd8\xff\xe0JPEG') + dat(14, b'\x89PNG')),
+  box(b'----', full(b'mean',0,0,b'com.apple.iTunes') + full(b'name',0,0,b'iTunNORM') + dat(1, b' 0000')),
+]
+ilst = box(b'ilst', b''.join(items))
+xyz = box(b'\xa9xyz', b'\0\x04\x15\xc7+1.0/')
+src = open('progressive.mp4','rb').read()
+for name, meta in [('metadata.mp4', full(b'meta',0,0, hdlr + ilst + box(b'free', b'\0'*16))),
+                   ('metadata_qt.mp4', box(b'meta', hdlr + ilst))]:
+    udta = box(b'udta', meta + xyz)
+    pos = 0; out = b''
+    while pos < len(src):
+        size, t = struct.unpack('>I4s', src[pos:pos+8])
+        b = src[pos:pos+size]
+        if t == b'moov':
+            b = struct.pack('>I', size + len(udta)) + b[4:] + udta
+        out += b; pos += size
+    open(name,'wb').write(out)
+
+# Add shift to the stco entries under moov
+CONTAINERS = {b'moov', b'trak', b'mdia', b'minf', b'stbl'}
+def shift_offsets(d, shift):
+    out = b''; pos = 0
+    while pos < len(d):
+        size, t = struct.unpack('>I4s', d[pos:pos+8]); body = d[pos+8:pos+size]
+        if t in CONTAINERS:
+            body = shift_offsets(body, shift)
+        elif t == b'stco':
+            count = struct.unpack('>I', body[4:8])[0]
+            offsets = struct.unpack('>%dI' % count, body[8:8+4*count])
+            body = body[:8] + b''.join(struct.pack('>I', o + shift) for o in offsets)
+        out += struct.pack('>I4s', size, t) + body; pos += size
+    return out
+src = open('metadata.mp4','rb').read()
+ftyp, mdat, moov = src[:32], src[32:143], src[143:]
+free = box(b'free', b'\0'*24)
+moov = shift_offsets(moov, len(moov) + len(free))
+open('metadata_faststart.mp4','wb').write(ftyp + moov + free + mdat)